@@ -7,6 +7,7 @@ use max_tree::prelude::*;
 type Map = Vec<Vec<u8>>;
 type Pos = [usize; 2];
 
+#[allow(clippy::toplevel_ref_arg)]
 fn main() {
     let ref mut map: Map = vec![
         vec![0, 0, 1],
@@ -17,14 +18,16 @@ fn main() {
 
     let max_depth = 4;
     let eps_depth = 0.00001;
-    let mut ai = Ai {
-        actions: actions,
-        execute: execute,
-        utility: utility,
-        undo: undo,
-        settings: AiSettings::new(max_depth, eps_depth),
-        analysis: AiAnalysis::new(),
-    };
+    let mut ai = Ai::builder()
+        .actions(actions)
+        .execute(execute)
+        .utility(utility)
+        .undo(undo)
+        .state_hash(state_hash)
+        .avoid_cycles(true)
+        .max_depth(max_depth)
+        .eps_depth(eps_depth)
+        .build();
     let mut root = Node::root(start);
     ai.full(&mut root, 0, map);
 
@@ -41,10 +44,15 @@ fn main() {
 
 fn undo(_: &Pos, _: &mut Map) {}
 
+fn state_hash(pos: &Pos, map: &Map) -> u64 {
+    (pos[1] * map[0].len() + pos[0]) as u64
+}
+
 fn utility(pos: &Pos, map: &Map) -> f64 {
     map[pos[1]][pos[0]] as f64
 }
 
+#[allow(clippy::absurd_extreme_comparisons)]
 fn execute(pos: &Pos, action: &Action, map: &mut Map) -> Result<[usize; 2], ()> {
     Ok(match *action {
         Action::Left => {
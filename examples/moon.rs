@@ -163,17 +163,16 @@ fn main() {
 
     let max_depth = 10;
     let eps_depth = 0.0;
-    let mut settings = AiSettings::new(max_depth, eps_depth);
-    settings.analysis = true;
-    settings.max_mib = Some(10.0);
-    let mut ai = Ai {
-        actions: actions_x,
-        execute: execute,
-        settings: settings,
-        undo: undo,
-        utility: utility2,
-        analysis: AiAnalysis::new(),
-    };
+    let mut ai = Ai::builder()
+        .actions(actions_x)
+        .execute(execute)
+        .utility(utility2)
+        .undo(undo)
+        .max_depth(max_depth)
+        .eps_depth(eps_depth)
+        .analysis(true)
+        .max_mib(10.0)
+        .build();
     let mut root = Node::root(space.spaceship.clone());
     ai.greedy(&mut root, 0, &mut space);
 
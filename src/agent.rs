@@ -0,0 +1,103 @@
+//! Agents that reuse the tree between actions.
+//!
+//! When an AI agent acts step by step, rebuilding the tree from scratch after every action
+//! wastes the computation of the subtree of the chosen child.
+//! An `Agent` keeps the tree between steps:
+//! After committing the optimal action, the chosen child is promoted to root,
+//! and the next search only expands nodes up to the horizon.
+//!
+//! Nodes keep the depth from the initial root, such that utility computed with settings
+//! of existing nodes stays consistent with new nodes, e.g. when discounting by depth.
+//! The maximum depth of each search is the number of steps taken plus the horizon.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::outcome::SearchOutcome;
+use crate::utility::Utility;
+
+/// An AI setup with a tree that is reused between actions.
+pub struct Agent<T, A, C, U = f64, E = ()> {
+    /// The AI setup.
+    pub ai: Ai<T, A, C, U, E>,
+    /// The tree of the current state.
+    pub root: Node<T, A, U>,
+    /// The number of actions committed to context, which is the depth of root.
+    pub depth: usize,
+    /// The number of steps to search ahead of root.
+    pub horizon: usize,
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Agent<T, A, C, U, E> {
+    /// Creates a new agent with node data of the current state.
+    pub fn new(ai: Ai<T, A, C, U, E>, data: T, horizon: usize) -> Agent<T, A, C, U, E> {
+        Agent {ai, root: Node::root(data), depth: 0, horizon}
+    }
+
+    /// Tops up the search of the tree to the horizon, reusing expanded nodes.
+    ///
+    /// `AiSettings::max_depth` is restored afterwards.
+    pub fn plan(&mut self, ctx: &mut C) -> SearchOutcome
+        where A: Clone
+    {
+        let started = self.ai.start_timer();
+        let max_depth = self.ai.settings.max_depth;
+        self.ai.settings.max_depth = self.depth + self.horizon;
+        self.ai.deepen(&mut self.root, self.depth, ctx);
+        let outcome = self.ai.finish(started);
+        self.ai.settings.max_depth = max_depth;
+        outcome
+    }
+
+    /// Commits the optimal action to context and promotes the chosen child to root.
+    ///
+    /// Returns `None` when the root is terminal,
+    /// or the action is not approved (see `Ai::approve`) or fails to execute.
+    pub fn act(&mut self, ctx: &mut C) -> Option<A>
+        where A: Clone
+    {
+        let i = self.root.optimal()?;
+        let data = self.ai.commit(&self.root.data, &self.root.children[i].0, ctx)?;
+        let (a, mut ch) = self.root.children.swap_remove(i);
+        // The committed node data describes the actual state of context.
+        ch.data = data;
+        let mut old = std::mem::replace(&mut self.root, ch);
+        let removed = old.clear();
+        if self.ai.settings.analysis {
+            // The promoted child is no longer counted, since it is root.
+            self.ai.analysis.node_count -= removed + 1;
+        }
+        self.depth += 1;
+        Some(a)
+    }
+
+    /// Plans and then acts, see `Agent::plan` and `Agent::act`.
+    pub fn step(&mut self, ctx: &mut C) -> Option<A>
+        where A: Clone
+    {
+        self.plan(ctx);
+        self.act(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn reuses_tree() {
+        let mut agent = Agent::new(line_ai(0), 0, 2);
+        let mut pos = 0;
+        assert_eq!(agent.plan(&mut pos).nodes_created, 14);
+        assert_eq!(agent.act(&mut pos), Some(1));
+        // Only the leaves of the promoted subtree are expanded.
+        assert_eq!(agent.plan(&mut pos).nodes_created, 8);
+        assert_eq!(agent.act(&mut pos), Some(1));
+        assert_eq!(agent.step(&mut pos), Some(1));
+        assert_eq!(pos, 3);
+        assert_eq!(agent.depth, 3);
+        // Staying at the maximum is better than moving away and back.
+        assert_eq!(agent.step(&mut pos), None);
+        assert_eq!(agent.ai.settings.max_depth, 0);
+    }
+}
@@ -0,0 +1,336 @@
+//! Arena-backed representation of maximum trees.
+//!
+//! A `NodeArena` stores all nodes in a flat list, where children are referenced by index.
+//! The children of a node are stored next to each other,
+//! such that they can be referenced by the index of the first child and the number of children.
+//! This reduces the number of allocations for trees with many small nodes,
+//! and improves cache locality when traversing children.
+//!
+//! The root is always stored at index `0`.
+//! Use `NodeArena::from_node` and `NodeArena::into_node` to convert between representations.
+//!
+//! `Ai::full_arena`, `Ai::greedy_arena` and `Ai::iterative_deepening_arena`
+//! run the same algorithms as the boxed versions, constructing children directly in the arena.
+//! Other algorithms can be used by converting to `Node` first.
+
+use std::ops::Range;
+
+use crate::{Ai, Full, Node};
+use crate::depth_first::Storage;
+use crate::execute_error::ExecuteError;
+use crate::outcome::SearchOutcome;
+use crate::utility::Utility;
+
+/// Stores a node in an arena.
+#[derive(Clone, Debug)]
+pub struct ArenaNode<T, A, U = f64> {
+    /// Stores maximum utility of itself or any children.
+    pub max: U,
+    /// Stores node data.
+    pub data: T,
+    /// The action leading to this node, `None` for root.
+    pub action: Option<A>,
+    /// The index of the first child.
+    pub first_child: usize,
+    /// The number of children.
+    pub len: usize,
+    /// Whether the node is partially expanded, see `Node::partial`.
+    pub partial: bool,
+}
+
+impl<T, A, U> ArenaNode<T, A, U> {
+    /// Returns the indices of child nodes.
+    pub fn children(&self) -> Range<usize> {self.first_child..self.first_child + self.len}
+}
+
+/// Stores a maximum tree in a flat list of nodes.
+#[derive(Clone, Debug)]
+pub struct NodeArena<T, A, U = f64> {
+    /// Stores nodes, with root at index `0`.
+    pub nodes: Vec<ArenaNode<T, A, U>>,
+}
+
+impl<T, A, U: Utility> NodeArena<T, A, U> {
+    /// Creates a new arena with a root.
+    ///
+    /// This sets the utility to unknown, like `Node::root`.
+    pub fn root(data: T) -> NodeArena<T, A, U> {
+        NodeArena {
+            nodes: vec![ArenaNode {
+                max: U::unknown(),
+                data,
+                action: None,
+                first_child: 0,
+                len: 0,
+                partial: false,
+            }]
+        }
+    }
+
+    /// Converts a boxed tree into an arena.
+    ///
+    /// Nodes are stored in breadth-first order.
+    pub fn from_node(root: Node<T, A, U>) -> NodeArena<T, A, U> {
+        use std::collections::VecDeque;
+
+        let mut nodes = vec![];
+        let mut queue = VecDeque::new();
+        queue.push_back((None, root));
+        while let Some((action, node)) = queue.pop_front() {
+            let i = nodes.len();
+            let Node {max, data, children, partial} = node;
+            nodes.push(ArenaNode {
+                max,
+                data,
+                action,
+                // Children are pushed after all nodes that are already in queue.
+                first_child: i + queue.len() + 1,
+                len: children.len(),
+                partial,
+            });
+            for (a, ch) in children {
+                queue.push_back((Some(a), ch));
+            }
+        }
+        NodeArena {nodes}
+    }
+
+    /// Converts the arena into a boxed tree.
+    ///
+    /// Nodes that are not reachable from root are dropped.
+    pub fn into_node(self) -> Node<T, A, U> {
+        let n = self.nodes.len();
+        let mut built: Vec<Option<Node<T, A, U>>> = (0..n).map(|_| None).collect();
+        let mut actions: Vec<Option<A>> = (0..n).map(|_| None).collect();
+        // Children are always stored after their parent,
+        // so the tree can be built in reverse order.
+        for (i, node) in self.nodes.into_iter().enumerate().rev() {
+            let children = node.children()
+                .map(|j| (actions[j].take().unwrap(), built[j].take().unwrap()))
+                .collect();
+            actions[i] = node.action;
+            built[i] = Some(Node {max: node.max, data: node.data, children, partial: node.partial});
+        }
+        built[0].take().unwrap()
+    }
+
+    /// Returns the number of nodes, including root.
+    pub fn len(&self) -> usize {self.nodes.len()}
+
+    /// Returns `true` if the arena has no nodes.
+    pub fn is_empty(&self) -> bool {self.nodes.is_empty()}
+
+    /// Returns the optimal child index of a node, if any.
+    ///
+    /// This has the same semantics as `Node::optimal`.
+    pub fn optimal(&self, node: usize) -> Option<usize> {
+        let max = self.nodes[node].max;
+        self.nodes[node].children().find(|&j| self.nodes[j].max >= max)
+    }
+
+    /// Returns the node indices of optimal path from root, excluding root.
+    pub fn optimal_path(&self) -> Vec<usize> {
+        let mut res = vec![];
+        let mut node = 0;
+        while let Some(j) = self.optimal(node) {
+            res.push(j);
+            node = j;
+        }
+        res
+    }
+}
+
+/// Stores the nodes of an arena.
+///
+/// New children are pushed at the end of the arena.
+/// When a node is expanded again, its old descendants are left unreachable,
+/// unless they are stored at the end of the arena.
+struct ArenaStorage<'a, T, A, U> {
+    arena: &'a mut NodeArena<T, A, U>,
+    /// The node indices on the current path, starting with root.
+    path: Vec<usize>,
+}
+
+impl<'a, T, A, U> ArenaStorage<'a, T, A, U> {
+    fn new(arena: &'a mut NodeArena<T, A, U>) -> ArenaStorage<'a, T, A, U> {
+        ArenaStorage {arena, path: vec![0]}
+    }
+
+    fn node(&self) -> &ArenaNode<T, A, U> {
+        &self.arena.nodes[*self.path.last().expect("There should be a current node")]
+    }
+
+    fn node_mut(&mut self) -> &mut ArenaNode<T, A, U> {
+        &mut self.arena.nodes[*self.path.last().expect("There should be a current node")]
+    }
+}
+
+impl<'a, T, A, U: Utility> Storage<T, A, U> for ArenaStorage<'a, T, A, U> {
+    fn data(&self) -> &T {&self.node().data}
+
+    fn max(&self) -> U {self.node().max}
+
+    fn set_max(&mut self, max: U) {self.node_mut().max = max}
+
+    fn partial(&self) -> bool {self.node().partial}
+
+    fn set_partial(&mut self, partial: bool) {self.node_mut().partial = partial}
+
+    fn child_count(&self) -> usize {self.node().len}
+
+    fn child(&self, i: usize) -> (&A, &T, U) {
+        let ch = &self.arena.nodes[self.node().first_child + i];
+        (ch.action.as_ref().expect("Child should have an action"), &ch.data, ch.max)
+    }
+
+    fn push(&mut self, a: A, data: T, max: U) {
+        let n = self.arena.nodes.len();
+        let node = self.node_mut();
+        if node.len == 0 {
+            node.first_child = n;
+        }
+        assert_eq!(node.first_child + node.len, n, "Children should be stored next to each other");
+        node.len += 1;
+        self.arena.nodes.push(ArenaNode {
+            max,
+            data,
+            action: Some(a),
+            first_child: 0,
+            len: 0,
+            partial: false,
+        });
+    }
+
+    fn clear(&mut self) -> usize {
+        let mut removed = 0;
+        let mut first = self.arena.nodes.len();
+        let mut stack = vec![self.node().children()];
+        while let Some(children) = stack.pop() {
+            removed += children.len();
+            first = first.min(children.start);
+            for j in children {
+                stack.push(self.arena.nodes[j].children());
+            }
+        }
+        self.node_mut().len = 0;
+        // Descendants are stored after the node, so they can be removed when they are last.
+        if first + removed == self.arena.nodes.len() {
+            self.arena.nodes.truncate(first);
+        }
+        removed
+    }
+
+    fn retain(&mut self, f: &mut dyn FnMut(usize, &A, &T, U) -> bool) {
+        let range = self.node().children();
+        let mut kept = 0;
+        let mut leaves = true;
+        for (i, j) in range.clone().enumerate() {
+            let ch = &self.arena.nodes[j];
+            if f(i, ch.action.as_ref().expect("Child should have an action"), &ch.data, ch.max) {
+                self.arena.nodes.swap(range.start + kept, j);
+                kept += 1;
+            } else {
+                leaves &= ch.len == 0;
+            }
+        }
+        self.node_mut().len = kept;
+        // Removed children without descendants can be dropped when they are last.
+        if leaves && range.end == self.arena.nodes.len() {
+            self.arena.nodes.truncate(range.start + kept);
+        }
+    }
+
+    fn open(&mut self) {}
+
+    fn action(&self, i: usize) -> (&T, &A) {(self.data(), self.child(i).0)}
+
+    fn descend(&mut self, i: usize) {
+        let j = self.node().first_child + i;
+        self.path.push(j);
+    }
+
+    fn ascend(&mut self) {self.path.pop();}
+
+    fn close(&mut self) {}
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Same as `Ai::full`, but constructs the tree in an arena.
+    pub fn full_arena(&mut self, arena: &mut NodeArena<T, A, U>, depth: usize, ctx: &mut C) -> SearchOutcome
+        where A: Clone
+    {
+        let started = self.start_timer();
+        self.depth_first(&mut ArenaStorage::new(arena), depth, ctx, &mut Full::new());
+        self.finish(started)
+    }
+
+    /// Same as `Ai::greedy`, but constructs the tree in an arena.
+    pub fn greedy_arena(&mut self, arena: &mut NodeArena<T, A, U>, depth: usize, ctx: &mut C) -> SearchOutcome
+        where A: Clone
+    {
+        let started = self.start_timer();
+        self.greedy_with(&mut ArenaStorage::new(arena), depth, ctx, &mut |node, _| node.optimal());
+        self.finish(started)
+    }
+
+    /// Same as `Ai::iterative_deepening`, but constructs the tree in an arena.
+    pub fn iterative_deepening_arena(&mut self, arena: &mut NodeArena<T, A, U>, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        self.iterative_deepening_in(&mut ArenaStorage::new(arena), depth, ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn full_matches_boxed_tree() {
+        let mut ai = line_ai(4);
+        let mut pos = 0;
+        let mut arena = NodeArena::root(0);
+        ai.full_arena(&mut arena, 0, &mut pos);
+        assert_eq!(pos, 0);
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(arena.len(), root.descendants() + 1);
+        assert_eq!(arena.nodes[0].max, root.max);
+        assert_eq!(arena.optimal_path().len(), root.optimal_path().len());
+
+        // Round trip through breadth-first order.
+        let arena = NodeArena::from_node(arena.into_node());
+        let node = arena.clone().into_node();
+        assert_eq!(node.descendants(), root.descendants());
+        assert_eq!(node.optimal_path(), root.optimal_path());
+        for (i, n) in arena.nodes.iter().enumerate() {
+            assert!(n.children().all(|j| j > i));
+        }
+    }
+
+    #[test]
+    fn greedy_and_deepening_match_boxed_tree() {
+        let mut ai = line_ai(4);
+        ai.settings.greed_elim = true;
+        let mut pos = 0;
+        let mut arena = NodeArena::root(0);
+        ai.greedy_arena(&mut arena, 0, &mut pos);
+        assert_eq!(pos, 0);
+        let mut root = Node::root(0);
+        ai.greedy(&mut root, 0, &mut pos);
+        // Eliminated children are removed from the end of the arena.
+        assert_eq!(arena.len(), root.descendants() + 1);
+        assert_eq!(arena.clone().into_node().optimal_path(), root.optimal_path());
+
+        let mut arena = NodeArena::root(0);
+        ai.iterative_deepening_arena(&mut arena, 0, &mut pos);
+        assert_eq!(pos, 0);
+        let mut root = Node::root(0);
+        ai.iterative_deepening(&mut root, 0, &mut pos);
+        // Children are only constructed once, so no nodes are left unreachable.
+        assert_eq!(arena.len(), root.descendants() + 1);
+        assert_eq!(arena.nodes[0].max, root.max);
+        assert_eq!(arena.into_node().optimal_path(), root.optimal_path());
+    }
+}
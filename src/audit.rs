@@ -0,0 +1,124 @@
+//! Audit log of actions committed to the context.
+//!
+//! Search executes many actions that are undone afterwards, which are only simulated.
+//! When `Ai::audit` is set, actions that are actually applied to the context,
+//! by `Ai::update` or `Ai::realtime`, are recorded as an `AuditEntry`
+//! with the wall-clock time and the utility of the resulting node data.
+//! Actions executed during search are never recorded.
+//!
+//! Every entry stores a hash of its content chained with the hash of the previous entry,
+//! such that changing, inserting or removing entries is detected by `AuditLog::tampered`.
+//! The hash is not cryptographic, so it detects accidental or naive changes,
+//! but not an adversary who recomputes the chain.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::utility::Utility;
+
+/// An action committed to the context.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditEntry<A, U = f64> {
+    /// The committed action.
+    pub action: A,
+    /// The utility of the resulting node data.
+    pub utility: U,
+    /// The wall-clock time since the Unix epoch.
+    pub time: Duration,
+    /// The hash of the entry chained with the hash of the previous entry.
+    pub hash: u64,
+}
+
+/// Records actions committed to the context.
+pub struct AuditLog<A, U = f64> {
+    /// The recorded entries, in order of execution.
+    pub entries: Vec<AuditEntry<A, U>>,
+    /// Clones actions, such that recording does not require `A: Clone` in every algorithm.
+    clone: fn(&A) -> A,
+    /// Hashes actions, such that recording does not require `A: Hash` in every algorithm.
+    hash: fn(&A) -> u64,
+}
+
+fn hash_action<A: Hash>(a: &A) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    a.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<A: Clone + Hash, U> AuditLog<A, U> {
+    /// Creates a new empty audit log.
+    pub fn new() -> AuditLog<A, U> {
+        AuditLog {
+            entries: vec![],
+            clone: A::clone,
+            hash: hash_action::<A>,
+        }
+    }
+}
+
+impl<A: Clone + Hash, U> Default for AuditLog<A, U> {
+    fn default() -> AuditLog<A, U> {AuditLog::new()}
+}
+
+impl<A, U: Utility> AuditLog<A, U> {
+    /// Computes the chained hash of an entry.
+    fn chain(&self, prev: u64, action: &A, utility: U, time: Duration) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        prev.hash(&mut hasher);
+        (self.hash)(action).hash(&mut hasher);
+        utility.to_f64().to_bits().hash(&mut hasher);
+        time.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Records that an action was committed to the context.
+    pub(crate) fn record(&mut self, a: &A, utility: U) {
+        let prev = self.entries.last().map(|e| e.hash).unwrap_or(0);
+        let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let hash = self.chain(prev, a, utility, time);
+        self.entries.push(AuditEntry {action: (self.clone)(a), utility, time, hash});
+    }
+
+    /// Returns the index of the first entry that does not match the hash chain.
+    ///
+    /// Returns `None` when no entry has been tampered with.
+    pub fn tampered(&self) -> Option<usize> {
+        let mut prev = 0;
+        for (i, e) in self.entries.iter().enumerate() {
+            if self.chain(prev, &e.action, e.utility, e.time) != e.hash {return Some(i)};
+            prev = e.hash;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn records_committed_actions() {
+        let mut ai = line_ai(2);
+        ai.audit = Some(AuditLog::new());
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        // Search is only simulated.
+        assert!(ai.audit.as_ref().unwrap().entries.is_empty());
+
+        let i = ai.update(&root, &mut pos).unwrap();
+        assert_eq!(pos, 1);
+        let audit = ai.audit.as_mut().unwrap();
+        assert_eq!(audit.entries.len(), 1);
+        assert_eq!(audit.entries[0].action, root.children[i].0);
+        assert_eq!(audit.entries[0].utility, -2.0);
+        assert_eq!(audit.tampered(), None);
+
+        audit.entries[0].action = -1;
+        assert_eq!(audit.tampered(), Some(0));
+    }
+}
@@ -0,0 +1,130 @@
+//! Best-first search guided by a heuristic.
+//!
+//! Nodes are expanded in order of utility plus heuristic,
+//! using a priority queue over the frontier of unexpanded nodes.
+//! The heuristic is set by `Ai::heuristic`.
+//! Without a heuristic, nodes are expanded in order of utility.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::{Ai, Collect, Node};
+use crate::depth_first::NodeStorage;
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// Stores a frontier node in the priority queue.
+pub(crate) struct Frontier<P = f64> {
+    pub priority: P,
+    /// The accumulated cost, used by `Ai::uniform_cost` and `Ai::weighted_astar`.
+    pub cost: f64,
+    pub depth: usize,
+    pub path: Vec<usize>,
+}
+
+impl<P: PartialOrd> PartialEq for Frontier<P> {
+    fn eq(&self, other: &Frontier<P>) -> bool {self.cmp(other) == Ordering::Equal}
+}
+
+impl<P: PartialOrd> Eq for Frontier<P> {}
+
+impl<P: PartialOrd> PartialOrd for Frontier<P> {
+    fn partial_cmp(&self, other: &Frontier<P>) -> Option<Ordering> {Some(self.cmp(other))}
+}
+
+impl<P: PartialOrd> Ord for Frontier<P> {
+    fn cmp(&self, other: &Frontier<P>) -> Ordering {
+        self.priority.partial_cmp(&other.priority).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Computes priority of a node in best-first search.
+    ///
+    /// The heuristic is added to utility as a negative penalty, see `Utility::penalize`.
+    fn priority(&self, utility: U, data: &T, ctx: &C) -> U {
+        if let Some(heuristic) = &self.heuristic {
+            utility.penalize(-heuristic(data, ctx))
+        } else {
+            utility
+        }
+    }
+
+    /// Expands nodes in order of utility plus heuristic.
+    ///
+    /// Stops after the specified number of expansions,
+    /// when there are no more nodes to expand,
+    /// or when a limit of memory or time is exceeded.
+    /// Nodes deeper than `AiSettings::max_depth` are not expanded.
+    ///
+    /// The context is restored for every expanded node by replaying actions from root.
+    pub fn best_first(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C, expansions: usize)
+        where A: Clone
+    {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let started = self.start_timer();
+        let mut queue = BinaryHeap::new();
+        queue.push(Frontier {
+            priority: self.priority(root.max, &root.data, ctx),
+            cost: 0.0,
+            depth,
+            path: vec![],
+        });
+        for _ in 0..expansions {
+            let f = match queue.pop() {
+                Some(f) => f,
+                None => break,
+            };
+
+            let n = self.replay(root, &f.path, ctx);
+            if n == f.path.len() {
+                let node = root.node_mut(&f.path);
+                let mut priorities = Collect::new(|ai: &Self, data: &T, ctx: &C| {
+                    ai.heuristic.as_ref().map(|h| h(data, ctx)).unwrap_or(0.0)
+                });
+                self.sub_breadth_with(&mut NodeStorage::new(node), f.depth, ctx, &mut priorities);
+                if f.depth < self.settings.max_depth {
+                    for (i, (ch, h)) in node.children.iter().zip(priorities.values).enumerate() {
+                        let mut path = f.path.clone();
+                        path.push(i);
+                        queue.push(Frontier {
+                            priority: ch.1.max.penalize(-h),
+                            cost: 0.0,
+                            depth: f.depth + 1,
+                            path,
+                        });
+                    }
+                }
+            }
+            self.rollback(root, &f.path[..n], ctx);
+
+            if self.limit_exceeded() {break};
+        }
+
+        // Update maximum utility since children are changed.
+        root.update_max();
+        self.stop_timer(started);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn heuristic_guides_search() {
+        let mut ai = line_ai(10);
+        ai.heuristic = Some(Box::new(|_, pos| if *pos > 0 {1.0} else {0.0}));
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.best_first(&mut root, 0, &mut pos, 4);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.analysis.node_count, 8);
+        assert_eq!(root.optimal_path(), vec![1, 1, 1]);
+    }
+}
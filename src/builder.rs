@@ -0,0 +1,427 @@
+//! Builder for constructing AI setups.
+//!
+//! Constructing `Ai` directly requires filling every field.
+//! With a builder, only utility, actions, execute and undo are required,
+//! while other fields use defaults.
+//!
+//! ```
+//! use max_tree::prelude::*;
+//!
+//! let mut ai: Ai<i32, i32, i32> = Ai::builder()
+//!     .utility(|_, pos: &i32| -(*pos - 3).abs() as f64)
+//!     .actions(|_, _| vec![-1, 1])
+//!     .execute(|_, a, pos| {*pos += a; Ok(*a)})
+//!     .undo(|a, pos| *pos -= a)
+//!     .max_depth(3)
+//!     .build();
+//! let mut root = Node::root(0);
+//! let mut pos = 0;
+//! ai.full(&mut root, 0, &mut pos);
+//! assert_eq!(root.optimal_path().len(), 3);
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::{
+    ActionTestFn,
+    ActionsFn,
+    Ai,
+    AiAnalysis,
+    AiSettings,
+    BoundFn,
+    CostFn,
+    ExecuteFn,
+    GoalFn,
+    HeuristicFn,
+    NodeKindFn,
+    OrderFn,
+    PlayerFn,
+    SizeFn,
+    StateHashFn,
+    UndoFn,
+    UtilityFn,
+};
+use crate::expectimax::{NodeKind, Risk};
+use crate::hooks::{Hooks, Progress};
+use crate::minimax::Player;
+use crate::schedule::DepthSettings;
+use crate::trace::Trace;
+use crate::audit::AuditLog;
+use crate::undo_check::UndoCheck;
+use crate::undo_strategy::UndoStrategy;
+use crate::value::ValueEstimator;
+
+/// Builds an AI setup.
+pub struct AiBuilder<T, A, C, U = f64, E = ()> {
+    utility: Option<UtilityFn<T, C, U>>,
+    actions: Option<ActionsFn<T, A, C>>,
+    execute: Option<ExecuteFn<T, A, C, E>>,
+    undo: Option<UndoFn<T, C>>,
+    heuristic: Option<HeuristicFn<T, C>>,
+    bound: Option<BoundFn<T, C, U>>,
+    player: Option<PlayerFn<T, C>>,
+    node_kind: Option<NodeKindFn<T, A, C>>,
+    state_hash: Option<StateHashFn<T, C>>,
+    cost: Option<CostFn<T, A, C>>,
+    order: Option<OrderFn<T, A, C>>,
+    is_goal: Option<GoalFn<T, C>>,
+    forbid: Option<ActionTestFn<T, A, C>>,
+    approve: Option<ActionTestFn<T, A, C>>,
+    impact: Option<HeuristicFn<T, C>>,
+    value_estimator: Option<Box<dyn ValueEstimator<T, C, U> + Send + Sync>>,
+    data_size: Option<SizeFn<T>>,
+    action_size: Option<SizeFn<A>>,
+    trace: Option<Trace<A, U>>,
+    audit: Option<AuditLog<A, U>>,
+    undo_check: Option<UndoCheck<A, C>>,
+    undo_strategy: UndoStrategy<C>,
+    settings: AiSettings,
+    hooks: Hooks<T, U>,
+}
+
+impl<T, A, C, U, E> Default for AiBuilder<T, A, C, U, E> {
+    fn default() -> AiBuilder<T, A, C, U, E> {AiBuilder::new()}
+}
+
+impl<T, A, C, U, E> AiBuilder<T, A, C, U, E> {
+    /// Creates a new builder.
+    ///
+    /// The default settings have maximum depth `1` and no depth discount.
+    pub fn new() -> AiBuilder<T, A, C, U, E> {
+        AiBuilder {
+            utility: None,
+            actions: None,
+            execute: None,
+            undo: None,
+            heuristic: None,
+            bound: None,
+            player: None,
+            node_kind: None,
+            state_hash: None,
+            cost: None,
+            order: None,
+            is_goal: None,
+            forbid: None,
+            approve: None,
+            impact: None,
+            value_estimator: None,
+            data_size: None,
+            action_size: None,
+            trace: None,
+            audit: None,
+            undo_check: None,
+            undo_strategy: UndoStrategy::Delta,
+            settings: AiSettings::new(1, 0.0),
+            hooks: Hooks::new(),
+        }
+    }
+
+    /// Sets utility (required).
+    pub fn utility(mut self, f: impl Fn(&T, &C) -> U + Send + Sync + 'static) -> Self {
+        self.utility = Some(Box::new(f));
+        self
+    }
+
+    /// Sets actions (required).
+    pub fn actions(mut self, f: impl Fn(&T, &C) -> Vec<A> + Send + Sync + 'static) -> Self {
+        self.actions = Some(Box::new(f));
+        self
+    }
+
+    /// Sets execute (required).
+    pub fn execute(mut self, f: impl Fn(&T, &A, &mut C) -> Result<T, E> + Send + Sync + 'static) -> Self {
+        self.execute = Some(Box::new(f));
+        self
+    }
+
+    /// Sets undo (required, unless using `UndoStrategy::Clone`).
+    pub fn undo(mut self, f: impl Fn(&T, &mut C) + Send + Sync + 'static) -> Self {
+        self.undo = Some(Box::new(f));
+        self
+    }
+
+    /// Sets heuristic, see `Ai::heuristic`.
+    pub fn heuristic(mut self, f: impl Fn(&T, &C) -> f64 + Send + Sync + 'static) -> Self {
+        self.heuristic = Some(Box::new(f));
+        self
+    }
+
+    /// Sets upper bound, see `Ai::bound`.
+    pub fn bound(mut self, f: impl Fn(&T, &C, usize) -> U + Send + Sync + 'static) -> Self {
+        self.bound = Some(Box::new(f));
+        self
+    }
+
+    /// Sets player, see `Ai::player`.
+    pub fn player(mut self, f: impl Fn(&T, &C) -> Player + Send + Sync + 'static) -> Self {
+        self.player = Some(Box::new(f));
+        self
+    }
+
+    /// Sets node kind, see `Ai::node_kind`.
+    pub fn node_kind(mut self, f: impl Fn(&T, &C) -> NodeKind<A> + Send + Sync + 'static) -> Self {
+        self.node_kind = Some(Box::new(f));
+        self
+    }
+
+    /// Sets state hash, see `Ai::state_hash`.
+    pub fn state_hash(mut self, f: impl Fn(&T, &C) -> u64 + Send + Sync + 'static) -> Self {
+        self.state_hash = Some(Box::new(f));
+        self
+    }
+
+    /// Sets cost, see `Ai::cost`.
+    pub fn cost(mut self, f: impl Fn(&T, &A, &C) -> f64 + Send + Sync + 'static) -> Self {
+        self.cost = Some(Box::new(f));
+        self
+    }
+
+    /// Sets action ordering, see `Ai::order`.
+    pub fn order(mut self, f: impl Fn(&T, &C, &A) -> f64 + Send + Sync + 'static) -> Self {
+        self.order = Some(Box::new(f));
+        self
+    }
+
+    /// Sets terminal state predicate, see `Ai::is_goal`.
+    pub fn is_goal(mut self, f: impl Fn(&T, &C) -> bool + Send + Sync + 'static) -> Self {
+        self.is_goal = Some(Box::new(f));
+        self
+    }
+
+    /// Sets forbidden actions, see `Ai::forbid`.
+    pub fn forbid(mut self, f: impl Fn(&T, &A, &C) -> bool + Send + Sync + 'static) -> Self {
+        self.forbid = Some(Box::new(f));
+        self
+    }
+
+    /// Sets approval of actions committed to context, see `Ai::approve`.
+    pub fn approve(mut self, f: impl Fn(&T, &A, &C) -> bool + Send + Sync + 'static) -> Self {
+        self.approve = Some(Box::new(f));
+        self
+    }
+
+    /// Sets impact subtracted from utility, see `Ai::impact`.
+    pub fn impact(mut self, f: impl Fn(&T, &C) -> f64 + Send + Sync + 'static) -> Self {
+        self.impact = Some(Box::new(f));
+        self
+    }
+
+    /// Sets value estimator of leaves, see `Ai::value_estimator`.
+    pub fn value_estimator(mut self, estimator: impl ValueEstimator<T, C, U> + Send + Sync + 'static) -> Self {
+        self.value_estimator = Some(Box::new(estimator));
+        self
+    }
+
+    /// Sets heap memory of node data, see `Ai::data_size`.
+    pub fn data_size(mut self, f: impl Fn(&T) -> usize + Send + Sync + 'static) -> Self {
+        self.data_size = Some(Box::new(f));
+        self
+    }
+
+    /// Sets heap memory of actions, see `Ai::action_size`.
+    pub fn action_size(mut self, f: impl Fn(&A) -> usize + Send + Sync + 'static) -> Self {
+        self.action_size = Some(Box::new(f));
+        self
+    }
+
+    /// Sets trace recording executed actions, see `Ai::trace`.
+    pub fn trace(mut self, trace: Trace<A, U>) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Sets audit log recording actions committed to context, see `Ai::audit`.
+    pub fn audit(mut self, audit: AuditLog<A, U>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Sets check that undo restores the context, see `Ai::undo_check`.
+    pub fn undo_check(mut self, check: UndoCheck<A, C>) -> Self {
+        self.undo_check = Some(check);
+        self
+    }
+
+    /// Sets how to restore the context after executing actions, see `Ai::undo_strategy`.
+    pub fn undo_strategy(mut self, strategy: UndoStrategy<C>) -> Self {
+        self.undo_strategy = strategy;
+        self
+    }
+
+    /// Sets callback fired when a node is expanded, see `Hooks::on_expand`.
+    pub fn on_expand(mut self, f: impl Fn(&T, usize, usize) + Send + Sync + 'static) -> Self {
+        self.hooks.on_expand = Some(Box::new(f));
+        self
+    }
+
+    /// Sets callback fired when best utility improves, see `Hooks::on_improve`.
+    pub fn on_improve(mut self, f: impl Fn(&T, U, usize) + Send + Sync + 'static) -> Self {
+        self.hooks.on_improve = Some(Box::new(f));
+        self
+    }
+
+    /// Sets callback fired when children are discarded, see `Hooks::on_prune`.
+    pub fn on_prune(mut self, f: impl Fn(&T, usize) + Send + Sync + 'static) -> Self {
+        self.hooks.on_prune = Some(Box::new(f));
+        self
+    }
+
+    /// Sets callback fired periodically with a progress report, see `Hooks::on_progress`.
+    pub fn on_progress(
+        mut self,
+        interval: Duration,
+        f: impl Fn(&Progress<U>) + Send + Sync + 'static
+    ) -> Self {
+        self.hooks.progress_interval = interval;
+        self.hooks.on_progress = Some(Box::new(f));
+        self
+    }
+
+    /// Sets all settings.
+    pub fn settings(mut self, settings: AiSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets maximum depth.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.settings.max_depth = max_depth;
+        self
+    }
+
+    /// Sets depth discount, see `AiSettings::eps_depth`.
+    pub fn eps_depth(mut self, eps_depth: f64) -> Self {
+        self.settings.eps_depth = eps_depth;
+        self
+    }
+
+    /// Sets range of utility, see `AiSettings::utility_clamp`.
+    pub fn utility_clamp(mut self, min: f64, max: f64) -> Self {
+        self.settings.utility_clamp = Some((min, max));
+        self
+    }
+
+    /// Sets scale of impact subtracted from utility, see `AiSettings::impact_weight`.
+    pub fn impact_weight(mut self, weight: f64) -> Self {
+        self.settings.impact_weight = weight;
+        self
+    }
+
+    /// Sets weight of heuristic in weighted A*, see `AiSettings::astar_weight`.
+    pub fn astar_weight(mut self, weight: f64) -> Self {
+        self.settings.astar_weight = weight;
+        self
+    }
+
+    /// Sets aggregation of outcomes at chance nodes, see `AiSettings::risk`.
+    pub fn risk(mut self, risk: Risk) -> Self {
+        self.settings.risk = risk;
+        self
+    }
+
+    /// Sets utility that is good enough, see `AiSettings::target_utility`.
+    pub fn target_utility(mut self, target: f64) -> Self {
+        self.settings.target_utility = Some(target);
+        self
+    }
+
+    /// Sets minimum improvement for greedy search, see `AiSettings::min_gain`.
+    pub fn min_gain(mut self, min_gain: f64) -> Self {
+        self.settings.min_gain = min_gain;
+        self
+    }
+
+    /// Sets whether to run analysis.
+    pub fn analysis(mut self, analysis: bool) -> Self {
+        self.settings.analysis = analysis;
+        self
+    }
+
+    /// Sets whether to avoid cycles, see `AiSettings::avoid_cycles`.
+    pub fn avoid_cycles(mut self, avoid_cycles: bool) -> Self {
+        self.settings.avoid_cycles = avoid_cycles;
+        self
+    }
+
+    /// Sets memory limit in MiB, see `AiSettings::max_mib`.
+    pub fn max_mib(mut self, max_mib: f64) -> Self {
+        self.settings.max_mib = Some(max_mib);
+        self
+    }
+
+    /// Sets time limit, see `AiSettings::max_time`.
+    pub fn max_time(mut self, max_time: Duration) -> Self {
+        self.settings.max_time = Some(max_time);
+        self
+    }
+
+    /// Sets limit to number of created nodes, see `AiSettings::max_nodes`.
+    pub fn max_nodes(mut self, max_nodes: usize) -> Self {
+        self.settings.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /// Sets limit to number of children of each node, see `AiSettings::max_children`.
+    pub fn max_children(mut self, max_children: usize) -> Self {
+        self.settings.max_children = Some(max_children);
+        self
+    }
+
+    /// Sets depth-dependent settings, see `AiSettings::schedule`.
+    pub fn schedule(mut self, f: impl Fn(usize) -> DepthSettings + Send + Sync + 'static) -> Self {
+        self.settings.schedule = Some(Box::new(f));
+        self
+    }
+
+    /// Sets cancellation flag, see `AiSettings::cancel`.
+    pub fn cancel(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.settings.cancel = Some(cancel);
+        self
+    }
+
+    /// Builds the AI setup.
+    ///
+    /// Panics if utility, actions, execute or undo is not set.
+    /// Undo is not required with `UndoStrategy::Clone`.
+    pub fn build(self) -> Ai<T, A, C, U, E> {
+        let undo = match (self.undo, &self.undo_strategy) {
+            (Some(undo), _) => undo,
+            (None, UndoStrategy::Clone(_)) => Box::new(|_: &T, _: &mut C| {}),
+            (None, UndoStrategy::Delta) => panic!("undo is required"),
+        };
+        Ai {
+            utility: self.utility.expect("utility is required"),
+            actions: self.actions.expect("actions is required"),
+            execute: self.execute.expect("execute is required"),
+            undo,
+            heuristic: self.heuristic,
+            bound: self.bound,
+            player: self.player,
+            node_kind: self.node_kind,
+            state_hash: self.state_hash,
+            cost: self.cost,
+            order: self.order,
+            is_goal: self.is_goal,
+            forbid: self.forbid,
+            approve: self.approve,
+            impact: self.impact,
+            value_estimator: self.value_estimator,
+            data_size: self.data_size,
+            action_size: self.action_size,
+            trace: self.trace,
+            audit: self.audit,
+            undo_check: self.undo_check,
+            undo_strategy: self.undo_strategy,
+            settings: self.settings,
+            analysis: AiAnalysis::new(),
+            hooks: self.hooks,
+        }
+    }
+}
+
+impl<T, A, C, U, E> Ai<T, A, C, U, E> {
+    /// Returns a builder for constructing an AI setup.
+    pub fn builder() -> AiBuilder<T, A, C, U, E> {AiBuilder::new()}
+}
@@ -0,0 +1,137 @@
+//! Checkpoints for resuming search after a process restart.
+//!
+//! A checkpoint stores the partially constructed tree,
+//! together with the settings and analysis counters needed to resume search.
+//! With the `serde` feature, a checkpoint implements `Serialize` and `Deserialize`,
+//! such that it can be written to disk using any format supported by serde.
+//!
+//! The frontier of `Ai::full` and `Ai::iterative_deepening` is implicit in the tree:
+//! Nodes that are not expanded or only partially expanded (see `Node::partial`)
+//! are expanded when resuming,
+//! while children of nodes that are already expanded are reused.
+
+use std::fmt;
+
+use crate::{Ai, AiSettings, Node};
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// Stores search state for resuming later.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Checkpoint<T, A, U = f64> {
+    /// The partially constructed tree.
+    ///
+    /// Nodes whose expansion was interrupted are marked by `Node::partial`.
+    pub root: Node<T, A, U>,
+    /// The depth of root.
+    pub depth: usize,
+    /// The settings of search.
+    ///
+    /// `AiSettings::cancel` and `AiSettings::schedule` are not stored,
+    /// since they are only meaningful in the current process.
+    pub settings: AiSettings,
+    /// The number of nodes, see `AiAnalysis::node_count`.
+    pub node_count: usize,
+}
+
+impl<T: fmt::Debug, A: fmt::Debug, U: fmt::Debug> fmt::Debug for Checkpoint<T, A, U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Checkpoint")
+            .field("root", &self.root)
+            .field("depth", &self.depth)
+            .field("node_count", &self.node_count)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Creates a checkpoint of a tree, using the current settings and analysis.
+    pub fn checkpoint(&self, root: Node<T, A, U>, depth: usize) -> Checkpoint<T, A, U> {
+        Checkpoint {
+            root,
+            depth,
+            settings: AiSettings {cancel: None, schedule: None, ..self.settings},
+            node_count: self.analysis.node_count,
+        }
+    }
+
+    /// Resumes a full search from a checkpoint, returning the tree.
+    ///
+    /// The settings and analysis counters are restored from the checkpoint,
+    /// keeping the current `AiSettings::cancel` and `AiSettings::schedule`.
+    /// Partially expanded nodes are expanded again.
+    /// The context must be in the state of the root.
+    pub fn resume(&mut self, checkpoint: Checkpoint<T, A, U>, ctx: &mut C) -> Node<T, A, U>
+        where A: Clone
+    {
+        let Checkpoint {mut root, depth, settings, node_count} = checkpoint;
+        let cancel = self.settings.cancel.take();
+        let schedule = self.settings.schedule.take();
+        self.settings = AiSettings {cancel, schedule, ..settings};
+        self.analysis.node_count = node_count;
+        let started = self.start_timer();
+        self.deepen(&mut root, depth, ctx);
+        self.stop_timer(started);
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn resume_after_restart() {
+        let mut ai = line_ai(6);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let node_count = ai.analysis.node_count;
+
+        // Interrupt search by a time limit.
+        let mut ai = line_ai(6);
+        ai.settings.analysis = true;
+        ai.settings.max_time = Some(Duration::from_secs(0));
+        let mut partial = Node::root(0);
+        ai.full(&mut partial, 0, &mut pos);
+        let mut checkpoint = ai.checkpoint(partial, 0);
+        assert!(checkpoint.node_count < node_count);
+        assert_eq!(checkpoint.settings.max_time, Some(Duration::from_secs(0)));
+
+        // Resume in a new process without a time limit.
+        checkpoint.settings.max_time = None;
+        let mut ai = line_ai(1);
+        let resumed = ai.resume(checkpoint, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.settings.max_depth, 6);
+        assert!(ai.settings.analysis);
+        assert_eq!(ai.analysis.node_count, node_count);
+        assert_eq!(resumed.max, root.max);
+        assert_eq!(resumed.optimal_path(), root.optimal_path());
+    }
+
+    #[test]
+    fn resume_partial_expansion() {
+        let mut ai = line_ai(2);
+        ai.settings.analysis = true;
+        ai.settings.max_nodes = Some(3);
+        let mut partial = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut partial, 0, &mut pos);
+        let checkpoint = ai.checkpoint(partial, 0);
+        assert!(checkpoint.root.iter_preorder().any(|(_, node)| node.partial));
+        assert_eq!(checkpoint.settings.max_nodes, Some(3));
+
+        let mut ai = line_ai(0);
+        let mut checkpoint = checkpoint;
+        checkpoint.settings.max_nodes = None;
+        let resumed = ai.resume(checkpoint, &mut pos);
+        assert_eq!(pos, 0);
+        assert!(resumed.iter_preorder().all(|(_, node)| !node.partial));
+        assert_eq!(ai.analysis.node_count, 14);
+    }
+}
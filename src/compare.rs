@@ -0,0 +1,117 @@
+//! Comparison of algorithms over repeated trials.
+//!
+//! The algorithms are configured as a `Portfolio`,
+//! and every trial runs the whole portfolio on a clone of the same initial context.
+//! Results are summarized per strategy, such that algorithms can be compared side by side.
+//! Repeated trials are useful for stochastic strategies and for measuring time.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::Ai;
+use crate::Node;
+use crate::execute_error::ExecuteError;
+use crate::portfolio::Portfolio;
+use crate::utility::Utility;
+
+/// Summarizes results of a strategy over all trials.
+#[derive(Clone, Debug)]
+pub struct Comparison<U = f64> {
+    /// The name of strategy.
+    pub name: String,
+    /// The highest maximum utility at root over all trials.
+    pub best: U,
+    /// The mean of maximum utility at root, see `Utility::to_f64`.
+    pub mean: f64,
+    /// The mean number of nodes created, see `AiAnalysis::nodes_created`.
+    pub nodes_created: f64,
+    /// The mean time spent on search.
+    pub elapsed: Duration,
+    /// The number of trials.
+    pub trials: usize,
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Runs every strategy in portfolio for a number of trials, summarizing the results.
+    ///
+    /// The root factory is called for every run to construct a new root.
+    /// The analysis of AI is not changed.
+    pub fn compare(
+        &mut self,
+        portfolio: &Portfolio<T, A, C, U, E>,
+        mut root_factory: impl FnMut() -> Node<T, A, U>,
+        depth: usize,
+        ctx: &C,
+        trials: usize,
+    ) -> Vec<Comparison<U>>
+        where A: Clone, C: Clone
+    {
+        let mut res: Vec<Comparison<U>> = portfolio.entries.iter().map(|entry| Comparison {
+            name: entry.name.clone(),
+            best: U::unknown(),
+            mean: 0.0,
+            nodes_created: 0.0,
+            elapsed: Duration::from_secs(0),
+            trials,
+        }).collect();
+        if trials == 0 {return res};
+
+        let analysis = std::mem::take(&mut self.analysis);
+        for _ in 0..trials {
+            let (_, reports) = self.portfolio(portfolio, &mut root_factory, depth, ctx);
+            for (cmp, report) in res.iter_mut().zip(reports) {
+                if cmp.best.is_unknown() || report.max > cmp.best {cmp.best = report.max};
+                cmp.mean += report.max.to_f64();
+                cmp.nodes_created += report.analysis.nodes_created as f64;
+                cmp.elapsed += report.elapsed;
+            }
+        }
+        self.analysis = analysis;
+
+        for cmp in &mut res {
+            cmp.mean /= trials as f64;
+            cmp.nodes_created /= trials as f64;
+            cmp.elapsed /= trials as u32;
+        }
+        res
+    }
+}
+
+/// Formats comparisons as a text table, one line per strategy.
+pub fn table<U: Utility>(comparisons: &[Comparison<U>]) -> String {
+    let width = comparisons.iter().map(|c| c.name.len()).max().unwrap_or(0).max(8);
+    let mut s = String::new();
+    let _ = writeln!(s, "{:width$} {:>12} {:>12} {:>12} {:>12}",
+        "strategy", "best", "mean", "nodes", "ms", width = width);
+    for c in comparisons {
+        let _ = writeln!(s, "{:width$} {:>12.4} {:>12.4} {:>12.1} {:>12.3}",
+            c.name, c.best.to_f64(), c.mean, c.nodes_created, c.elapsed.as_secs_f64() * 1000.0, width = width);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolio::Strategy;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn compares_strategies() {
+        let mut ai = line_ai(2);
+        let mut portfolio = Portfolio::new();
+        portfolio
+            .add("greedy", Strategy::Greedy)
+            .add("full", Strategy::Full);
+        let pos = 0;
+        let res = ai.compare(&portfolio, || Node::root(0), 0, &pos, 3);
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[1].trials, 3);
+        assert_eq!(res[1].best, res[1].mean);
+        assert!(res[1].nodes_created > res[0].nodes_created);
+        assert_eq!(ai.analysis.node_count, 0);
+        let table = table(&res);
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.lines().nth(2).unwrap().starts_with("full"));
+    }
+}
@@ -0,0 +1,185 @@
+//! Cross-entropy method for optimizing sequences of actions.
+//!
+//! A population of action sequences is sampled from a distribution.
+//! The distribution is refitted to the elite set,
+//! which consists of the sequences with highest score.
+//! This is repeated for a number of iterations.
+//!
+//! The distribution stores a categorical distribution per step,
+//! over the indices of actions returned by `Ai::actions`.
+//! This works best when the list of actions has the same order in every state.
+//!
+//! The score of a sequence is the maximum utility along the path,
+//! which is the same semantics as for a maximum tree.
+//! The best sequence found is recorded as a path in the maximum tree.
+//!
+//! Scores are only compared to select the elite set,
+//! while the distribution is refitted from counts of actions,
+//! so any utility type can be used.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::rng::Rng;
+use crate::utility::Utility;
+
+/// Cross-entropy method settings.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrossEntropySettings {
+    /// The number of sequences sampled per iteration.
+    pub samples: usize,
+    /// The number of sequences with highest score used to refit distribution.
+    pub elite: usize,
+    /// The number of iterations.
+    pub iterations: usize,
+    /// How much the refitted distribution replaces the old one, between `0` and `1`.
+    pub smoothing: f64,
+}
+
+impl CrossEntropySettings {
+    /// Creates new settings.
+    pub fn new(samples: usize, elite: usize, iterations: usize) -> CrossEntropySettings {
+        CrossEntropySettings {
+            samples,
+            elite,
+            iterations,
+            smoothing: 0.7,
+        }
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Searches for the best sequence of actions using the cross-entropy method.
+    ///
+    /// The length of sequences is limited by `AiSettings::max_depth`.
+    /// The best sequence found is inserted as a path from root.
+    /// When a limit of memory or time is exceeded, the search terminates early.
+    pub fn cross_entropy(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        settings: &CrossEntropySettings,
+        rng: &mut Rng,
+    )
+        where A: Clone + PartialEq
+    {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let started = self.start_timer();
+        let mut weights: Vec<Vec<f64>> = vec![];
+        let mut best: (Option<U>, Vec<A>) = (None, vec![]);
+        for _ in 0..settings.iterations {
+            if self.limit_exceeded() {break};
+
+            let mut population = Vec::with_capacity(settings.samples);
+            for _ in 0..settings.samples {
+                let (score, indices, seq) = self.cem_sample(&root.data, depth, ctx, &mut weights, rng);
+                if score > best.0 {
+                    best = (score, seq);
+                }
+                population.push((score, indices));
+            }
+
+            population.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            population.truncate(settings.elite);
+            for (t, w) in weights.iter_mut().enumerate() {
+                let mut counts = vec![0.0; w.len()];
+                let mut total = 0.0;
+                for (_, indices) in &population {
+                    if let Some(&i) = indices.get(t) {
+                        counts[i] += 1.0;
+                        total += 1.0;
+                    }
+                }
+                if total == 0.0 {continue};
+
+                let sum: f64 = w.iter().sum();
+                for (w, count) in w.iter_mut().zip(counts) {
+                    let old = if sum > 0.0 {*w / sum} else {0.0};
+                    *w = settings.smoothing * count / total + (1.0 - settings.smoothing) * old;
+                }
+            }
+        }
+
+        self.insert_actions(root, depth, &best.1, ctx);
+        self.stop_timer(started);
+    }
+
+    /// Samples a sequence of actions from distribution.
+    ///
+    /// Returns the score, the indices of actions and the sequence of actions.
+    /// The score is `None` when no action is executed.
+    fn cem_sample(
+        &mut self,
+        data: &T,
+        depth: usize,
+        ctx: &mut C,
+        weights: &mut Vec<Vec<f64>>,
+        rng: &mut Rng,
+    ) -> (Option<U>, Vec<usize>, Vec<A>) {
+        let horizon = self.settings.max_depth.saturating_sub(depth);
+        let mut score: Option<U> = None;
+        let mut indices = vec![];
+        let mut seq = vec![];
+        let mut stack: Vec<T> = vec![];
+        while stack.len() < horizon {
+            let t = stack.len();
+            let cur = stack.last().unwrap_or(data);
+            let mut actions: Vec<Option<A>> = (self.actions)(cur, ctx).into_iter().map(Some).collect();
+            if actions.is_empty() {break};
+
+            if weights.len() <= t {weights.push(vec![])};
+            let w = &mut weights[t];
+            while w.len() < actions.len() {w.push(1.0 / actions.len() as f64)};
+
+            // Sample actions, removing those that fail to execute.
+            let mut w: Vec<f64> = w[..actions.len()].to_vec();
+            let mut next = None;
+            while w.iter().any(|&x| x > 0.0) {
+                let i = rng.weighted(&w);
+                let a = actions[i].take().unwrap();
+                if let Some(new_data) = self.try_execute(cur, &a, ctx) {
+                    next = Some((i, a, new_data));
+                    break;
+                }
+                w[i] = 0.0;
+            }
+            match next {
+                Some((i, a, new_data)) => {
+                    let utility = self.utility_with_settings(&new_data, depth + t + 1, ctx);
+                    if score.map(|s| utility > s).unwrap_or(true) {score = Some(utility)};
+                    indices.push(i);
+                    seq.push(a);
+                    stack.push(new_data);
+                }
+                None => break,
+            }
+        }
+
+        // Undo changes made to context to reset state.
+        while let Some(data) = stack.pop() {
+            self.revert(&data, ctx);
+        }
+        (score, indices, seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn finds_maximum() {
+        let mut ai = line_ai(6);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        let settings = CrossEntropySettings::new(20, 5, 10);
+        ai.cross_entropy(&mut root, 0, &mut pos, &settings, &mut Rng::new(2));
+        assert_eq!(pos, 0);
+        assert!(root.max > -0.001);
+    }
+}
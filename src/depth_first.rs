@@ -0,0 +1,279 @@
+//! Depth-first search driven by an explicit stack.
+//!
+//! Recursive search uses the call stack for every level of depth,
+//! which overflows for long horizons, e.g. when `AiSettings::max_depth`
+//! is in the tens of thousands with few actions.
+//! Instead, algorithms implement `DepthFirst` and are driven by `Ai::depth_first`,
+//! which keeps track of visited nodes in a vector.
+//!
+//! Nodes are accessed through `Storage`, which keeps track of the current node,
+//! such that the same algorithm constructs boxed trees and arenas (see `crate::arena`).
+//! For boxed trees, `NodeStorage` moves a child out of its parent while it is visited,
+//! such that the parent can be accessed without borrowing the whole tree.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::rng::Rng;
+use crate::utility::Utility;
+
+/// Callbacks of a depth-first search.
+pub(crate) trait DepthFirst<T, A, C, U: Utility, E, S: Storage<T, A, U>> {
+    /// Called when entering a node, with context in the state of the node.
+    ///
+    /// Returns the indices of children to visit, in order.
+    fn enter(&mut self, ai: &mut Ai<T, A, C, U, E>, node: &mut S, depth: usize, ctx: &mut C) -> Vec<usize>;
+
+    /// Called before entering a child, with context in the state of the child.
+    ///
+    /// Returns `false` to skip the child.
+    fn descend(&mut self, _ai: &mut Ai<T, A, C, U, E>, _child: &S, _depth: usize, _ctx: &C) -> bool {true}
+
+    /// Called when leaving a node, after visiting children.
+    fn leave(&mut self, _ai: &mut Ai<T, A, C, U, E>, _node: &mut S, _depth: usize, _ctx: &C) {}
+}
+
+/// Storage of the nodes constructed by search.
+///
+/// The storage keeps track of the current node on the path from root,
+/// which starts at root.
+pub(crate) trait Storage<T, A, U: Utility> {
+    /// Returns node data of the current node.
+    fn data(&self) -> &T;
+
+    /// Returns maximum utility of the current node.
+    fn max(&self) -> U;
+
+    /// Sets maximum utility of the current node.
+    fn set_max(&mut self, max: U);
+
+    /// Returns `true` if the current node is partially expanded.
+    fn partial(&self) -> bool;
+
+    /// Sets whether the current node is partially expanded.
+    fn set_partial(&mut self, partial: bool);
+
+    /// Returns the number of children of the current node.
+    fn child_count(&self) -> usize;
+
+    /// Returns the action, node data and maximum utility of a child of the current node.
+    fn child(&self, i: usize) -> (&A, &T, U);
+
+    /// Adds a child without children to the current node.
+    fn push(&mut self, a: A, data: T, max: U);
+
+    /// Removes the descendants of the current node, returning the number of removed nodes.
+    fn clear(&mut self) -> usize;
+
+    /// Keeps the children of the current node for which a function returns `true`.
+    ///
+    /// The function is called with the index, action, node data and maximum utility of each child.
+    fn retain(&mut self, f: &mut dyn FnMut(usize, &A, &T, U) -> bool);
+
+    /// Prepares the children of the current node for visiting.
+    ///
+    /// Until `close` is called, children are only accessed through `action`, `descend` and `ascend`.
+    fn open(&mut self);
+
+    /// Returns node data of the current node and the action of a child, while children are open.
+    fn action(&self, i: usize) -> (&T, &A);
+
+    /// Makes a child of the current node the current node.
+    fn descend(&mut self, i: usize);
+
+    /// Makes the parent of the current node the current node.
+    fn ascend(&mut self);
+
+    /// Finishes visiting the children of the current node.
+    fn close(&mut self);
+
+    /// Returns the optimal child index of the current node, see `Node::optimal`.
+    fn optimal(&self) -> Option<usize> {
+        let max = self.max();
+        (0..self.child_count()).find(|&i| self.child(i).2 >= max)
+    }
+
+    /// Same as `optimal`, but with probability `epsilon`
+    /// returns a random non-optimal child instead.
+    ///
+    /// When the node is terminal, a random child is returned with probability `epsilon`.
+    fn epsilon_optimal(&self, epsilon: f64, rng: &mut Rng) -> Option<usize> {
+        let optimal = self.optimal();
+        if rng.next_f64() >= epsilon {return optimal};
+
+        let n = self.child_count();
+        match optimal {
+            Some(i) if n > 1 => {
+                let j = rng.next_usize(n - 1);
+                Some(if j >= i {j + 1} else {j})
+            }
+            Some(i) => Some(i),
+            None if n > 0 => Some(rng.next_usize(n)),
+            None => None,
+        }
+    }
+}
+
+/// Children that are moved out while visiting, paired with actions.
+type Slots<T, A, U> = Vec<(A, Option<Node<T, A, U>>)>;
+
+/// Stores a node that is being visited.
+struct Frame<T, A, U> {
+    /// The node, or `None` for root.
+    node: Option<Node<T, A, U>>,
+    /// The index of node among the children of its parent.
+    index: usize,
+    /// The children of node, moved out while visiting.
+    children: Slots<T, A, U>,
+}
+
+/// Stores the nodes of a boxed tree.
+pub(crate) struct NodeStorage<'a, T, A, U> {
+    root: &'a mut Node<T, A, U>,
+    /// The nodes on the current path, starting with root.
+    frames: Vec<Frame<T, A, U>>,
+}
+
+impl<'a, T, A, U> NodeStorage<'a, T, A, U> {
+    pub(crate) fn new(root: &'a mut Node<T, A, U>) -> NodeStorage<'a, T, A, U> {
+        NodeStorage {root, frames: vec![Frame {node: None, index: 0, children: vec![]}]}
+    }
+
+    fn frame(&self) -> &Frame<T, A, U> {self.frames.last().expect("There should be a frame")}
+
+    fn node(&self) -> &Node<T, A, U> {self.frame().node.as_ref().unwrap_or(&*self.root)}
+
+    fn node_mut(&mut self) -> &mut Node<T, A, U> {
+        let frame = self.frames.last_mut().expect("There should be a frame");
+        match &mut frame.node {
+            Some(node) => node,
+            None => &mut *self.root,
+        }
+    }
+}
+
+impl<'a, T, A, U: Utility> Storage<T, A, U> for NodeStorage<'a, T, A, U> {
+    fn data(&self) -> &T {&self.node().data}
+
+    fn max(&self) -> U {self.node().max}
+
+    fn set_max(&mut self, max: U) {self.node_mut().max = max}
+
+    fn partial(&self) -> bool {self.node().partial}
+
+    fn set_partial(&mut self, partial: bool) {self.node_mut().partial = partial}
+
+    fn child_count(&self) -> usize {self.node().children.len()}
+
+    fn child(&self, i: usize) -> (&A, &T, U) {
+        let (a, ch) = &self.node().children[i];
+        (a, &ch.data, ch.max)
+    }
+
+    fn push(&mut self, a: A, data: T, max: U) {
+        self.node_mut().children.push((a, Node {max, data, children: vec![], partial: false}));
+    }
+
+    fn clear(&mut self) -> usize {self.node_mut().clear()}
+
+    fn retain(&mut self, f: &mut dyn FnMut(usize, &A, &T, U) -> bool) {
+        let mut i = 0;
+        self.node_mut().children.retain(|(a, ch)| {
+            i += 1;
+            f(i - 1, a, &ch.data, ch.max)
+        });
+    }
+
+    fn open(&mut self) {
+        let children = std::mem::take(&mut self.node_mut().children);
+        let frame = self.frames.last_mut().expect("There should be a frame");
+        frame.children = children.into_iter().map(|(a, ch)| (a, Some(ch))).collect();
+    }
+
+    fn action(&self, i: usize) -> (&T, &A) {(&self.node().data, &self.frame().children[i].0)}
+
+    fn descend(&mut self, i: usize) {
+        let frame = self.frames.last_mut().expect("There should be a frame");
+        let ch = frame.children[i].1.take().expect("Child should only be visited once");
+        self.frames.push(Frame {node: Some(ch), index: i, children: vec![]});
+    }
+
+    fn ascend(&mut self) {
+        let frame = self.frames.pop().expect("There should be a frame");
+        let parent = self.frames.last_mut().expect("There should be a parent frame");
+        parent.children[frame.index].1 = frame.node;
+    }
+
+    fn close(&mut self) {
+        let frame = self.frames.last_mut().expect("There should be a frame");
+        let children = std::mem::take(&mut frame.children).into_iter()
+            .map(|(a, ch)| (a, ch.expect("Child should be restored")))
+            .collect();
+        self.node_mut().children = children;
+    }
+}
+
+/// Keeps track of the children left to visit of a node.
+struct Visit {
+    depth: usize,
+    /// The indices of children left to visit, in reverse order.
+    order: Vec<usize>,
+}
+
+impl Visit {
+    fn new(depth: usize, mut order: Vec<usize>) -> Visit {
+        order.reverse();
+        Visit {depth, order}
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Runs a depth-first search using an explicit stack.
+    pub(crate) fn depth_first<S: Storage<T, A, U>, D: DepthFirst<T, A, C, U, E, S>>(
+        &mut self,
+        storage: &mut S,
+        depth: usize,
+        ctx: &mut C,
+        dfs: &mut D,
+    ) {
+        let order = dfs.enter(self, storage, depth, ctx);
+        storage.open();
+        let mut stack = vec![Visit::new(depth, order)];
+        while let Some(visit) = stack.last_mut() {
+            if let Some(i) = visit.order.pop() {
+                let depth = visit.depth + 1;
+                let (data, a) = storage.action(i);
+                if self.try_execute(data, a, ctx).is_none() {continue};
+
+                storage.descend(i);
+                if dfs.descend(self, storage, depth, ctx) {
+                    let order = dfs.enter(self, storage, depth, ctx);
+                    storage.open();
+                    stack.push(Visit::new(depth, order));
+                } else {
+                    // Undo changes made to context to reset state.
+                    self.revert(storage.data(), ctx);
+                    ascend(storage);
+                }
+            } else {
+                let visit = stack.pop().expect("There should be a visit");
+                storage.close();
+                dfs.leave(self, storage, visit.depth, ctx);
+                if stack.is_empty() {break};
+
+                // Undo changes made to context to reset state.
+                self.revert(storage.data(), ctx);
+                ascend(storage);
+            }
+        }
+    }
+}
+
+/// Moves to the parent of the current node, updating its maximum utility.
+fn ascend<T, A, U: Utility, S: Storage<T, A, U>>(storage: &mut S) {
+    let max = storage.max();
+    storage.ascend();
+    // Update maximum utility since children are changed.
+    if max > storage.max() {
+        storage.set_max(max);
+    }
+}
@@ -0,0 +1,125 @@
+//! Differences between two maximum trees.
+//!
+//! Nodes are matched by the sequence of actions from root,
+//! such that trees constructed by different searches can be compared.
+//! This is useful to see where a plan diverged after changing e.g. the utility function.
+
+use crate::Node;
+
+/// A difference between two trees.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change<A> {
+    /// A subtree exists only in the new tree.
+    Added {
+        /// The actions from root.
+        actions: Vec<A>,
+        /// The number of nodes in subtree.
+        nodes: usize,
+        /// The maximum utility of subtree.
+        max: f64,
+    },
+    /// A subtree exists only in the old tree.
+    Removed {
+        /// The actions from root.
+        actions: Vec<A>,
+        /// The number of nodes in subtree.
+        nodes: usize,
+        /// The maximum utility of subtree.
+        max: f64,
+    },
+    /// The maximum utility of a node changed.
+    Utility {
+        /// The actions from root.
+        actions: Vec<A>,
+        /// The maximum utility in the old tree.
+        old: f64,
+        /// The maximum utility in the new tree.
+        new: f64,
+    },
+}
+
+/// Returns differences between two trees, in pre-order.
+///
+/// Subtrees that are added or removed are reported once at their root.
+pub fn diff<T, A>(old: &Node<T, A>, new: &Node<T, A>) -> Vec<Change<A>>
+    where A: Clone + PartialEq
+{
+    let mut changes = vec![];
+    diff_node(old, new, &mut vec![], &mut changes);
+    changes
+}
+
+fn diff_node<T, A>(old: &Node<T, A>, new: &Node<T, A>, actions: &mut Vec<A>, changes: &mut Vec<Change<A>>)
+    where A: Clone + PartialEq
+{
+    if old.max != new.max && !(old.max.is_nan() && new.max.is_nan()) {
+        changes.push(Change::Utility {actions: actions.clone(), old: old.max, new: new.max});
+    }
+    for (a, ch) in &old.children {
+        actions.push(a.clone());
+        match new.children.iter().find(|(b, _)| b == a) {
+            Some((_, new_ch)) => diff_node(ch, new_ch, actions, changes),
+            None => changes.push(Change::Removed {
+                actions: actions.clone(),
+                nodes: 1 + ch.descendants(),
+                max: ch.max,
+            }),
+        }
+        actions.pop();
+    }
+    for (a, ch) in &new.children {
+        if !old.children.iter().any(|(b, _)| b == a) {
+            let mut actions = actions.clone();
+            actions.push(a.clone());
+            changes.push(Change::Added {actions, nodes: 1 + ch.descendants(), max: ch.max});
+        }
+    }
+}
+
+/// Returns the actions shared by the optimal paths of two trees,
+/// or `None` if the optimal paths are equal.
+///
+/// The optimal paths diverge after the returned actions.
+pub fn plan_divergence<T, A>(old: &Node<T, A>, new: &Node<T, A>) -> Option<Vec<A>>
+    where A: Clone + PartialEq
+{
+    let mut actions = vec![];
+    let (mut old, mut new) = (old, new);
+    loop {
+        match (old.optimal(), new.optimal()) {
+            (None, None) => return None,
+            (Some(i), Some(j)) if old.children[i].0 == new.children[j].0 => {
+                actions.push(old.children[i].0.clone());
+                old = &old.children[i].1;
+                new = &new.children[j].1;
+            }
+            _ => return Some(actions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn utility_change() {
+        let mut ai = line_ai(2);
+        let mut pos = 0;
+        let mut old = Node::root(0);
+        ai.full(&mut old, 0, &mut pos);
+        assert!(diff(&old, &old).is_empty());
+        assert_eq!(plan_divergence(&old, &old), None);
+
+        ai.utility = Box::new(|_, pos| -(*pos + 3).abs() as f64);
+        let mut new = Node::root(0);
+        ai.settings.max_depth = 0;
+        ai.full(&mut new, 0, &mut pos);
+        let changes = diff(&old, &new);
+        assert_eq!(changes[0], Change::Utility {actions: vec![], old: old.max, new: new.max});
+        assert!(changes.contains(&Change::Removed {actions: vec![-1, -1], nodes: 3, max: old.get(&[0, 0]).unwrap().max}));
+        assert!(!changes.iter().any(|ch| matches!(ch, Change::Added {..})));
+        assert_eq!(plan_divergence(&old, &new), Some(vec![]));
+    }
+}
@@ -0,0 +1,82 @@
+//! Human readable display of maximum trees.
+//!
+//! Each node is written on its own line, indented by depth,
+//! with the action leading to it, node data and maximum utility.
+//! Nodes on the optimal path from root are marked with `*`.
+//! Deep trees can be cut off with `TreeDisplay::max_depth`.
+
+use std::fmt;
+
+use crate::Node;
+use crate::utility::Utility;
+
+/// Displays a tree, see `Node::display`.
+pub struct TreeDisplay<'a, T, A, U = f64> {
+    node: &'a Node<T, A, U>,
+    fmt_data: fn(&T) -> String,
+    fmt_action: fn(&A) -> String,
+    max_depth: Option<usize>,
+}
+
+impl<'a, T, A, U> TreeDisplay<'a, T, A, U> {
+    /// Sets depth below which nodes are summarized by the number of descendants.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+}
+
+impl<T, A, U: Utility> Node<T, A, U> {
+    /// Returns a displayable indented tree, marking the optimal path.
+    pub fn display(&self, fmt_data: fn(&T) -> String, fmt_action: fn(&A) -> String) -> TreeDisplay<'_, T, A, U> {
+        TreeDisplay {node: self, fmt_data, fmt_action, max_depth: None}
+    }
+}
+
+impl<'a, T, A, U: Utility + fmt::Display> TreeDisplay<'a, T, A, U> {
+    fn write_node(&self, f: &mut fmt::Formatter, node: &Node<T, A, U>, depth: usize, optimal: bool) -> fmt::Result {
+        let opt = if optimal {node.optimal()} else {None};
+        for (i, (a, ch)) in node.children.iter().enumerate() {
+            let on_path = opt == Some(i);
+            write!(f, "{:indent$}", "", indent = 2 * (depth + 1))?;
+            if on_path {write!(f, "* ")?};
+            writeln!(f, "{}: {} [max: {}]", (self.fmt_action)(a), (self.fmt_data)(&ch.data), ch.max)?;
+            if ch.children.is_empty() {continue};
+            if self.max_depth.map(|d| depth + 1 >= d).unwrap_or(false) {
+                writeln!(f, "{:indent$}... ({} descendants)", "", ch.descendants(), indent = 2 * (depth + 2))?;
+            } else {
+                self.write_node(f, ch, depth + 1, on_path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T, A, U: Utility + fmt::Display> fmt::Display for TreeDisplay<'a, T, A, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "* {} [max: {}]", (self.fmt_data)(&self.node.data), self.node.max)?;
+        if self.max_depth == Some(0) && !self.node.children.is_empty() {
+            return writeln!(f, "  ... ({} descendants)", self.node.descendants());
+        }
+        self.write_node(f, self.node, 0, true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Node;
+
+    #[test]
+    fn displays_tree() {
+        let mut root: Node<i32, i32> = Node::root(0);
+        root.max = 2.0;
+        let mut ch = Node {max: 2.0, data: 1, children: vec![], partial: false};
+        ch.children.push((1, Node {max: 2.0, data: 2, children: vec![], partial: false}));
+        root.children.push((-1, Node {max: 0.0, data: -1, children: vec![], partial: false}));
+        root.children.push((1, ch));
+        let s = root.display(|d| format!("{}", d), |a| format!("{}", a)).to_string();
+        assert_eq!(s, "* 0 [max: 2]\n  -1: -1 [max: 0]\n  * 1: 1 [max: 2]\n    * 1: 2 [max: 2]\n");
+        let s = root.display(|d| format!("{}", d), |a| format!("{}", a)).max_depth(1).to_string();
+        assert_eq!(s, "* 0 [max: 2]\n  -1: -1 [max: 0]\n  * 1: 1 [max: 2]\n    ... (1 descendants)\n");
+    }
+}
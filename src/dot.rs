@@ -0,0 +1,76 @@
+//! GraphViz DOT export of maximum trees.
+//!
+//! Each node is labeled with node data and maximum utility,
+//! and each edge is labeled with the action.
+//! The output can be rendered with e.g. `dot -Tsvg tree.dot > tree.svg`.
+
+use std::fmt::Write;
+
+use crate::Node;
+
+impl<T, A> Node<T, A> {
+    /// Returns a DOT graph of the tree.
+    pub fn to_dot(&self, label_data: fn(&T) -> String, label_action: fn(&A) -> String) -> String {
+        self.dot(label_data, label_action, false)
+    }
+
+    /// Same as `to_dot`, but highlights the optimal path from root.
+    pub fn to_dot_optimal(&self, label_data: fn(&T) -> String, label_action: fn(&A) -> String) -> String {
+        self.dot(label_data, label_action, true)
+    }
+
+    fn dot(&self, label_data: fn(&T) -> String, label_action: fn(&A) -> String, optimal: bool) -> String {
+        let mut s = String::from("digraph max_tree {\n");
+        let mut id = 0;
+        self.dot_node(&mut s, &mut id, label_data, label_action, optimal);
+        s.push_str("}\n");
+        s
+    }
+
+    /// Writes node and its children, returning the id of node.
+    fn dot_node(
+        &self,
+        s: &mut String,
+        id: &mut usize,
+        label_data: fn(&T) -> String,
+        label_action: fn(&A) -> String,
+        optimal: bool,
+    ) -> usize {
+        let n = *id;
+        *id += 1;
+        let style = if optimal {", style=bold, color=red"} else {""};
+        let _ = writeln!(s, "    n{} [label=\"{}\\nmax: {}\"{}];", n, escape(&label_data(&self.data)), self.max, style);
+
+        let opt = if optimal {self.optimal()} else {None};
+        for (i, (a, ch)) in self.children.iter().enumerate() {
+            let on_path = opt == Some(i);
+            let m = ch.dot_node(s, id, label_data, label_action, on_path);
+            let style = if on_path {", style=bold, color=red"} else {""};
+            let _ = writeln!(s, "    n{} -> n{} [label=\"{}\"{}];", n, m, escape(&label_action(a)), style);
+        }
+        n
+    }
+}
+
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlights_optimal_path() {
+        let mut root: Node<i32, i32> = Node::root(0);
+        root.max = 1.0;
+        root.children.push((-1, Node {max: 0.0, data: -1, children: vec![], partial: false}));
+        root.children.push((1, Node {max: 1.0, data: 1, children: vec![], partial: false}));
+        let dot = root.to_dot_optimal(|d| format!("\"{}\"", d), |a| format!("{}", a));
+        assert!(dot.starts_with("digraph max_tree {\n"));
+        assert!(dot.contains("n0 [label=\"\\\"0\\\"\\nmax: 1\", style=bold, color=red];"));
+        assert!(dot.contains("n0 -> n1 [label=\"-1\"];"));
+        assert!(dot.contains("n0 -> n2 [label=\"1\", style=bold, color=red];"));
+        assert!(!root.to_dot(|d| format!("{}", d), |a| format!("{}", a)).contains("bold"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Errors of searches and helpers.
+//!
+//! Searches keep the partial tree when they stop early,
+//! so by default, stopping early is reported by `SearchOutcome` and not as an error.
+//! The `Ai::try_full` and `Ai::try_greedy` methods return `Result<SearchOutcome, Error<E>>`,
+//! where stopping early because of a limit, cancellation or an aborting error is an error.
+//! Reaching `AiSettings::target_utility` is not an error.
+//! Other searches are run by `Ai::try_search`, which returns the result of the search.
+//! For helpers, `Node::try_validate` returns a violated invariant as an error.
+//!
+//! An error that aborts the search is always collected in `AiAnalysis::errors`,
+//! such that it can be propagated whether or not analysis is activated.
+
+use std::fmt;
+use std::hash::Hash;
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::invariant::InvariantError;
+use crate::outcome::{SearchOutcome, StopReason};
+use crate::utility::Utility;
+
+/// An error of a search or helper.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error<E = ()> {
+    /// Estimated memory usage exceeded `AiSettings::max_mib`.
+    MemoryExceeded,
+    /// Time spent exceeded `AiSettings::max_time`.
+    TimeExceeded,
+    /// The number of created nodes exceeded `AiSettings::max_nodes`.
+    NodesExceeded,
+    /// The search was cancelled, see `AiSettings::cancel`.
+    Cancelled,
+    /// An action failed to execute with an error that aborted the search.
+    Execute(E),
+    /// The search was aborted by an error that is no longer in `AiAnalysis::errors`.
+    Aborted,
+    /// The maximum tree invariant is violated, see `Node::validate`.
+    Invariant(InvariantError),
+}
+
+impl<E> From<InvariantError> for Error<E> {
+    fn from(err: InvariantError) -> Error<E> {Error::Invariant(err)}
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::MemoryExceeded => write!(f, "memory limit exceeded"),
+            Error::TimeExceeded => write!(f, "time limit exceeded"),
+            Error::NodesExceeded => write!(f, "node limit exceeded"),
+            Error::Cancelled => write!(f, "search cancelled"),
+            Error::Execute(err) => write!(f, "execute failed: {:?}", err),
+            Error::Aborted => write!(f, "search aborted"),
+            Error::Invariant(err) => write!(f, "invariant violated: {:?}", err),
+        }
+    }
+}
+
+impl<E: fmt::Debug> std::error::Error for Error<E> {}
+
+impl<T, A, U: Utility> Node<T, A, U> {
+    /// Same as `Node::validate`, but returns the violation as `Error`.
+    pub fn try_validate<E>(&self) -> Result<(), Error<E>>
+        where A: Eq + Hash
+    {
+        Ok(self.validate()?)
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError + Clone> Ai<T, A, C, U, E> {
+    /// Converts the outcome of the last search into a result.
+    ///
+    /// The error that aborted the search is cloned from `AiAnalysis::errors`.
+    pub fn outcome_result(&self, outcome: SearchOutcome) -> Result<SearchOutcome, Error<E>> {
+        match outcome.stop {
+            StopReason::Exhausted | StopReason::DepthLimit | StopReason::TargetReached => Ok(outcome),
+            StopReason::MemoryLimit => Err(Error::MemoryExceeded),
+            StopReason::TimeLimit => Err(Error::TimeExceeded),
+            StopReason::NodeLimit => Err(Error::NodesExceeded),
+            StopReason::Cancelled => Err(Error::Cancelled),
+            StopReason::Aborted => Err(self.analysis.abort_error
+                .and_then(|i| self.analysis.errors.get(i))
+                .map(|err| Error::Execute(err.clone()))
+                .unwrap_or(Error::Aborted)),
+        }
+    }
+
+    /// Runs a search, returning its result, or an error when stopping early.
+    ///
+    /// This is used for searches that do not return `SearchOutcome`,
+    /// e.g. `ai.try_search(|ai| ai.beam(&mut root, 0, &mut ctx, 4))`.
+    /// The search counts as one search, so analysis and limits span the whole closure.
+    pub fn try_search<R>(&mut self, search: impl FnOnce(&mut Self) -> R) -> Result<R, Error<E>> {
+        let started = self.start_timer();
+        let res = search(self);
+        let outcome = self.finish(started);
+        self.outcome_result(outcome).map(|_| res)
+    }
+
+    /// Same as `Ai::full`, but returns an error when stopping early.
+    pub fn try_full(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C)
+        -> Result<SearchOutcome, Error<E>>
+        where A: Clone
+    {
+        let outcome = self.full(root, depth, ctx);
+        self.outcome_result(outcome)
+    }
+
+    /// Same as `Ai::greedy`, but returns an error when stopping early.
+    pub fn try_greedy(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C)
+        -> Result<SearchOutcome, Error<E>>
+        where A: Clone
+    {
+        let outcome = self.greedy(root, depth, ctx);
+        self.outcome_result(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execute_error::ActionError;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn propagates_errors() {
+        let mut ai: Ai<i32, i32, i32, f64, ActionError> = Ai::builder()
+            .utility(|_, pos: &i32| -(*pos as f64 - 3.0).abs())
+            .actions(|_, _| vec![-1, 1])
+            .execute(|_, a, pos| {
+                if *pos + a == 2 {return Err(ActionError::Failure("simulation failed".into()))};
+                *pos += a;
+                Ok(*a)
+            })
+            .undo(|a, pos| *pos -= a)
+            .max_depth(4)
+            .build();
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        let err = ai.try_full(&mut root, 0, &mut pos).unwrap_err();
+        assert_eq!(err, Error::Execute(ActionError::Failure("simulation failed".into())));
+        assert_eq!(err.to_string(), "execute failed: Failure(\"simulation failed\")");
+        // Aborting errors are collected without analysis.
+        assert!(!ai.analysis.errors.is_empty());
+        assert_eq!(ai.analysis.abort_error, Some(0));
+        let outcome = SearchOutcome {
+            stop: StopReason::Aborted,
+            nodes_created: 0,
+            max_depth_reached: 0,
+            search_time: Default::default(),
+        };
+        assert_eq!(ai.outcome_result(outcome.clone()), Err(err.clone()));
+        assert_eq!(ai.outcome_result(outcome.clone()), Err(err));
+        ai.analysis.errors.clear();
+        assert_eq!(ai.outcome_result(outcome), Err(Error::Aborted));
+
+        let mut ai = line_ai(2);
+        let mut root = Node::root(0);
+        assert_eq!(ai.try_full(&mut root, 0, &mut pos).unwrap().stop, StopReason::DepthLimit);
+        ai.settings.max_nodes = Some(1);
+        let mut root = Node::root(0);
+        assert_eq!(ai.try_greedy(&mut root, 0, &mut pos), Err(Error::NodesExceeded));
+        let err: Error = InvariantError::Max {path: vec![0]}.into();
+        assert_eq!(err.to_string(), "invariant violated: Max { path: [0] }");
+    }
+
+    #[test]
+    fn try_other_searches() {
+        let mut ai = line_ai(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        assert_eq!(ai.try_search(|ai| {ai.beam(&mut root, 0, &mut pos, 1); root.max > -0.001}), Ok(true));
+        assert_eq!(root.try_validate::<()>(), Ok(()));
+
+        ai.settings.max_nodes = Some(1);
+        let mut root = Node::root(0);
+        assert_eq!(ai.try_search(|ai| ai.beam(&mut root, 0, &mut pos, 1)), Err(Error::NodesExceeded));
+
+        root.children[0].1.max = 10.0;
+        assert_eq!(root.try_validate::<()>(), Err(Error::Invariant(InvariantError::Max {path: vec![0]})));
+    }
+}
@@ -0,0 +1,99 @@
+//! Errors from executing actions.
+//!
+//! By default, `Ai::execute` returns `Result<T, ()>`,
+//! where an error means the action is not available and the child is skipped.
+//! With a custom error type, errors are collected in `AiAnalysis::errors`,
+//! and an error can abort the search, e.g. when a simulation fails.
+
+/// Implemented by error types of `Ai::execute`.
+pub trait ExecuteError {
+    /// Returns `true` if the search should be aborted.
+    ///
+    /// When aborted, `AiAnalysis::aborted` is set and the search terminates
+    /// like when a limit of memory or time is exceeded.
+    fn abort(&self) -> bool;
+}
+
+impl ExecuteError for () {
+    fn abort(&self) -> bool {false}
+}
+
+/// A common error type for executing actions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ActionError {
+    /// The action is illegal in this state, so the child is skipped.
+    Illegal,
+    /// The simulation failed, so the search is aborted.
+    Failure(String),
+}
+
+impl ExecuteError for ActionError {
+    fn abort(&self) -> bool {
+        matches!(self, ActionError::Failure(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ai, Node};
+    use crate::mcts::Stats;
+    use crate::rng::Rng;
+
+    fn ai(fail_at: i32) -> Ai<i32, i32, i32, f64, ActionError> {
+        Ai::builder()
+            .utility(|_, pos: &i32| -(*pos as f64 - 3.0).abs())
+            .actions(|_, _| vec![-1, 1])
+            .execute(move |_, a, pos| {
+                if *pos + a < 0 {return Err(ActionError::Illegal)};
+                if *pos + a == fail_at {return Err(ActionError::Failure("simulation failed".into()))};
+                *pos += a;
+                Ok(*a)
+            })
+            .undo(|a, pos| *pos -= a)
+            .max_depth(4)
+            .analysis(true)
+            .build()
+    }
+
+    #[test]
+    fn collects_errors() {
+        let mut ai = ai(-1);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max, 0.0);
+        assert!(!ai.analysis.aborted);
+        assert!(!ai.analysis.errors.is_empty());
+        assert!(ai.analysis.errors.iter().all(|err| *err == ActionError::Illegal));
+    }
+
+    #[test]
+    fn aborts_on_failure() {
+        let mut ai = ai(2);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert!(ai.analysis.aborted);
+        assert!(root.max < 0.0);
+    }
+
+    #[test]
+    fn other_algorithms_collect_errors() {
+        let mut ai = ai(-1);
+        let mut pos = 0;
+        ai.minimax(&mut Node::root(0), 0, &mut pos);
+        ai.expectimax(&mut Node::root(0), 0, &mut pos);
+        ai.tabu(&mut Node::root(0), 0, &mut pos, 4);
+        ai.best_first(&mut Node::root(0), 0, &mut pos, 4);
+        ai.uniform_cost(&mut Node::root(0), 0, &mut pos, 4);
+        ai.mcts(&mut Node::root(0), &mut Stats::new(), 0, &mut pos, 10, &mut Rng::new(0));
+        assert_eq!(pos, 0);
+        assert!(!ai.analysis.aborted);
+        assert!(!ai.analysis.errors.is_empty());
+        assert!(ai.analysis.errors.iter().all(|err| *err == ActionError::Illegal));
+    }
+}
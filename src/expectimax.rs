@@ -0,0 +1,215 @@
+//! Expectimax search with chance nodes.
+//!
+//! Under non-determinism, the maximum utility becomes maximum expected utility.
+//! Non-determinism is modeled by chance nodes,
+//! where the environment picks an outcome instead of the AI agent.
+//! Outcomes are represented as actions with probabilities,
+//! such that they are executed and undone like any other action.
+//!
+//! The value of a chance node is the expected value of its outcomes,
+//! while the value of a decision node is the maximum of its own utility and children.
+//! Values are stored in `Node::max`.
+//!
+//! Since the AI agent does not choose outcomes,
+//! `Node::optimal` is only meaningful for decision nodes.
+//!
+//! For risk-averse planning, the value of a chance node can be aggregated
+//! by the worst outcome or by conditional value at risk (CVaR) instead,
+//! see `AiSettings::risk`.
+//!
+//! Expected values are weighted sums of utility, so expectimax requires `f64` as utility.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+
+/// Decides what kind of node it is.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeKind<A> {
+    /// The AI agent chooses an action.
+    Decision,
+    /// The environment chooses an outcome with probability.
+    ///
+    /// Outcomes are executed as actions.
+    Chance(Vec<(f64, A)>),
+}
+
+/// Aggregates values of outcomes at chance nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Risk {
+    /// The expected value of outcomes (risk-neutral).
+    #[default]
+    Expected,
+    /// The value of the worst outcome (robust).
+    WorstCase,
+    /// The expected value of the worst outcomes with total probability `alpha`.
+    ///
+    /// This is between `WorstCase` when `alpha` approaches `0` and `Expected` when `alpha` is `1`.
+    CVaR(f64),
+}
+
+impl Risk {
+    /// Aggregates a list of outcomes with probabilities and values.
+    ///
+    /// Probabilities are normalized.
+    /// Returns `None` if total probability is not positive.
+    pub fn aggregate(&self, outcomes: &[(f64, f64)]) -> Option<f64> {
+        let total: f64 = outcomes.iter().map(|&(p, _)| p).sum();
+        if total.is_nan() || total <= 0.0 {return None};
+        match *self {
+            Risk::Expected => Some(outcomes.iter().map(|&(p, v)| p * v).sum::<f64>() / total),
+            Risk::WorstCase => outcomes.iter().filter(|&&(p, _)| p > 0.0)
+                .map(|&(_, v)| v).fold(None, |m: Option<f64>, v| Some(m.map_or(v, |m| m.min(v)))),
+            Risk::CVaR(alpha) => {
+                let mut sorted = outcomes.to_vec();
+                sorted.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+                let mass = alpha.clamp(0.0, 1.0) * total;
+                if mass == 0.0 {return Risk::WorstCase.aggregate(outcomes)};
+                let (mut left, mut sum) = (mass, 0.0);
+                for (p, v) in sorted {
+                    let p = p.min(left);
+                    sum += p * v;
+                    left -= p;
+                    if left <= 0.0 {break};
+                }
+                Some(sum / mass)
+            }
+        }
+    }
+}
+
+impl<T, A, C, E: ExecuteError> Ai<T, A, C, f64, E> {
+    /// Constructs an expectimax tree.
+    ///
+    /// The kind of each node is decided by `Ai::node_kind`.
+    /// When this is not set, all nodes are decision nodes,
+    /// which is equivalent to `Ai::full`.
+    ///
+    /// Outcomes that fail to execute are ignored,
+    /// and the probabilities of the remaining outcomes are normalized.
+    /// The values of outcomes are aggregated using `AiSettings::risk`.
+    /// `AiSettings::max_children` limits children of decision nodes only,
+    /// since the value of a chance node depends on all outcomes.
+    pub fn expectimax(&mut self, root: &mut Node<T, A>, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        let started = self.start_timer();
+        self.expectimax_node(root, depth, ctx);
+        self.stop_timer(started);
+    }
+
+    fn expectimax_node(&mut self, root: &mut Node<T, A>, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        let kind = match &self.node_kind {
+            Some(node_kind) => node_kind(&root.data, ctx),
+            None => NodeKind::Decision,
+        };
+        let expand = depth < self.settings.max_depth;
+        match kind {
+            NodeKind::Decision => {
+                let utility = self.utility_with_settings(&root.data, depth, ctx);
+                self.sub_breadth(root, depth, ctx);
+
+                if expand && !self.limit_exceeded() {
+                    for (a, ch) in &mut root.children {
+                        if self.try_execute(&root.data, a, ctx).is_some() {
+                            self.expectimax_node(ch, depth + 1, ctx);
+
+                            // Undo changes made to context to reset state.
+                            self.revert(&ch.data, ctx);
+                        }
+                    }
+                }
+
+                // Children might be chance nodes with lower expected utility,
+                // so maximum utility is computed after expanding them.
+                root.max = utility;
+                for (_, ch) in &root.children {
+                    if ch.max > root.max {
+                        root.max = ch.max;
+                    }
+                }
+            }
+            NodeKind::Chance(outcomes) => {
+                root.children.clear();
+                let mut values = vec![];
+                for (p, a) in outcomes {
+                    if let Some(data) = self.try_execute(&root.data, &a, ctx) {
+                        let mut ch = Node {
+                            max: self.utility_with_settings(&data, depth + 1, ctx),
+                            data,
+                            children: vec![],
+                            partial: false,
+                        };
+                        self.count_node(&ch.data, &a, ch.max, depth + 1);
+                        if expand && !self.limit_exceeded() {
+                            self.expectimax_node(&mut ch, depth + 1, ctx);
+                        }
+
+                        // Undo changes made to context to reset state.
+                        self.revert(&ch.data, ctx);
+
+                        values.push((p, ch.max));
+                        root.children.push((a, ch));
+                    }
+                }
+                root.max = match self.settings.risk.aggregate(&values) {
+                    Some(value) => value,
+                    None => self.utility_with_settings(&root.data, depth, ctx),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn expected_utility() {
+        let mut ai = line_ai(3);
+        ai.node_kind = Some(Box::new(|_, pos| {
+            if *pos == 1 {NodeKind::Chance(vec![(0.75, 1), (0.25, -1)])}
+            else {NodeKind::Decision}
+        }));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.expectimax(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+
+        let chance = &root.children[1].1;
+        assert_eq!(chance.children.len(), 2);
+        let expected = 0.75 * chance.children[0].1.max + 0.25 * chance.children[1].1.max;
+        assert!((chance.max - expected).abs() < 1e-9);
+        assert_eq!(root.optimal(), Some(1));
+    }
+
+    #[test]
+    fn risk_averse() {
+        let outcomes = [(0.5, 1.0), (0.25, -2.0), (0.25, 4.0)];
+        assert_eq!(Risk::Expected.aggregate(&outcomes), Some(1.0));
+        assert_eq!(Risk::WorstCase.aggregate(&outcomes), Some(-2.0));
+        assert_eq!(Risk::CVaR(0.5).aggregate(&outcomes), Some(-0.5));
+        assert_eq!(Risk::CVaR(1.0).aggregate(&outcomes), Some(1.0));
+        assert_eq!(Risk::WorstCase.aggregate(&[]), None);
+
+        // A gamble with high expected value is avoided by worst-case planning.
+        let mut ai = line_ai(1);
+        ai.utility = Box::new(|_, pos| match *pos {-1 => 1.0, 2 => -10.0, 3 => 20.0, _ => 0.0});
+        ai.node_kind = Some(Box::new(|_, pos| {
+            if *pos == 1 {NodeKind::Chance(vec![(0.5, 1), (0.5, 2)])}
+            else {NodeKind::Decision}
+        }));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.expectimax(&mut root, 0, &mut pos);
+        assert_eq!(root.children[root.optimal().unwrap()].0, 1);
+        ai.settings.risk = Risk::WorstCase;
+        let mut root = Node::root(0);
+        ai.expectimax(&mut root, 0, &mut pos);
+        assert_eq!(root.children[root.optimal().unwrap()].0, -1);
+    }
+}
@@ -0,0 +1,114 @@
+//! Optimization toward a cluster of goals.
+//!
+//! When the true goal among many possible goals is uncertain,
+//! optimizing for a single goal risks excluding the true goal.
+//! Instead, one can optimize for a cluster of goals,
+//! where the utility of a node aggregates the utility of every goal.
+//! By default, the aggregate is the minimum, such that no goal in the cluster is neglected.
+//!
+//! Use `AiBuilder::goal_cluster` to set utility from a cluster of goals:
+//!
+//! ```
+//! use max_tree::prelude::*;
+//! use max_tree::goals::{GoalCluster, Aggregate};
+//!
+//! let cluster = GoalCluster::new(Aggregate::Min)
+//!     .goal(|_, pos: &i32| -(*pos - 2).abs() as f64)
+//!     .goal(|_, pos: &i32| -(*pos - 4).abs() as f64);
+//! let mut ai: Ai<i32, i32, i32> = Ai::builder()
+//!     .goal_cluster(cluster)
+//!     .actions(|_, _| vec![-1, 1])
+//!     .execute(|_, a, pos| {*pos += a; Ok(*a)})
+//!     .undo(|a, pos| *pos -= a)
+//!     .max_depth(4)
+//!     .eps_depth(0.0001)
+//!     .build();
+//! let mut root = Node::root(0);
+//! let mut pos = 0;
+//! ai.full(&mut root, 0, &mut pos);
+//! // Position 3 is between both goals.
+//! assert_eq!(root.optimal_path().len(), 3);
+//! ```
+
+use crate::UtilityFn;
+use crate::builder::AiBuilder;
+
+/// Aggregates utility of goals in a cluster.
+#[derive(Clone, Copy, Debug)]
+pub enum Aggregate {
+    /// The minimum utility of goals, such that the worst goal is optimized.
+    Min,
+    /// The mean utility of goals.
+    Mean,
+    /// A custom aggregate of utility of goals.
+    Custom(fn(&[f64]) -> f64),
+}
+
+impl Aggregate {
+    /// Aggregates utility of goals.
+    ///
+    /// Returns `NaN` (not a number) when there are no goals.
+    pub fn aggregate(&self, utilities: &[f64]) -> f64 {
+        if utilities.is_empty() {return f64::NAN};
+        match *self {
+            Aggregate::Min => utilities.iter().cloned().fold(f64::INFINITY, f64::min),
+            Aggregate::Mean => utilities.iter().sum::<f64>() / utilities.len() as f64,
+            Aggregate::Custom(f) => f(utilities),
+        }
+    }
+}
+
+/// Stores a cluster of goals, each with its own utility.
+pub struct GoalCluster<T, C> {
+    /// The utility of each goal.
+    pub goals: Vec<UtilityFn<T, C>>,
+    /// Aggregates utility of goals.
+    pub aggregate: Aggregate,
+}
+
+impl<T, C> GoalCluster<T, C> {
+    /// Creates a new empty cluster.
+    pub fn new(aggregate: Aggregate) -> GoalCluster<T, C> {
+        GoalCluster {goals: vec![], aggregate}
+    }
+
+    /// Adds a goal.
+    pub fn goal(mut self, f: impl Fn(&T, &C) -> f64 + Send + Sync + 'static) -> Self {
+        self.goals.push(Box::new(f));
+        self
+    }
+
+    /// Returns the utility of every goal.
+    pub fn utilities(&self, data: &T, ctx: &C) -> Vec<f64> {
+        self.goals.iter().map(|f| f(data, ctx)).collect()
+    }
+
+    /// Returns the aggregated utility of goals.
+    pub fn utility(&self, data: &T, ctx: &C) -> f64 {
+        self.aggregate.aggregate(&self.utilities(data, ctx))
+    }
+}
+
+impl<T: 'static, A, C: 'static, E> AiBuilder<T, A, C, f64, E> {
+    /// Sets utility from a cluster of goals.
+    pub fn goal_cluster(self, cluster: GoalCluster<T, C>) -> Self {
+        self.utility(move |data, ctx| cluster.utility(data, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_goals() {
+        let cluster: GoalCluster<i32, ()> = GoalCluster::new(Aggregate::Min)
+            .goal(|pos: &i32, _| *pos as f64)
+            .goal(|pos: &i32, _| -*pos as f64);
+        assert_eq!(cluster.utilities(&2, &()), vec![2.0, -2.0]);
+        assert_eq!(cluster.utility(&2, &()), -2.0);
+        assert_eq!(Aggregate::Mean.aggregate(&[1.0, 3.0]), 2.0);
+        assert_eq!(Aggregate::Custom(|u| u.iter().product()).aggregate(&[2.0, 3.0]), 6.0);
+        assert!(Aggregate::Min.aggregate(&[]).is_nan());
+    }
+}
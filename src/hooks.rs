@@ -0,0 +1,182 @@
+//! Callbacks fired during search.
+//!
+//! Hooks make it possible to stream progress of a search,
+//! e.g. into a logger or a user interface, without changing the search algorithms.
+//!
+//! - `on_expand` fires when a node is expanded with all available actions
+//! - `on_improve` fires when a new node has higher utility than any node constructed so far
+//! - `on_prune` fires when children are discarded, e.g. by `AiSettings::greed_elim`
+//! - `on_progress` fires periodically with a `Progress` report
+
+use std::time::{Duration, Instant};
+
+use crate::Ai;
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// Called with the node data of the expanded node, the number of children and the depth.
+pub type ExpandFn<T> = Box<dyn Fn(&T, usize, usize) + Send + Sync>;
+/// Called with the node data, the utility and the depth of the new node.
+pub type ImproveFn<T, U = f64> = Box<dyn Fn(&T, U, usize) + Send + Sync>;
+/// Called with the node data and the number of discarded children.
+pub type PruneFn<T> = Box<dyn Fn(&T, usize) + Send + Sync>;
+
+/// Called with a progress report.
+pub type ProgressFn<U = f64> = Box<dyn Fn(&Progress<U>) + Send + Sync>;
+
+/// Reports progress of the current search.
+#[derive(Clone, Debug)]
+pub struct Progress<U = f64> {
+    /// The number of nodes expanded so far.
+    pub expansions: usize,
+    /// The number of nodes, see `AiAnalysis::node_count`.
+    ///
+    /// This is only counted when `AiSettings::analysis` is activated.
+    pub node_count: usize,
+    /// The depth of the last expanded node.
+    pub depth: usize,
+    /// The best utility found so far.
+    pub best: Option<U>,
+    /// The estimated memory usage of nodes in Mibibytes.
+    pub mib: f64,
+    /// The time spent on search so far.
+    pub elapsed: Duration,
+}
+
+/// Stores optional callbacks fired during search.
+pub struct Hooks<T, U = f64> {
+    /// Fires when a node is expanded.
+    pub on_expand: Option<ExpandFn<T>>,
+    /// Fires when the best utility found in the current search improves.
+    pub on_improve: Option<ImproveFn<T, U>>,
+    /// Fires when children are discarded.
+    pub on_prune: Option<PruneFn<T>>,
+    /// Fires periodically with a progress report.
+    pub on_progress: Option<ProgressFn<U>>,
+    /// The minimum time between progress reports.
+    ///
+    /// Progress is checked after each expansion,
+    /// so time between reports might be longer.
+    pub progress_interval: Duration,
+    /// The best utility found in the current search.
+    pub(crate) best: Option<U>,
+    /// The number of nodes expanded in the current search.
+    pub(crate) expansions: usize,
+    /// The time of the last progress report.
+    pub(crate) last_progress: Option<Instant>,
+}
+
+impl<T, U> Default for Hooks<T, U> {
+    fn default() -> Hooks<T, U> {Hooks::new()}
+}
+
+impl<T, U> Hooks<T, U> {
+    /// Creates new hooks without callbacks.
+    pub fn new() -> Hooks<T, U> {
+        Hooks {
+            on_expand: None,
+            on_improve: None,
+            on_prune: None,
+            on_progress: None,
+            progress_interval: Duration::from_secs(1),
+            best: None,
+            expansions: 0,
+            last_progress: None,
+        }
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Fires `on_expand`, and `on_progress` when the progress interval has passed.
+    pub(crate) fn expanded(&mut self, data: &T, children: usize, depth: usize) {
+        self.hooks.expansions += 1;
+        if let Some(f) = &self.hooks.on_expand {f(data, children, depth)}
+        if let Some(f) = &self.hooks.on_progress {
+            let now = Instant::now();
+            let due = self.hooks.last_progress
+                .map(|t| now.duration_since(t) >= self.hooks.progress_interval)
+                .unwrap_or(true);
+            if due {
+                self.hooks.last_progress = Some(now);
+                f(&Progress {
+                    expansions: self.hooks.expansions,
+                    node_count: self.analysis.node_count,
+                    depth,
+                    best: self.hooks.best,
+                    mib: self.analysis.mib(self.node_size()),
+                    elapsed: self.analysis.search_start.map(|t| now.duration_since(t)).unwrap_or_default(),
+                });
+            }
+        }
+    }
+
+    /// Fires `on_improve` if utility is higher than the best utility so far.
+    pub(crate) fn improved(&mut self, data: &T, utility: U, depth: usize) {
+        if utility.is_unknown() {return};
+        if self.hooks.best.map(|b| utility > b).unwrap_or(true) {
+            self.hooks.best = Some(utility);
+            if let Some(f) = &self.hooks.on_improve {f(data, utility, depth)}
+        }
+    }
+
+    /// Fires `on_prune` if any children were discarded.
+    pub(crate) fn pruned(&self, data: &T, removed: usize) {
+        if removed == 0 {return};
+        if let Some(f) = &self.hooks.on_prune {f(data, removed)}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn fires_hooks() {
+        let expanded = Arc::new(AtomicUsize::new(0));
+        let improved = Arc::new(AtomicUsize::new(0));
+        let pruned = Arc::new(AtomicUsize::new(0));
+        let mut ai = line_ai(4);
+        let e = expanded.clone();
+        ai.hooks.on_expand = Some(Box::new(move |_, _, _| {e.fetch_add(1, Ordering::SeqCst);}));
+        let i = improved.clone();
+        ai.hooks.on_improve = Some(Box::new(move |_, _, _| {i.fetch_add(1, Ordering::SeqCst);}));
+        let p = pruned.clone();
+        ai.hooks.on_prune = Some(Box::new(move |_, n| {p.fetch_add(n, Ordering::SeqCst);}));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.greedy(&mut root, 0, &mut pos);
+        // Greedy expands one node per step until reaching maximum at position 3.
+        assert_eq!(expanded.load(Ordering::SeqCst), 4);
+        // Utility improves for the first child and once per step toward position 3.
+        assert_eq!(improved.load(Ordering::SeqCst), 4);
+        // One child is discarded per step.
+        assert_eq!(pruned.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn reports_progress() {
+        use std::sync::Mutex;
+
+        let reports = Arc::new(Mutex::new(vec![]));
+        let mut ai = line_ai(3);
+        ai.settings.analysis = true;
+        ai.hooks.progress_interval = Duration::from_secs(0);
+        let r = reports.clone();
+        ai.hooks.on_progress = Some(Box::new(move |p| r.lock().unwrap().push(p.clone())));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 15);
+        let last = reports.last().unwrap();
+        assert_eq!(last.expansions, 15);
+        assert_eq!(last.node_count, 30);
+        assert!(last.best.unwrap() > -0.001);
+        assert!(last.mib > 0.0);
+    }
+}
@@ -0,0 +1,96 @@
+//! Validation and repair of the maximum tree invariant.
+//!
+//! In a maximum tree, the maximum utility of a node is never less than that of its children,
+//! and the actions among children are unique.
+//! Search algorithms maintain this invariant,
+//! but custom algorithms that edit trees by hand might break it,
+//! e.g. by removing the best child or changing utility of a leaf.
+//! Use `Node::validate` to check the invariant and `Node::recompute_max` to repair it.
+
+use std::hash::Hash;
+
+use crate::Node;
+use crate::utility::Utility;
+
+/// A violation of the maximum tree invariant.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvariantError {
+    /// A child has greater maximum utility than its parent,
+    /// or the parent has unknown utility while the child is known.
+    Max {
+        /// The path of child indices from root to the child.
+        path: Vec<usize>,
+    },
+    /// Two children of a node have the same action.
+    DuplicateAction {
+        /// The path of child indices from root to the node.
+        path: Vec<usize>,
+    },
+}
+
+impl<T, A, U: Utility> Node<T, A, U> {
+    /// Recomputes maximum utility bottom-up from leaves.
+    ///
+    /// The maximum utility of a leaf is the utility of the node itself, so it is kept.
+    /// Other nodes get the maximum utility of their children,
+    /// since the utility of the node itself is not stored in the tree.
+    /// To also take the utility of inner nodes into account, use `Ai::rescore`.
+    pub fn recompute_max(&mut self) {
+        if self.children.is_empty() {return};
+        self.max = U::unknown();
+        for (_, ch) in &mut self.children {
+            ch.recompute_max();
+            if self.max.is_unknown() || ch.max > self.max {
+                self.max = ch.max;
+            }
+        }
+    }
+
+    /// Checks the maximum tree invariant, returning the first violation in pre-order.
+    pub fn validate(&self) -> Result<(), InvariantError>
+        where A: Eq + Hash
+    {
+        for (path, node) in self.iter_preorder() {
+            if !node.check_unique_actions() {
+                return Err(InvariantError::DuplicateAction {path});
+            }
+            for (i, (_, ch)) in node.children.iter().enumerate() {
+                let known = !ch.max.is_unknown();
+                if known && (node.max.is_unknown() || ch.max > node.max) {
+                    let mut path = path.clone();
+                    path.push(i);
+                    return Err(InvariantError::Max {path});
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn repairs_edited_tree() {
+        let mut ai = line_ai(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.validate(), Ok(()));
+
+        // Improve a leaf by hand.
+        let path = vec![0, 0, 0, 0];
+        root.get_mut(&path).unwrap().max = 10.0;
+        assert_eq!(root.validate(), Err(InvariantError::Max {path: path.clone()}));
+        root.recompute_max();
+        assert_eq!(root.validate(), Ok(()));
+        assert_eq!(root.max, 10.0);
+        assert_eq!(root.optimal_path(), path);
+
+        root.children.push((-1, Node::root(0)));
+        assert_eq!(root.validate(), Err(InvariantError::DuplicateAction {path: vec![]}));
+    }
+}
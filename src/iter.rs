@@ -0,0 +1,270 @@
+//! Tree traversal iterators.
+//!
+//! For analysis that stops early, `Node::visit` avoids allocating paths for every node.
+//!
+//! Every iterator yields the path of child indices from root together with the node.
+//! The mutable iterators yield mutable references to maximum utility and node data,
+//! but not to children, such that the tree structure can not change during traversal.
+//!
+//! Changing maximum utility might break the invariant of a maximum tree,
+//! where every node stores the maximum utility of itself or any children.
+
+use std::collections::VecDeque;
+use std::iter::Enumerate;
+use std::ops::ControlFlow;
+use std::slice::IterMut;
+
+use crate::Node;
+
+type Entry<'a, T, A, U> = (Vec<usize>, &'a Node<T, A, U>);
+// Stores the index of the next child to visit.
+type PostEntry<'a, T, A, U> = (Vec<usize>, &'a Node<T, A, U>, usize);
+type EntryMut<'a, T, A, U> = (Vec<usize>, &'a mut Node<T, A, U>);
+type ChildrenMut<'a, T, A, U> = Enumerate<IterMut<'a, (A, Node<T, A, U>)>>;
+
+/// Mutable access to a node during traversal.
+pub struct NodeMut<'a, T, U = f64> {
+    /// Stores maximum utility of itself or any children.
+    pub max: &'a mut U,
+    /// Stores node data.
+    pub data: &'a mut T,
+}
+
+/// Iterates over nodes in pre-order (parents before children).
+pub struct Preorder<'a, T, A, U = f64> {
+    stack: Vec<Entry<'a, T, A, U>>,
+}
+
+impl<'a, T, A, U> Iterator for Preorder<'a, T, A, U> {
+    type Item = (Vec<usize>, &'a Node<T, A, U>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+        for (i, (_, ch)) in node.children.iter().enumerate().rev() {
+            self.stack.push((child_path(&path, i), ch));
+        }
+        Some((path, node))
+    }
+}
+
+/// Iterates over nodes in post-order (children before parents).
+pub struct Postorder<'a, T, A, U = f64> {
+    stack: Vec<PostEntry<'a, T, A, U>>,
+}
+
+impl<'a, T, A, U> Iterator for Postorder<'a, T, A, U> {
+    type Item = (Vec<usize>, &'a Node<T, A, U>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, node, next) = self.stack.last_mut()?;
+            let node: &'a Node<T, A, U> = node;
+            if *next < node.children.len() {
+                let i = *next;
+                *next += 1;
+                let path = child_path(path, i);
+                self.stack.push((path, &node.children[i].1, 0));
+            } else {
+                let (path, node, _) = self.stack.pop()?;
+                return Some((path, node));
+            }
+        }
+    }
+}
+
+/// Iterates over nodes in breadth-first order.
+pub struct Bfs<'a, T, A, U = f64> {
+    queue: VecDeque<Entry<'a, T, A, U>>,
+}
+
+impl<'a, T, A, U> Iterator for Bfs<'a, T, A, U> {
+    type Item = (Vec<usize>, &'a Node<T, A, U>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.queue.pop_front()?;
+        for (i, (_, ch)) in node.children.iter().enumerate() {
+            self.queue.push_back((child_path(&path, i), ch));
+        }
+        Some((path, node))
+    }
+}
+
+/// Iterates mutably over nodes in pre-order (parents before children).
+pub struct PreorderMut<'a, T, A, U = f64> {
+    stack: Vec<EntryMut<'a, T, A, U>>,
+}
+
+impl<'a, T, A, U> Iterator for PreorderMut<'a, T, A, U> {
+    type Item = (Vec<usize>, NodeMut<'a, T, U>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.stack.pop()?;
+        let Node {max, data, children, ..} = node;
+        for (i, (_, ch)) in children.iter_mut().enumerate().rev() {
+            self.stack.push((child_path(&path, i), ch));
+        }
+        Some((path, NodeMut {max, data}))
+    }
+}
+
+struct Frame<'a, T, A, U = f64> {
+    path: Vec<usize>,
+    node: NodeMut<'a, T, U>,
+    children: ChildrenMut<'a, T, A, U>,
+}
+
+impl<'a, T, A, U> Frame<'a, T, A, U> {
+    fn new(path: Vec<usize>, node: &'a mut Node<T, A, U>) -> Frame<'a, T, A, U> {
+        let Node {max, data, children, ..} = node;
+        Frame {path, node: NodeMut {max, data}, children: children.iter_mut().enumerate()}
+    }
+}
+
+/// Iterates mutably over nodes in post-order (children before parents).
+pub struct PostorderMut<'a, T, A, U = f64> {
+    stack: Vec<Frame<'a, T, A, U>>,
+}
+
+impl<'a, T, A, U> Iterator for PostorderMut<'a, T, A, U> {
+    type Item = (Vec<usize>, NodeMut<'a, T, U>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let top = self.stack.last_mut()?;
+            if let Some((i, (_, ch))) = top.children.next() {
+                let path = child_path(&top.path, i);
+                self.stack.push(Frame::new(path, ch));
+            } else {
+                let frame = self.stack.pop()?;
+                return Some((frame.path, frame.node));
+            }
+        }
+    }
+}
+
+/// Iterates mutably over nodes in breadth-first order.
+pub struct BfsMut<'a, T, A, U = f64> {
+    queue: VecDeque<EntryMut<'a, T, A, U>>,
+}
+
+impl<'a, T, A, U> Iterator for BfsMut<'a, T, A, U> {
+    type Item = (Vec<usize>, NodeMut<'a, T, U>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, node) = self.queue.pop_front()?;
+        let Node {max, data, children, ..} = node;
+        for (i, (_, ch)) in children.iter_mut().enumerate() {
+            self.queue.push_back((child_path(&path, i), ch));
+        }
+        Some((path, NodeMut {max, data}))
+    }
+}
+
+fn child_path(path: &[usize], i: usize) -> Vec<usize> {
+    let mut path = path.to_vec();
+    path.push(i);
+    path
+}
+
+impl<T, A, U> Node<T, A, U> {
+    /// Returns an iterator over nodes in pre-order (parents before children).
+    pub fn iter_preorder(&self) -> Preorder<'_, T, A, U> {
+        Preorder {stack: vec![(vec![], self)]}
+    }
+
+    /// Returns an iterator over nodes in post-order (children before parents).
+    pub fn iter_postorder(&self) -> Postorder<'_, T, A, U> {
+        Postorder {stack: vec![(vec![], self, 0)]}
+    }
+
+    /// Returns an iterator over nodes in breadth-first order.
+    pub fn iter_bfs(&self) -> Bfs<'_, T, A, U> {
+        Bfs {queue: vec![(vec![], self)].into()}
+    }
+
+    /// Returns a mutable iterator over nodes in pre-order (parents before children).
+    pub fn iter_preorder_mut(&mut self) -> PreorderMut<'_, T, A, U> {
+        PreorderMut {stack: vec![(vec![], self)]}
+    }
+
+    /// Returns a mutable iterator over nodes in post-order (children before parents).
+    pub fn iter_postorder_mut(&mut self) -> PostorderMut<'_, T, A, U> {
+        PostorderMut {stack: vec![Frame::new(vec![], self)]}
+    }
+
+    /// Returns a mutable iterator over nodes in breadth-first order.
+    pub fn iter_bfs_mut(&mut self) -> BfsMut<'_, T, A, U> {
+        BfsMut {queue: vec![(vec![], self)].into()}
+    }
+}
+
+impl<T, A, U> Node<T, A, U> {
+    /// Visits nodes in pre-order (parents before children), with path from root.
+    ///
+    /// Returns `ControlFlow::Break` as soon as the function does,
+    /// without visiting the remaining nodes.
+    pub fn visit<B, F>(&self, mut f: F) -> ControlFlow<B>
+        where F: FnMut(&[usize], &Node<T, A, U>) -> ControlFlow<B>
+    {
+        self.visit_path(&mut vec![], &mut f)
+    }
+
+    fn visit_path<B, F>(&self, path: &mut Vec<usize>, f: &mut F) -> ControlFlow<B>
+        where F: FnMut(&[usize], &Node<T, A, U>) -> ControlFlow<B>
+    {
+        f(path, self)?;
+        for (i, (_, ch)) in self.children.iter().enumerate() {
+            path.push(i);
+            ch.visit_path(path, f)?;
+            path.pop();
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn traversal_orders() {
+        let mut ai = line_ai(1);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+
+        let pre: Vec<Vec<usize>> = root.iter_preorder().map(|(p, _)| p).collect();
+        assert_eq!(pre, vec![vec![], vec![0], vec![0, 0], vec![0, 1], vec![1], vec![1, 0], vec![1, 1]]);
+        let post: Vec<Vec<usize>> = root.iter_postorder().map(|(p, _)| p).collect();
+        assert_eq!(post, vec![vec![0, 0], vec![0, 1], vec![0], vec![1, 0], vec![1, 1], vec![1], vec![]]);
+        let bfs: Vec<Vec<usize>> = root.iter_bfs().map(|(p, _)| p).collect();
+        assert_eq!(bfs, vec![vec![], vec![0], vec![1], vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]]);
+
+        let post_mut: Vec<Vec<usize>> = root.iter_postorder_mut().map(|(p, _)| p).collect();
+        assert_eq!(post_mut, post);
+        for (path, node) in root.iter_preorder_mut() {
+            *node.data = path.len() as i32;
+        }
+        for (path, node) in root.iter_bfs_mut() {
+            assert_eq!(*node.data, path.len() as i32);
+        }
+    }
+
+    #[test]
+    fn visit_early_exit() {
+        let mut ai = line_ai(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let mut visited = 0;
+        let res = root.visit(|path, node| {
+            visited += 1;
+            if node.data == 1 && path.len() == 2 {ControlFlow::Break(path.to_vec())}
+            else {ControlFlow::Continue(())}
+        });
+        assert_eq!(res, ControlFlow::Break(vec![0, 1]));
+        assert_eq!(visited, 10);
+        assert_eq!(root.visit(|_, _| ControlFlow::<()>::Continue(())), ControlFlow::Continue(()));
+    }
+}
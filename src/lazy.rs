@@ -0,0 +1,212 @@
+//! Lazy child expansion.
+//!
+//! `Ai::sub_breadth` stores node data for every child,
+//! which dominates memory usage when there are many available actions.
+//! A `LazyNode` only stores the action and maximum utility of each child,
+//! while node data is generated on demand by executing the action again.
+//!
+//! Children are evaluated with `Ai::lazy_expand` and forced with `Ai::lazy_force`.
+//! Only forced children store node data and can be expanded further.
+//! This requires that executing an action is deterministic.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// A node with lazily forced children.
+#[derive(Clone, Debug)]
+pub struct LazyNode<T, A, U = f64> {
+    /// Stores maximum utility of itself or any children.
+    pub max: U,
+    /// Stores node data.
+    pub data: T,
+    /// Stores children, `None` when the node has not been expanded.
+    pub children: Option<Vec<LazyChild<T, A, U>>>,
+}
+
+/// A child of a lazy node.
+#[derive(Clone, Debug)]
+pub struct LazyChild<T, A, U = f64> {
+    /// The action leading to the child.
+    pub action: A,
+    /// Stores maximum utility of the child or any of its children.
+    pub max: U,
+    /// The forced child, `None` when node data has not been generated.
+    pub node: Option<LazyNode<T, A, U>>,
+}
+
+impl<T, A, U: Utility> LazyNode<T, A, U> {
+    /// Creates a new root.
+    ///
+    /// This sets the utility to unknown, like `Node::root`.
+    pub fn root(data: T) -> LazyNode<T, A, U> {
+        LazyNode {max: U::unknown(), data, children: None}
+    }
+
+    /// Returns the optimal child, if any.
+    ///
+    /// This has the same semantics as `Node::optimal`.
+    /// Does not require children to be forced.
+    pub fn optimal(&self) -> Option<usize> {
+        self.children.as_ref()?.iter().position(|ch| ch.max >= self.max)
+    }
+
+    /// Converts into a maximum tree, dropping children that are not forced.
+    ///
+    /// A node with dropped children is marked as partially expanded.
+    pub fn into_node(self) -> Node<T, A, U> {
+        let children = self.children.unwrap_or_default();
+        let partial = children.iter().any(|ch| ch.node.is_none());
+        Node {
+            max: self.max,
+            data: self.data,
+            children: children.into_iter()
+                .filter_map(|LazyChild {action, node, ..}| node.map(|n| (action, n.into_node())))
+                .collect(),
+            partial,
+        }
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Evaluates the utility of children for all available actions,
+    /// without storing node data.
+    pub fn lazy_expand(&mut self, root: &mut LazyNode<T, A, U>, depth: usize, ctx: &mut C) {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+        let max = root.max;
+
+        let mut children = vec![];
+        for a in (self.actions)(&root.data, ctx) {
+            if let Some(data) = self.try_execute(&root.data, &a, ctx) {
+                let utility = self.utility_with_settings(&data, depth + 1, ctx);
+                if utility > root.max {
+                    root.max = utility;
+                }
+
+                // Undo changes made to context to reset state.
+                self.revert(&data, ctx);
+
+                children.push(LazyChild {action: a, max: utility, node: None});
+            }
+        }
+        if let Some(k) = self.max_children_at(depth) {
+            if children.len() > k {
+                // Keep only the children with highest utility, see `AiSettings::max_children`.
+                let n = children.len();
+                let mut indices: Vec<usize> = (0..n).collect();
+                indices.sort_by(|&i, &j| children[j].max.partial_cmp(&children[i].max)
+                    .unwrap_or(std::cmp::Ordering::Equal));
+                let mut keep = vec![false; n];
+                for &i in &indices[..k] {keep[i] = true}
+                let mut i = 0;
+                children.retain(|_| {i += 1; keep[i - 1]});
+                self.pruned(&root.data, n - k);
+
+                // The eliminated children might have higher utility.
+                root.max = max;
+                for ch in &children {
+                    if ch.max > root.max {
+                        root.max = ch.max;
+                    }
+                }
+            }
+        }
+        root.children = Some(children);
+    }
+
+    /// Generates node data of a child by executing its action,
+    /// returning the forced child.
+    ///
+    /// Returns `None` if the node is not expanded, the index is out of bounds,
+    /// or the action fails to execute.
+    /// Only forced children are counted in analysis.
+    pub fn lazy_force<'a>(
+        &mut self,
+        root: &'a mut LazyNode<T, A, U>,
+        index: usize,
+        ctx: &mut C,
+    ) -> Option<&'a mut LazyNode<T, A, U>> {
+        let ch = root.children.as_mut()?.get_mut(index)?;
+        if ch.node.is_none() {
+            let data = self.try_execute(&root.data, &ch.action, ctx)?;
+
+            // Undo changes made to context to reset state.
+            self.revert(&data, ctx);
+
+            ch.node = Some(LazyNode {max: ch.max, data, children: None});
+            if self.settings.analysis {
+                self.analysis.node_count += 1;
+            }
+        }
+        ch.node.as_mut()
+    }
+
+    /// Same as `Ai::greedy`, but only forces the children that are followed.
+    pub fn lazy_greedy(&mut self, root: &mut LazyNode<T, A, U>, depth: usize, ctx: &mut C) {
+        let started = self.start_timer();
+        self.lazy_greedy_node(root, depth, ctx);
+        self.stop_timer(started);
+    }
+
+    fn lazy_greedy_node(&mut self, root: &mut LazyNode<T, A, U>, depth: usize, ctx: &mut C) {
+        self.lazy_expand(root, depth, ctx);
+
+        if depth >= self.settings.max_depth {return};
+        if self.limit_exceeded() {return};
+
+        let i = match root.optimal() {
+            Some(i) => i,
+            None => return,
+        };
+        let LazyNode {max: root_max, data: root_data, children} = root;
+        let ch = &mut children.as_mut().unwrap()[i];
+        if let Some(data) = self.try_execute(root_data, &ch.action, ctx) {
+            if ch.node.is_none() {
+                self.count_node(&data, &ch.action, ch.max, depth + 1);
+                ch.node = Some(LazyNode {max: ch.max, data, children: None});
+            }
+            let node = ch.node.as_mut().unwrap();
+            self.lazy_greedy_node(node, depth + 1, ctx);
+
+            // Undo changes made to context to reset state.
+            self.revert(&node.data, ctx);
+
+            // Update maximum utility since children are changed.
+            ch.max = node.max;
+            if ch.max > *root_max {
+                *root_max = ch.max;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn greedy_forces_followed_children() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        let mut root = LazyNode::root(0);
+        let mut pos = 0;
+        ai.lazy_greedy(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.analysis.node_count, 3);
+
+        let mut eager = Node::root(0);
+        ai.greedy(&mut eager, 0, &mut pos);
+        let lazy = root.into_node();
+        assert_eq!(lazy.max, eager.max);
+        assert_eq!(lazy.descendants(), 3);
+
+        let mut root = LazyNode::root(0);
+        ai.lazy_expand(&mut root, 0, &mut pos);
+        let i = root.optimal().unwrap();
+        assert_eq!(ai.lazy_force(&mut root, i, &mut pos).map(|n| n.data), Some(1));
+        assert!(ai.lazy_force(&mut root, 2, &mut pos).is_none());
+    }
+}
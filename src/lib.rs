@@ -55,18 +55,77 @@
 //! intended to be used as a core for more advanced custom algorithms:
 //!
 //! - `Ai::full` does a complete search, finding global maximum
+//! - `Ai::full_arena`, `Ai::greedy_arena` and `Ai::iterative_deepening_arena` construct the tree in a flat arena
+//! - `Ai::full_par` is the same as `full`, but expands children of root on separate threads (`parallel` feature)
 //! - `Ai::greedy` does a local search, finding local maximum
+//! - `Ai::lazy_greedy` is the same as `greedy`, but only stores node data of followed children
+//! - `Ai::epsilon_greedy` is a stochastic variant of `greedy`, for escaping local maxima
+//! - `Ai::softmax` is a stochastic variant of `greedy`, sampling children by utility
+//! - `Ai::greedy_restarts` runs `greedy` multiple times, keeping the best tree
+//! - `Ai::greedy_par` runs `greedy` with different random seeds on separate threads (`parallel` feature)
+//! - `Ai::beam` does a beam search, a middle ground between `greedy` and `full`
+//! - `Ai::iterative_deepening` repeats `full` with increasing depth, reusing the tree
+//! - `Ai::greedy_then_full` runs `greedy` for a trunk, then `full` from the trunk leaf
 //! - `Ai::sub_breadth` constructs children for every available action
+//! - `Ai::best_first` expands nodes in order of utility plus a heuristic
+//! - `Ai::uniform_cost` expands nodes in order of accumulated cost (Dijkstra)
+//! - `Ai::weighted_astar` expands nodes in order of accumulated cost plus weighted heuristic
+//! - `Ai::memory_bounded` expands nodes in order of utility, dropping leaves when memory is exceeded
+//! - `Ai::mcts` does a Monte Carlo Tree Search (UCT), for large action spaces
+//! - `Ai::simulated_annealing` mutates a sequence of actions, recording the best as a path
+//! - `Ai::tabu` does a tabu search, avoiding recently visited states
+//! - `Ai::cross_entropy` samples sequences of actions, refitting the distribution to the best
+//! - `Ai::refine` samples continuous actions coarsely, then refines around the best child
+//! - `Ai::realtime` interleaves limited lookahead with committing actions to context
+//! - `Ai::mpc` plans to a horizon, commits the first action and replans (receding-horizon control)
+//! - `Ai::minimax` does an adversarial two-player search with alpha-beta pruning
+//! - `Ai::expectimax` computes maximum expected utility, using chance nodes
+//! - `Ai::portfolio` runs multiple algorithms on clones of context, keeping the best tree
 //!
 //! The `full` and `greedy` algorithms assumes determinism and perfect information in context.
 //! Basically, it means they should only be used in simulations or controlled environments.
 //!
+//! Both return a `SearchOutcome` telling whether the search finished or stopped early,
+//! e.g. because a limit of memory was exceeded.
+//!
+//! To act step by step without rebuilding the tree, an `Agent` promotes the chosen child to root
+//! after each action and tops up the search to a horizon.
+//! For GUIs and notebooks, a `SearchSession` runs a search a few expansions at a time,
+//! such that the partial tree can be inspected in between.
+//!
 //! The `Ai::sub_breadth` is used as a common sub-procedure for several algorithms.
+//! To evaluate leaves beyond maximum depth by e.g. a learned value function instead of utility,
+//! set `Ai::value_estimator` to a `ValueEstimator`.
+//!
+//! To visualize a maximum tree, use `Node::to_dot` to export it as a GraphViz DOT graph.
+//! To distill a search into a learned heuristic or policy, `Node::write_training_csv`
+//! and `Node::write_training_jsonl` export features, best achievable utility and best action of nodes.
+//! For a quick look in the terminal, print `Node::display` as an indented tree.
+//! To debug a search, set `Ai::trace` to record executed actions and rebuild the tree step by step.
+//! To review what the AI agent actually did, set `Ai::audit` to record actions committed to context.
+//! To keep a human in the loop between planning and acting, set `Ai::approve`.
+//! To find a broken undo, set `Ai::undo_check` to verify that undo restores the context.
+//! For small contexts, `UndoStrategy::Clone` restores cloned snapshots instead of calling undo.
+//! To compare algorithms side by side over repeated trials, use `Ai::compare` with a `Portfolio`.
+//! As a baseline, `Ai::rollout` simulates a policy to a horizon and `Ai::evaluate_policy` averages repeated rollouts.
+//! For cooperating agents, `AiBuilder::joint_actions` plans over joint actions,
+//! while `AiBuilder::turn_taking` lets agents take turns by depth.
+//! For long horizons, `AiBuilder::macro_actions` executes a `MacroAction`,
+//! e.g. repeating an action for 10 steps, atomically as one edge in the tree.
 //!
 //! For non-determinism, the maximum utility becomes maximum expected utility.
-//! This requires constructing the maximum tree with custom algorithms.
+//! The `Ai::expectimax` algorithm supports this by modeling outcomes with chance nodes.
+//! With `AiBuilder::stochastic`, execute returns weighted outcomes that become chance nodes.
+//! Other kinds of non-determinism requires constructing the maximum tree with custom algorithms.
 //! For more information, see "Custom algorithms" below.
 //!
+//! ### Serialization
+//!
+//! With the `serde` feature, `Node`, `AiSettings` and `AiAnalysis` implement
+//! `Serialize` and `Deserialize` from [serde](https://serde.rs/).
+//! This makes it possible to store a constructed maximum tree and reload it later for offline analysis.
+//! To resume a long-running search after a process restart, store a `Checkpoint` and use `Ai::resume`.
+//!
 //! ### Differences from reward accumulation
 //!
 //! A maximum tree does not accumulate rewards over actions.
@@ -78,9 +137,34 @@
 //! Just add the reward to accumulated rewards so far.
 //! The accumulated rewards are stored as maximum utility.
 //!
+//! This is built into `AiBuilder::accumulate`, which wraps node data in `Accumulated`
+//! and sums rewards along the path, discounted by a factor `gamma`.
+//!
+//! For accumulated costs, `Ai::uniform_cost` subtracts the cost of the path from utility,
+//! expanding nodes in order of lowest accumulated cost.
+//! This finds shortest paths when the utility is the same for every goal.
+//!
 //! Optimization for final reward has special terminal semantics.
 //! For more information, see "Terminal semantics" below.
 //!
+//! ### Utility types
+//!
+//! By default, utility is `f64`.
+//! For exact comparisons, e.g. in puzzles, utility can be any type implementing `Utility`,
+//! such as ordered integers, which is supported by all algorithms that only compare utility.
+//! Algorithms that compute with utility, e.g. expected values in expectimax or averages in MCTS, require `f64`.
+//! For strict priorities between objectives, `LexUtility` compares them lexicographically.
+//!
+//! ### Execution errors
+//!
+//! By default, `Ai::execute` returns `Result<T, ()>`, where an error skips the child.
+//! To tell an illegal action apart from a failed simulation, use an error type implementing `ExecuteError`,
+//! such as `ActionError`, which is supported by all algorithms.
+//! Errors are collected in `AiAnalysis::errors` when analysis is activated,
+//! and an error that aborts stops the search and sets `AiAnalysis::aborted`.
+//! The aborting error is always collected and referred to by `AiAnalysis::abort_error`,
+//! such that `Ai::try_full`, `Ai::try_greedy` or `Ai::try_search` propagate it as an `Error`.
+//!
 //! ### Discounting action depth
 //!
 //! By default, more steps to complete the goal is not penalized.
@@ -90,6 +174,12 @@
 //!
 //! Since this is common behavior, one can activate this by setting
 //! `AiSettings::eps_depth` to e.g. `0.0000001`.
+//! Notice that integer utility types ignore `eps_depth`.
+//!
+//! Similarly, side effects can be penalized by setting `Ai::impact`,
+//! which measures the impact of a node, e.g. the distance from the initial state of the context.
+//! The impact is scaled by `AiSettings::impact_weight` and subtracted from utility,
+//! which encourages low-impact plans.
 //!
 //! ### Custom algorithms
 //!
@@ -99,10 +189,16 @@
 //!
 //! The maximum tree is designed to be convenient for composing different search algorithms.
 //!
+//! When editing trees by hand, use `Node::validate` to check the maximum tree invariant
+//! and `Node::recompute_max` to repair it.
+//!
 //! One can perform e.g. posterior safety analysis without side effects in the context.
 //!
 //! It is also possible to restore state of the context and continue search from any node,
 //! using a different search algorithm than the one used to construct the tree.
+//! For example, `Ai::resume_full` restores the context by replaying actions from root,
+//! continues a full search from a node and re-propagates maximum utility to root.
+//! To do this in custom algorithms, use `Ai::restore` and `Ai::rollback`.
 //! The final maximum tree can be used with any analysis algorithm.
 //!
 //! Under non-determinism or hidden states in the context,
@@ -121,6 +217,8 @@
 //! Under verification for safety, evaluation of the terminal semantics must be included.
 //! This library is not safe to use when the terminal semantics of a given application
 //! has been not been verified for safety.
+//! To check terminal nodes of a tree against application specific terminal semantics,
+//! use `safety::verify_terminals`, which reports violations with paths.
 //!
 //! When a node is terminal, which is the case for any global maximum
 //! that do not have any children with equal maximum utility,
@@ -137,6 +235,8 @@
 //! Instead, according to higher order utilitariansim, one should optimize for a cluster of goals
 //! where each goal is reachable from any other.
 //! For more information, see [Groupoid Assumption of Multi-Goal Optimization](https://github.com/advancedresearch/path_semantics/blob/master/papers-wip/groupoid-assumption-of-multi-goal-optimization.pdf).
+//! To optimize for a cluster of goals, use `AiBuilder::goal_cluster`,
+//! which aggregates utility of every goal, e.g. by the minimum.
 //!
 //! Although classical utility theory can be used to achieve a single goal,
 //! this does not guarantee that achieving the goal is meaningful.
@@ -175,6 +275,7 @@
 //! Licensed under either of
 //!  * Apache License, Version 2.0 ([LICENSE-APACHE](LICENSE-APACHE) or http://www.apache.org/licenses/LICENSE-2.0)
 //!  * MIT license ([LICENSE-MIT](LICENSE-MIT) or http://opensource.org/licenses/MIT)
+//!
 //! at your option.
 //!
 //! ### Contribution
@@ -186,17 +287,104 @@
 /// Reexports commonly used objects.
 pub mod prelude {
     pub use super::{Ai, AiAnalysis, AiSettings, Node};
+    pub use super::execute_error::{ActionError, ExecuteError};
+    pub use super::expectimax::NodeKind;
+    pub use super::hooks::Hooks;
+    pub use super::minimax::Player;
+    pub use super::reward::Accumulated;
+    pub use super::rng::Rng;
+    pub use super::undo_strategy::UndoStrategy;
+    pub use super::utility::{LexUtility, Utility};
+    pub use super::value::ValueEstimator;
 }
 
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use audit::AuditLog;
+use expectimax::{NodeKind, Risk};
+use mcts::Widening;
+use minimax::Player;
+use outcome::{SearchOutcome, StopReason};
+use rng::Rng;
+use schedule::Schedule;
+use depth_first::{DepthFirst, NodeStorage, Storage};
+use execute_error::ExecuteError;
+use hooks::Hooks;
+use trace::Trace;
+use undo_check::UndoCheck;
+use undo_strategy::UndoStrategy;
+use utility::Utility;
+use value::ValueEstimator;
+
+pub mod agent;
+pub mod arena;
+pub mod audit;
+pub mod best_first;
+pub mod builder;
+pub mod checkpoint;
+pub mod compare;
+pub mod cross_entropy;
+mod depth_first;
+pub mod diff;
+pub mod display;
+pub mod dot;
+pub mod error;
+pub mod execute_error;
+pub mod expectimax;
+pub mod goals;
+pub mod hooks;
+pub mod invariant;
+pub mod iter;
+pub mod lazy;
+pub mod macro_action;
+pub mod mcts;
+pub mod memory_bounded;
+pub mod minimax;
+pub mod mpc;
+pub mod multi_agent;
+pub mod outcome;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod pareto;
+pub mod plan;
+pub mod portfolio;
+pub mod realtime;
+pub mod refine;
+pub mod regret;
+pub mod report;
+pub mod reward;
+pub mod rng;
+pub mod rollout;
+pub mod safety;
+pub mod sampling;
+pub mod schedule;
+pub mod session;
+pub mod simulated_annealing;
+pub mod stochastic;
+pub mod tabu;
+pub mod tie_break;
+pub mod trace;
+pub mod training;
+pub mod tree_stats;
+pub mod undo_check;
+pub mod undo_strategy;
+pub mod uniform_cost;
+pub mod utility;
+pub mod value;
+
 /// Stores action node (represented as a maximum tree).
 ///
 /// Each node stores a maximum utility of itself or any children.
 ///
 /// A terminal node has higher utility than any other children.
 #[derive(Debug)]
-pub struct Node<T, A> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Node<T, A, U = f64> {
     /// Stores maximum utility of itself or any children.
-    pub max: f64,
+    pub max: U,
     /// Stores node data.
     pub data: T,
     /// Stores child nodes.
@@ -206,19 +394,26 @@ pub struct Node<T, A> {
     /// This means the action should be unique among the children.
     /// This invariant is enforced by trusted search algorithms.
     /// Use `check_unique_actions` when the input is not trusted.
-    pub children: Vec<(A, Node<T, A>)>,
+    pub children: Vec<(A, Node<T, A, U>)>,
+    /// Whether the expansion of children was interrupted, e.g. by a limit or a transposition,
+    /// such that some available actions might have no child.
+    ///
+    /// A partially expanded node is expanded again by searches that reuse the tree.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub partial: bool,
 }
 
-impl<T, A> Node<T, A> {
+impl<T, A, U: Utility> Node<T, A, U> {
     /// Creates a new root.
     ///
-    /// This sets the utility to `NaN` (not a number).
+    /// This sets the utility to unknown, e.g. `NaN` (not a number) for `f64`.
     /// There are no children, which must be added through search.
-    pub fn root(data: T) -> Node<T, A> {
+    pub fn root(data: T) -> Node<T, A, U> {
         Node {
-            max: std::f64::NAN,
+            max: U::unknown(),
             data,
-            children: vec![]
+            children: vec![],
+            partial: false,
         }
     }
 
@@ -232,9 +427,9 @@ impl<T, A> Node<T, A> {
         use std::collections::HashSet;
 
         let mut hash_set = HashSet::new();
-        for &(ref a, _) in &self.children {
+        for (a, _) in &self.children {
             if hash_set.contains(a) {return false}
-            hash_set.insert(a.clone());
+            hash_set.insert(a);
         }
         true
     }
@@ -252,6 +447,10 @@ impl<T, A> Node<T, A> {
     ///
     /// Returns `None` if no children has higher utility.
     /// This means the node is terminal.
+    ///
+    /// When several children have maximum utility, the first one is returned.
+    /// Use `Node::optimal_with` to control tie-breaking,
+    /// or `Node::all_optimal` to get all of them.
     pub fn optimal(&self) -> Option<usize> {
         for (i, ch) in self.children.iter().enumerate() {
             if ch.1.max >= self.max {return Some(i)}
@@ -260,39 +459,374 @@ impl<T, A> Node<T, A> {
     }
 
     /// Returns optimal path from root.
+    ///
+    /// Use `Node::plan` to get the actions and utilities along the path.
     pub fn optimal_path(&self) -> Vec<usize> {
         let mut node = self;
         let mut res = vec![];
-        loop {
-            if let Some(i) = node.optimal() {
-                node = &node.children[i].1;
-                res.push(i);
-            } else {
-                break;
-            }
+        while let Some(i) = node.optimal() {
+            node = &node.children[i].1;
+            res.push(i);
         }
         res
     }
+
+    /// Returns the `k` best paths from root, with utility.
+    ///
+    /// A path ends at a terminal node, which has no children with equal or greater utility.
+    /// The utility of a path is the maximum utility of the terminal node,
+    /// which is the utility of the node itself.
+    /// Paths are sorted by descending utility, such that the first path is optimal.
+    /// Paths with equal utility are sorted in the order of children.
+    pub fn optimal_paths(&self, k: usize) -> Vec<(U, Vec<usize>)> {
+        let mut paths: Vec<(U, Vec<usize>)> = self.iter_preorder()
+            .filter(|(_, node)| node.terminal())
+            .map(|(path, node)| (node.max, path))
+            .collect();
+        paths.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        paths.truncate(k);
+        paths
+    }
+
+    /// Returns the best achievable utility and continuation when a prefix of path is taken.
+    ///
+    /// This answers counterfactual questions about the tree, e.g. what if another action was chosen.
+    /// The continuation is the optimal path from the node at end of prefix.
+    /// Returns `None` if an index is out of bounds.
+    pub fn counterfactual(&self, path_prefix: &[usize]) -> Option<(U, Vec<usize>)> {
+        let node = self.get(path_prefix)?;
+        Some((node.max, node.optimal_path()))
+    }
+
+    /// Returns node at end of path of child indices.
+    ///
+    /// Returns `None` if an index is out of bounds.
+    pub fn get(&self, path: &[usize]) -> Option<&Node<T, A, U>> {
+        let mut node = self;
+        for &i in path {
+            node = &node.children.get(i)?.1;
+        }
+        Some(node)
+    }
+
+    /// Returns a mutable reference to node at end of path of child indices.
+    ///
+    /// Returns `None` if an index is out of bounds.
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut Node<T, A, U>> {
+        let mut node = self;
+        for &i in path {
+            node = &mut node.children.get_mut(i)?.1;
+        }
+        Some(node)
+    }
+
+    /// Returns node at end of sequence of actions.
+    ///
+    /// Returns `None` if there is no child with the action.
+    pub fn get_by_actions(&self, actions: &[A]) -> Option<&Node<T, A, U>>
+        where A: PartialEq
+    {
+        let mut node = self;
+        for a in actions {
+            node = &node.children.iter().find(|(b, _)| b == a)?.1;
+        }
+        Some(node)
+    }
+
+    /// Removes all descendants iteratively, returning the number of removed nodes.
+    ///
+    /// Dropping a node drops descendants recursively,
+    /// which overflows the call stack for deep trees, e.g. with long horizons.
+    /// Call this before dropping a tree that might be deep.
+    pub fn clear(&mut self) -> usize {
+        let mut removed = 0;
+        let mut stack = std::mem::take(&mut self.children);
+        while let Some((_, mut node)) = stack.pop() {
+            removed += 1;
+            stack.append(&mut node.children);
+        }
+        removed
+    }
+
+    /// Returns node data, removing descendants iteratively, see `Node::clear`.
+    pub fn into_data(mut self) -> T {
+        self.clear();
+        self.data
+    }
+
+    /// Returns the child subtree at index, dropping the rest of the tree.
+    ///
+    /// This is used to reuse a subtree as new root after executing an action,
+    /// e.g. with `Ai::update`, instead of searching from scratch.
+    ///
+    /// Panics if index is out of bounds.
+    pub fn into_child(mut self, index: usize) -> Node<T, A, U> {
+        self.children.swap_remove(index).1
+    }
+
+    /// Returns the subtree at end of path of child indices, dropping the rest of the tree.
+    ///
+    /// Panics if an index is out of bounds.
+    pub fn into_descendant(self, path: &[usize]) -> Node<T, A, U> {
+        let mut node = self;
+        for &i in path {
+            node = node.into_child(i);
+        }
+        node
+    }
+
+    /// Removes subtrees with maximum utility below threshold.
+    ///
+    /// Returns the number of removed nodes,
+    /// which can be subtracted from `AiAnalysis::node_count`.
+    ///
+    /// The maximum utility of remaining nodes does not change,
+    /// since a child with the same maximum utility as its parent is never removed.
+    /// When the root is below threshold, all children are removed,
+    /// but the maximum utility of root is kept, because its own utility is not stored.
+    pub fn prune_below(&mut self, threshold: U) -> usize {
+        let mut removed = 0;
+        self.children.retain(|(_, ch)| {
+            if ch.max < threshold {
+                removed += 1 + ch.descendants();
+                false
+            } else {true}
+        });
+        for (_, ch) in &mut self.children {
+            removed += ch.prune_below(threshold);
+        }
+        removed
+    }
+
+    /// Removes all children that are not on a path achieving the maximum utility of root.
+    ///
+    /// Returns the number of removed nodes,
+    /// which can be subtracted from `AiAnalysis::node_count`.
+    /// All optimal paths are kept, not only the one returned by `Node::optimal_path`.
+    pub fn prune_to_optimal(&mut self) -> usize {
+        let max = self.max;
+        self.prune_below(max)
+    }
+
+    /// Returns a copy of the tree with only the paths achieving the maximum utility of root.
+    ///
+    /// This is the non-destructive version of `Node::prune_to_optimal`.
+    pub fn optimal_subtree(&self) -> Node<T, A, U>
+        where T: Clone, A: Clone
+    {
+        self.subtree_above(self.max)
+    }
+
+    fn subtree_above(&self, threshold: U) -> Node<T, A, U>
+        where T: Clone, A: Clone
+    {
+        Node {
+            max: self.max,
+            data: self.data.clone(),
+            children: self.children.iter()
+                .filter(|(_, ch)| ch.max >= threshold)
+                .map(|(a, ch)| (a.clone(), ch.subtree_above(threshold)))
+                .collect(),
+            partial: self.partial,
+        }
+    }
+
+    /// Merges another tree into this tree.
+    ///
+    /// Children with equal actions are merged recursively,
+    /// keeping the node data of this tree.
+    /// Other children are moved from the other tree.
+    /// The maximum utility is the highest of the two trees.
+    ///
+    /// Returns the number of nodes in the other tree that were merged with existing nodes,
+    /// including the root, which can be subtracted from `AiAnalysis::node_count`
+    /// when both trees are counted.
+    pub fn merge(&mut self, other: Node<T, A, U>) -> usize
+        where A: PartialEq
+    {
+        if other.max > self.max || self.max.is_unknown() {
+            self.max = other.max;
+        }
+        let mut merged = 1;
+        for (a, ch) in other.children {
+            match self.children.iter().position(|(b, _)| *b == a) {
+                Some(i) => merged += self.children[i].1.merge(ch),
+                None => self.children.push((a, ch)),
+            }
+        }
+        merged
+    }
+
+    /// Returns a mutable reference to node at end of path.
+    pub(crate) fn node_mut(&mut self, path: &[usize]) -> &mut Node<T, A, U> {
+        let mut node = self;
+        for &i in path {
+            node = &mut node.children[i].1;
+        }
+        node
+    }
+
+    /// Counts the number of nodes below this node.
+    pub(crate) fn descendants(&self) -> usize {
+        self.children.iter().map(|ch| 1 + ch.1.descendants()).sum()
+    }
+
+    /// Updates maximum utility from children, using an explicit stack.
+    ///
+    /// Children are moved out while visiting, like in `Ai::depth_first`.
+    pub(crate) fn update_max(&mut self) {
+        let children = std::mem::take(&mut self.children);
+        // Stores the visited child, the children left to visit and the visited children.
+        let mut stack = vec![(None, children.into_iter(), vec![])];
+        while let Some((_, left, _)) = stack.last_mut() {
+            if let Some((a, mut ch)) = left.next() {
+                let children = std::mem::take(&mut ch.children);
+                stack.push((Some((a, ch)), children.into_iter(), vec![]));
+                continue;
+            }
+
+            let (node, _, children) = stack.pop().expect("There should be a frame");
+            match node {
+                Some((a, mut ch)) => {
+                    ch.children = children;
+                    ch.max_from_children();
+                    let parent = stack.last_mut().expect("There should be a parent frame");
+                    parent.2.push((a, ch));
+                }
+                None => {
+                    self.children = children;
+                    self.max_from_children();
+                }
+            }
+        }
+    }
+
+    /// Updates maximum utility from children, without visiting descendants.
+    fn max_from_children(&mut self) {
+        for (_, ch) in &self.children {
+            if ch.max > self.max {
+                self.max = ch.max;
+            }
+        }
+    }
 }
 
 /// AI settings.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AiSettings {
     /// Maximum depth.
     pub max_depth: usize,
     /// Utility discount from action depth.
     ///
     /// This is usually a small positive number (e.g. `0.000001`).
+    ///
+    /// Integer utility types (e.g. `i32`) ignore this discount,
+    /// since it rounds to zero. Scale the utility by depth manually instead.
     pub eps_depth: f64,
     /// Whether to run analysis.
     pub analysis: bool,
     /// Eliminate unexplored actions when using greedy search.
     pub greed_elim: bool,
+    /// The minimum improvement of utility for greedy search to continue descending.
+    ///
+    /// Greedy search stops when the chosen child improves utility by less than this,
+    /// which avoids taking tiny improvements on nearly flat utility landscapes.
+    /// The default is `0`, which continues as long as utility does not decrease.
+    /// Improvements are computed after converting to `f64`, see `Utility::to_f64`.
+    pub min_gain: f64,
     /// A limit to estimated memory usage,
     /// causing the search to terminate.
     ///
     /// This limit is only checked occationally, e.g. after breadth search,
     /// so actual memory usage before termination will exceed limit.
     pub max_mib: Option<f64>,
+    /// A limit to time spent on search,
+    /// causing the search to terminate.
+    ///
+    /// This limit is only checked occationally, e.g. after breadth search,
+    /// so actual time spent will exceed limit.
+    pub max_time: Option<Duration>,
+    /// A limit to the number of nodes created in a search,
+    /// causing the search to terminate.
+    ///
+    /// Unlike `AiSettings::max_mib`, this does not depend on estimated memory usage
+    /// and is enforced whether or not analysis is activated.
+    /// The limit is checked before constructing each child in `Ai::sub_breadth`.
+    pub max_nodes: Option<usize>,
+    /// A limit to the number of children of each node.
+    ///
+    /// When set, `Ai::sub_breadth` keeps only the children with highest utility,
+    /// in the order of actions.
+    /// This keeps the tree narrow when there are many available actions.
+    /// With progressive widening, `Ai::mcts` stops widening at this limit instead.
+    pub max_children: Option<usize>,
+    /// Exploration constant used by Monte Carlo Tree Search.
+    ///
+    /// Higher values explores more, lower values exploits more.
+    /// The value should be adjusted to the scale of utility.
+    pub exploration: f64,
+    /// Progressive widening used by Monte Carlo Tree Search.
+    ///
+    /// When set, only a limited number of children are expanded at a node,
+    /// growing with the number of visits.
+    /// This is useful when there are many available actions.
+    pub widening: Option<Widening>,
+    /// Weight of heuristic used by weighted A*, see `Ai::weighted_astar`.
+    ///
+    /// The weight `1` gives optimal plans (A*), when the heuristic never overestimates.
+    /// Higher values expands fewer nodes, with plans costing up to this times the optimal plan.
+    pub astar_weight: f64,
+    /// Temperature used by softmax (Boltzmann) sampling.
+    ///
+    /// Higher values explores more, lower values approaches greedy search.
+    /// The value should be adjusted to the scale of utility.
+    pub temperature: f64,
+    /// Whether to use a transposition table in `Ai::full`.
+    ///
+    /// Requires `Ai::state_hash`.
+    /// This avoids expanding states reachable by multiple sequences of actions more than once.
+    /// A state that is reached again is kept as a leaf and marked as partially expanded,
+    /// see `Node::partial`.
+    pub transpositions: bool,
+    /// Aggregates values of outcomes at chance nodes in `Ai::expectimax`.
+    ///
+    /// The default is the expected value.
+    /// Use `Risk::WorstCase` or `Risk::CVaR` for risk-averse planning.
+    pub risk: Risk,
+    /// A flag for cooperative cancellation of search,
+    /// causing the search to terminate when set to `true`.
+    ///
+    /// The flag can be set from another thread.
+    /// It is checked before constructing each child in `Ai::sub_breadth`,
+    /// such that even a single large expansion can be interrupted,
+    /// and the partial tree constructed so far is kept.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Whether to skip actions returning to a state on the current path
+    /// in `Ai::full` and `Ai::greedy`.
+    ///
+    /// Requires `Ai::state_hash`.
+    /// This avoids oscillations, e.g. moving back and forth, which add nodes without new states.
+    pub avoid_cycles: bool,
+    /// Bounds utility to a range `(min, max)` after subtracting impact and before discounting by depth.
+    ///
+    /// This prevents runaway or infinite utility from dominating the search,
+    /// both for numerical stability and for keeping utility bounded.
+    pub utility_clamp: Option<(f64, f64)>,
+    /// Scales the impact subtracted from utility, see `Ai::impact`.
+    ///
+    /// The default is `1`.
+    pub impact_weight: f64,
+    /// A utility that is good enough, causing the search to terminate when reached.
+    ///
+    /// When a node with at least this utility is created, `AiAnalysis::target_reached` is set,
+    /// and the optimal path of the tree leads to the node or one at least as good.
+    /// This finds a satisfying plan fast, instead of an optimal plan eventually.
+    /// Utility is compared after converting to `f64`, see `Utility::to_f64`.
+    pub target_utility: Option<f64>,
+    /// Overrides settings depending on depth, see `DepthSettings`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub schedule: Option<Schedule>,
 }
 
 impl AiSettings {
@@ -303,27 +837,141 @@ impl AiSettings {
             eps_depth,
             analysis: false,
             greed_elim: true,
+            min_gain: 0.0,
             max_mib: None,
+            max_time: None,
+            max_nodes: None,
+            max_children: None,
+            exploration: std::f64::consts::SQRT_2,
+            widening: None,
+            astar_weight: 1.0,
+            temperature: 1.0,
+            transpositions: false,
+            risk: Risk::Expected,
+            cancel: None,
+            avoid_cycles: false,
+            utility_clamp: None,
+            impact_weight: 1.0,
+            target_utility: None,
+            schedule: None,
         }
     }
 }
 
 /// Stores results from analysis.
-pub struct AiAnalysis {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AiAnalysis<E = ()> {
     /// Keeps track of maximum number of nodes.
     pub node_count: usize,
+    /// Collects errors from executing actions.
+    ///
+    /// Errors are only collected when `AiSettings::analysis` is activated,
+    /// except errors that abort the search, which are always collected.
+    pub errors: Vec<E>,
+    /// Whether the last search was aborted by an error, see `ExecuteError::abort`.
+    pub aborted: bool,
+    /// The index in `AiAnalysis::errors` of the first error that aborted the last search.
+    pub abort_error: Option<usize>,
+    /// Whether the last search reached `AiSettings::target_utility`.
+    pub target_reached: bool,
+    /// The reason the current search stopped early, recorded where the search stopped.
+    ///
+    /// This is `None` when the search did not stop early because of a limit.
+    pub stop: Option<StopReason>,
+    /// The number of nodes created in the current search.
+    ///
+    /// This is counted whether or not analysis is activated,
+    /// and is used to check `AiSettings::max_nodes`.
+    pub nodes_created: usize,
+    /// The wall-clock time of the last search.
+    pub search_time: Duration,
+    /// The number of nodes pruned in the current search.
+    ///
+    /// This counts children removed by `AiSettings::greed_elim`, `AiSettings::max_children`,
+    /// cutoffs of `Ai::minimax` and dropped leaves of `Ai::memory_bounded`.
+    /// It is only counted when analysis is activated.
+    pub pruned: usize,
+    /// The deepest depth of created nodes in the current search.
+    ///
+    /// This is tracked whether or not analysis is activated.
+    pub max_depth_reached: usize,
+    /// The number of actions that failed to execute in the current search.
+    pub failed_executes: usize,
+    /// The number of created nodes at each depth in the current search, excluding pruned nodes.
+    ///
+    /// Together with `max_depth_reached`, this shows where a search spends its effort.
+    pub depth_histogram: Vec<usize>,
+    /// The heap memory in bytes owned by nodes created in the current search.
+    ///
+    /// This is only counted when `Ai::data_size` or `Ai::action_size` is set.
+    pub heap_bytes: usize,
+    /// Stores the time when the current search started.
+    ///
+    /// This is used to check `AiSettings::max_time`.
+    /// It is not serialized, since an instant is only meaningful in the current process.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub search_start: Option<Instant>,
+    /// Counters shared with other workers, when searching on a worker thread.
+    #[cfg(feature = "parallel")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) worker: Option<parallel::WorkerCounters>,
+}
+
+impl<E> Default for AiAnalysis<E> {
+    fn default() -> AiAnalysis<E> {AiAnalysis::new()}
 }
 
-impl AiAnalysis {
+impl<E> AiAnalysis<E> {
     /// Creates new AI analysis.
-    pub fn new() -> AiAnalysis {
+    pub fn new() -> AiAnalysis<E> {
         AiAnalysis {
             node_count: 0,
+            errors: vec![],
+            aborted: false,
+            abort_error: None,
+            target_reached: false,
+            stop: None,
+            nodes_created: 0,
+            search_time: Duration::from_secs(0),
+            pruned: 0,
+            max_depth_reached: 0,
+            failed_executes: 0,
+            depth_histogram: vec![],
+            heap_bytes: 0,
+            search_start: None,
+            #[cfg(feature = "parallel")]
+            worker: None,
+        }
+    }
+
+    /// Returns the mean heap memory in bytes of nodes created in the current search.
+    pub fn mean_heap_size(&self) -> usize {
+        let created: usize = self.depth_histogram.iter().sum();
+        self.heap_bytes.checked_div(created).unwrap_or(0)
+    }
+
+    /// Counts a created node at some depth, owning some heap memory.
+    pub(crate) fn count_node(&mut self, depth: usize, heap: usize) {
+        self.node_count += 1;
+        self.heap_bytes += heap;
+        if self.depth_histogram.len() <= depth {
+            self.depth_histogram.resize(depth + 1, 0);
+        }
+        self.depth_histogram[depth] += 1;
+    }
+
+    /// Counts a pruned node at some depth, owning some heap memory.
+    pub(crate) fn count_pruned(&mut self, depth: usize, heap: usize) {
+        self.node_count -= 1;
+        self.pruned += 1;
+        self.heap_bytes = self.heap_bytes.saturating_sub(heap);
+        if let Some(n) = self.depth_histogram.get_mut(depth) {
+            *n = n.saturating_sub(1);
         }
     }
 }
 
-impl AiAnalysis {
+impl<E> AiAnalysis<E> {
     /// Estimates the maximum memory usage of nodes in Gibibytes.
     pub fn gib(&self, node_size: usize) -> f64 {
         (self.node_count as f64 * node_size as f64) / 1073741824.0
@@ -340,6 +988,53 @@ impl AiAnalysis {
     }
 }
 
+/// Children of a node, paired with the actions leading to them.
+pub type Children<T, A, U = f64> = Vec<(A, Node<T, A, U>)>;
+
+/// Calculates utility from data and context.
+///
+/// This is a closure, such that it can capture configuration, e.g. utility weights.
+pub type UtilityFn<T, C, U = f64> = Box<dyn Fn(&T, &C) -> U + Send + Sync>;
+
+/// Returns a list of possible actions from data and context.
+pub type ActionsFn<T, A, C> = Box<dyn Fn(&T, &C) -> Vec<A> + Send + Sync>;
+
+/// Executes an action, returning new node data.
+pub type ExecuteFn<T, A, C, E = ()> = Box<dyn Fn(&T, &A, &mut C) -> Result<T, E> + Send + Sync>;
+
+/// Undoes change made to context.
+pub type UndoFn<T, C> = Box<dyn Fn(&T, &mut C) + Send + Sync>;
+
+/// Estimates a value from data and context, see `Ai::heuristic` and `Ai::impact`.
+pub type HeuristicFn<T, C> = Box<dyn Fn(&T, &C) -> f64 + Send + Sync>;
+
+/// Computes an upper bound of utility from data, context and depth, see `Ai::bound`.
+pub type BoundFn<T, C, U = f64> = Box<dyn Fn(&T, &C, usize) -> U + Send + Sync>;
+
+/// Decides which player chooses action, see `Ai::player`.
+pub type PlayerFn<T, C> = Box<dyn Fn(&T, &C) -> Player + Send + Sync>;
+
+/// Decides the kind of a node, see `Ai::node_kind`.
+pub type NodeKindFn<T, A, C> = Box<dyn Fn(&T, &C) -> NodeKind<A> + Send + Sync>;
+
+/// Computes a hash of data and context, see `Ai::state_hash`.
+pub type StateHashFn<T, C> = Box<dyn Fn(&T, &C) -> u64 + Send + Sync>;
+
+/// Computes the cost of an action from data and context, see `Ai::cost`.
+pub type CostFn<T, A, C> = Box<dyn Fn(&T, &A, &C) -> f64 + Send + Sync>;
+
+/// Scores an action from data and context, see `Ai::order`.
+pub type OrderFn<T, A, C> = Box<dyn Fn(&T, &C, &A) -> f64 + Send + Sync>;
+
+/// Tests data and context, see `Ai::is_goal`.
+pub type GoalFn<T, C> = Box<dyn Fn(&T, &C) -> bool + Send + Sync>;
+
+/// Tests an action from data and context, see `Ai::forbid` and `Ai::approve`.
+pub type ActionTestFn<T, A, C> = Box<dyn Fn(&T, &A, &C) -> bool + Send + Sync>;
+
+/// Returns heap memory in bytes, see `Ai::data_size` and `Ai::action_size`.
+pub type SizeFn<X> = Box<dyn Fn(&X) -> usize + Send + Sync>;
+
 /// AI setup.
 ///
 /// Provides a common setup for different search algorithms.
@@ -361,166 +1056,1901 @@ impl AiAnalysis {
 /// This stores the data that is necessary to calculate utility.
 /// The context is modified by actions when exploring,
 /// but these changes are undone when rolling back changes.
-pub struct Ai<T, A, C> {
+pub struct Ai<T, A, C, U = f64, E = ()> {
     /// Calculates utility from data and context.
-    pub utility: fn(&T, &C) -> f64,
+    pub utility: UtilityFn<T, C, U>,
     /// Returns a list of possible actions.
-    pub actions: fn(&T, &C) -> Vec<A>,
+    pub actions: ActionsFn<T, A, C>,
     /// Executes an action, returning new node data.
-    pub execute: fn(&T, a: &A, &mut C) -> Result<T, ()>,
+    ///
+    /// When the action can not be executed, an error is returned and the child is skipped.
+    /// See `ExecuteError` for how errors are handled.
+    pub execute: ExecuteFn<T, A, C, E>,
     /// Undoes change made to context.
     ///
     /// The data required to rollback delta changes
     /// must be stored in node data.
-    pub undo: fn(&T, &mut C),
+    /// This is not called with `UndoStrategy::Clone`, see `Ai::undo_strategy`.
+    pub undo: UndoFn<T, C>,
+    /// Estimates utility that can be gained from data and context.
+    ///
+    /// This is used by `Ai::best_first` to guide search,
+    /// and by `Ai::weighted_astar` to estimate remaining cost as the negative heuristic.
+    pub heuristic: Option<HeuristicFn<T, C>>,
+    /// Computes an upper bound of utility for a node and all its descendants.
+    ///
+    /// The arguments are node data, context and depth.
+    /// This is used by `Ai::full` to prune subtrees (branch-and-bound).
+    /// The bound must never be less than the utility computed with settings,
+    /// otherwise the global maximum might be missed.
+    pub bound: Option<BoundFn<T, C, U>>,
+    /// Decides which player chooses action at a node.
+    ///
+    /// This is used by `Ai::minimax`.
+    /// When this is not set, players alternate by depth.
+    pub player: Option<PlayerFn<T, C>>,
+    /// Decides whether a node is a decision node or a chance node.
+    ///
+    /// This is used by `Ai::expectimax`.
+    /// When this is not set, all nodes are decision nodes.
+    pub node_kind: Option<NodeKindFn<T, A, C>>,
+    /// Computes a hash that identifies the state of data and context.
+    ///
+    /// This is used by `Ai::tabu` to detect visited states,
+    /// by `Ai::realtime` to learn values of states,
+    /// by `Ai::uniform_cost` to expand each state only once,
+    /// and by `Ai::full` for transpositions (see `AiSettings::transpositions`).
+    pub state_hash: Option<StateHashFn<T, C>>,
+    /// Computes the cost of executing an action.
+    ///
+    /// The arguments are node data, action and context before executing the action.
+    /// This is used by `Ai::uniform_cost` to accumulate costs.
+    /// When this is not set, every action costs `1`.
+    pub cost: Option<CostFn<T, A, C>>,
+    /// Scores actions to expand promising children first.
+    ///
+    /// The arguments are node data, context and action.
+    /// Children are sorted by descending score in `Ai::sub_breadth`.
+    /// Good ordering improves pruning, e.g. with `Ai::bound` or `Ai::minimax`.
+    pub order: Option<OrderFn<T, A, C>>,
+    /// Returns `true` for terminal states of the environment, e.g. when a goal is reached.
+    ///
+    /// Nodes in terminal states are not expanded.
+    /// Unlike returning no actions, this makes it explicit that the episode is over.
+    pub is_goal: Option<GoalFn<T, C>>,
+    /// Returns `true` for actions that must never be executed, e.g. by safety constraints.
+    ///
+    /// The arguments are node data, action and context before executing the action.
+    /// This is checked before executing actions in all algorithms,
+    /// such that forbidden actions are never explored.
+    pub forbid: Option<ActionTestFn<T, A, C>>,
+    /// Returns `true` for actions that are approved to be committed to context,
+    /// e.g. by asking a human.
+    ///
+    /// The arguments are node data, action and context before executing the action.
+    /// This is consulted by `Ai::update`, `Ai::execute_path` and `Ai::realtime`,
+    /// but never during search, such that planning and acting are separated.
+    pub approve: Option<ActionTestFn<T, A, C>>,
+    /// Measures the impact or side effects of a node, e.g. by distance from the initial state.
+    ///
+    /// The impact is scaled by `AiSettings::impact_weight` and subtracted from utility
+    /// in `Ai::utility_with_settings`, before clamping and discounting by depth.
+    /// This encourages low-impact plans.
+    pub impact: Option<HeuristicFn<T, C>>,
+    /// Estimates the value of leaves deeper than maximum depth, used instead of utility.
+    ///
+    /// See `ValueEstimator`.
+    pub value_estimator: Option<Box<dyn ValueEstimator<T, C, U> + Send + Sync>>,
+    /// Returns the heap memory in bytes owned by node data, e.g. by strings or vectors.
+    ///
+    /// This is added to the size of nodes when estimating memory usage, see `Ai::node_size`.
+    pub data_size: Option<SizeFn<T>>,
+    /// Returns the heap memory in bytes owned by an action.
+    pub action_size: Option<SizeFn<A>>,
+    /// Records executed actions when set, see `Trace`.
+    pub trace: Option<Trace<A, U>>,
+    /// Records actions committed to context when set, see `AuditLog`.
+    pub audit: Option<AuditLog<A, U>>,
+    /// Verifies that undo restores the context when set, see `UndoCheck`.
+    pub undo_check: Option<UndoCheck<A, C>>,
+    /// Decides how to restore the context after executing actions, see `UndoStrategy`.
+    pub undo_strategy: UndoStrategy<C>,
     /// Stores AI settings.
     pub settings: AiSettings,
     /// Stores analysis.
-    pub analysis: AiAnalysis,
+    pub analysis: AiAnalysis<E>,
+    /// Callbacks fired during search.
+    pub hooks: Hooks<T, U>,
 }
 
-impl<T, A, C> Ai<T, A, C> {
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
     /// Computes the size of nodes in bytes.
+    ///
+    /// When `Ai::data_size` or `Ai::action_size` is set,
+    /// this includes the mean heap memory of nodes created in the current search.
     pub fn node_size(&self) -> usize {
-        std::mem::size_of::<Node<T, A>>()
+        std::mem::size_of::<Node<T, A, U>>() + self.analysis.mean_heap_size()
+    }
+
+    /// Counts a created node in analysis, including heap memory of data and action.
+    ///
+    /// This must be called after executing the action creating the node,
+    /// such that the node is recorded when tracing.
+    pub(crate) fn count_node(&mut self, data: &T, a: &A, utility: U, depth: usize) {
+        self.analysis.nodes_created += 1;
+        if let Some(trace) = &mut self.trace {trace.create(utility)};
+        if let Some(target) = self.settings.target_utility {
+            if utility.to_f64() >= target {self.analysis.target_reached = true};
+        }
+        if depth > self.analysis.max_depth_reached {
+            self.analysis.max_depth_reached = depth;
+        }
+        if self.settings.analysis {
+            let heap = self.data_size.as_ref().map(|f| f(data)).unwrap_or(0) +
+                       self.action_size.as_ref().map(|f| f(a)).unwrap_or(0);
+            self.analysis.count_node(depth, heap);
+        }
+    }
+
+    /// Counts a pruned leaf in analysis, see `AiAnalysis::pruned`.
+    pub(crate) fn count_pruned(&mut self, data: &T, a: &A, depth: usize) {
+        if self.settings.analysis {
+            let heap = self.data_size.as_ref().map(|f| f(data)).unwrap_or(0) +
+                       self.action_size.as_ref().map(|f| f(a)).unwrap_or(0);
+            self.analysis.count_pruned(depth, heap);
+        }
     }
 
     /// Calculates utility with extra terms computed from settings.
-    pub fn utility_with_settings(&self, data: &T, depth: usize, ctx: &C) -> f64 {
-        let utility = (self.utility)(data, ctx);
-        let discount_step = -self.settings.eps_depth * depth as f64;
-        utility + discount_step
+    pub fn utility_with_settings(&self, data: &T, depth: usize, ctx: &C) -> U {
+        self.with_settings((self.utility)(data, ctx), data, depth, ctx)
+    }
+
+    /// Adds extra terms computed from settings to utility.
+    pub(crate) fn with_settings(&self, utility: U, data: &T, depth: usize, ctx: &C) -> U {
+        let utility = match &self.impact {
+            Some(impact) => utility.penalize(self.settings.impact_weight * impact(data, ctx)),
+            None => utility,
+        };
+        let utility = match self.settings.utility_clamp {
+            Some((min, max)) => utility.clamp_to(min, max),
+            None => utility,
+        };
+        // The discount is summed along the path, so it is applied as a single step.
+        utility.discount(self.eps_depth_sum(0, depth), 1)
     }
 
     /// Updates context by tracing the optimal path.
-    pub fn update<'a>(&mut self, node: &'a Node<T, A>, ctx: &mut C) -> Option<usize> {
-        if let Some(i) = node.optimal() {
-            if (self.execute)(&node.data, &node.children[i].0, ctx).is_ok() {
-                Some(i)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+    ///
+    /// Returns `None` when the action is not approved, see `Ai::approve`.
+    pub fn update(&mut self, node: &Node<T, A, U>, ctx: &mut C) -> Option<usize> {
+        node.optimal().filter(|&i| self.commit(&node.data, &node.children[i].0, ctx).is_some())
     }
 
-    /// A sub-procedure constructing maximum tree of all available actions.
+    /// Updates context by executing actions along the optimal path from root.
     ///
-    /// Uses by other search algorithms.
-    pub fn sub_breadth(&mut self, root: &mut Node<T, A>, depth: usize, ctx: &mut C)
+    /// Stops when an action is not approved (see `Ai::approve`) or fails to execute.
+    /// Returns the executed actions with the utility of the resulting node data.
+    pub fn apply_optimal_path(&mut self, root: &Node<T, A, U>, ctx: &mut C) -> Vec<(A, U)>
         where A: Clone
     {
-        root.children.clear();
-        let actions = (self.actions)(&root.data, ctx);
-        for a in &actions {
-            if let Ok(data) = (self.execute)(&root.data, a, ctx) {
-                let utility = self.utility_with_settings(&data, depth + 1, ctx);
-                if utility > root.max {
-                    root.max = utility;
-                }
+        let mut applied = vec![];
+        let mut node = root;
+        while let Some(i) = node.optimal() {
+            let (a, ch) = &node.children[i];
+            let data = match self.commit(&node.data, a, ctx) {
+                Some(data) => data,
+                None => break,
+            };
+            applied.push((a.clone(), (self.utility)(&data, ctx)));
+            node = ch;
+        }
+        applied
+    }
 
-                // Undo changes made to context to reset state.
-                (self.undo)(&data, ctx);
+    /// Updates context by executing actions along a path of child indices.
+    ///
+    /// Stops when an action is not approved (see `Ai::approve`) or fails to execute,
+    /// or when the path is invalid.
+    /// Returns the number of actions committed to context.
+    pub fn execute_path(&mut self, root: &Node<T, A, U>, path: &[usize], ctx: &mut C) -> usize {
+        let mut node = root;
+        for (n, &i) in path.iter().enumerate() {
+            let (a, ch) = match node.children.get(i) {
+                Some(x) => x,
+                None => return n,
+            };
+            if self.commit(&node.data, a, ctx).is_none() {return n};
+            node = ch;
+        }
+        path.len()
+    }
 
-                root.children.push((a.clone(), Node {
-                    max: utility,
-                    data,
-                    children: vec![],
-                }));
+    /// Returns `true` if an action is approved to be committed to context, see `Ai::approve`.
+    pub fn approved(&self, data: &T, a: &A, ctx: &C) -> bool {
+        self.approve.as_ref().map(|f| f(data, a, ctx)).unwrap_or(true)
+    }
+
+    /// Executes an action that is not undone, recording it when `Ai::audit` is set.
+    ///
+    /// Returns `None` without executing when the action is not approved.
+    pub(crate) fn commit(&mut self, data: &T, a: &A, ctx: &mut C) -> Option<T> {
+        if !self.approved(data, a, ctx) {return None};
+        let data = self.try_execute(data, a, ctx)?;
+        if let Some(check) = &mut self.undo_check {check.commit()};
+        self.undo_strategy.pop();
+        if self.audit.is_some() {
+            let utility = (self.utility)(&data, ctx);
+            if let Some(audit) = &mut self.audit {audit.record(a, utility)};
+        }
+        Some(data)
+    }
 
-                if self.settings.analysis {
-                    self.analysis.node_count += 1;
+    /// Executes an action, collecting the error when it fails.
+    ///
+    /// When the error aborts the search, `AiAnalysis::aborted` is set.
+    /// Returns `None` without executing when the action is forbidden, see `Ai::forbid`.
+    pub(crate) fn try_execute(&mut self, data: &T, a: &A, ctx: &mut C) -> Option<T> {
+        if self.forbidden(data, a, ctx) {return None};
+        let before = self.undo_check.as_ref().map(|check| check.before(ctx));
+        let snapshot = self.undo_strategy.snapshot(ctx);
+        match (self.execute)(data, a, ctx) {
+            Ok(data) => {
+                if let Some(snapshot) = snapshot {self.undo_strategy.push(snapshot)};
+                if let Some(trace) = &mut self.trace {trace.execute(a)};
+                if let (Some(check), Some(before)) = (&mut self.undo_check, before) {
+                    check.execute(before, a);
+                }
+                Some(data)
+            }
+            Err(err) => {
+                if self.settings.analysis {self.analysis.failed_executes += 1};
+                if err.abort() {
+                    self.analysis.aborted = true;
+                    if self.analysis.abort_error.is_none() {
+                        self.analysis.abort_error = Some(self.analysis.errors.len());
+                    }
+                    self.analysis.errors.push(err);
+                } else if self.settings.analysis {
+                    self.analysis.errors.push(err);
                 }
+                None
             }
         }
     }
 
-    /// Returns `true` when estimated memory usage is exceeded, `false` otherwise.
+    /// Undoes changes made to context by executing an action, see `Ai::undo_strategy`.
+    pub(crate) fn revert(&mut self, data: &T, ctx: &mut C) {
+        if let Some(trace) = &mut self.trace {trace.undo()};
+        match self.undo_strategy.pop() {
+            Some(snapshot) => *ctx = snapshot,
+            None => (self.undo)(data, ctx),
+        }
+        if let Some(check) = &mut self.undo_check {check.undo(ctx)};
+    }
+
+    /// Executes actions along path from root, to restore context of a node.
     ///
-    /// Returns `false` when analysis is deactivated.
-    pub fn memory_exceeded(&self) -> bool {
-        if self.settings.analysis {
-            if let Some(limit) = self.settings.max_mib {
-                self.analysis.mib(self.node_size()) >= limit
-            } else {false}
-        } else {false}
+    /// Returns the number of actions executed.
+    /// When this is less than the length of path, an action failed.
+    pub(crate) fn replay(&mut self, root: &Node<T, A, U>, path: &[usize], ctx: &mut C) -> usize {
+        let mut node = root;
+        for (k, &i) in path.iter().enumerate() {
+            if self.try_execute(&node.data, &node.children[i].0, ctx).is_none() {return k};
+            node = &node.children[i].1;
+        }
+        path.len()
     }
 
-    /// Only picks choices that increases utility.
+    /// Restores context of the node at end of path, by executing actions from root.
     ///
-    /// In order to find global maximum, it requires utility gradient to be convex.
-    pub fn greedy(&mut self, root: &mut Node<T, A>, depth: usize, ctx: &mut C)
-        where A: Clone
-    {
-        if root.max.is_nan() {
-            root.max = self.utility_with_settings(&root.data, depth, ctx);
+    /// The context must be in the state of root.
+    /// Returns `false` if the path is invalid or an action fails to execute,
+    /// in which case the actions executed so far are undone and the context is unchanged.
+    /// Use `Ai::rollback` to undo the changes, e.g. after continuing search from the node.
+    pub fn restore(&mut self, root: &Node<T, A, U>, path: &[usize], ctx: &mut C) -> bool {
+        if root.get(path).is_none() {return false};
+        let n = self.replay(root, path, ctx);
+        if n < path.len() {
+            self.rollback(root, &path[..n], ctx);
+            false
+        } else {true}
+    }
+
+    /// Undoes changes made by `Ai::restore`, in reverse order.
+    ///
+    /// The context must be in the state of the node at end of path.
+    pub fn rollback(&mut self, root: &Node<T, A, U>, path: &[usize], ctx: &mut C) {
+        let mut nodes = Vec::with_capacity(path.len());
+        let mut node = root;
+        for &i in path {
+            node = &node.children[i].1;
+            nodes.push(node);
         }
+        for node in nodes.into_iter().rev() {
+            self.revert(&node.data, ctx);
+        }
+    }
 
-        self.sub_breadth(root, depth, ctx);
+    /// Executes a random action, trying the others if execution fails.
+    ///
+    /// Returns the action and new node data.
+    /// Returns `None` if there are no actions that can be executed.
+    pub(crate) fn execute_random(&mut self, data: &T, ctx: &mut C, rng: &mut Rng) -> Option<(A, T)> {
+        let mut actions = (self.actions)(data, ctx);
+        while !actions.is_empty() {
+            let a = actions.swap_remove(rng.next_usize(actions.len()));
+            if let Some(new_data) = self.try_execute(data, &a, ctx) {
+                return Some((a, new_data));
+            }
+        }
+        None
+    }
 
-        if depth >= self.settings.max_depth {return};
-        if self.memory_exceeded() {return};
+    /// Inserts a sequence of actions as a path from root.
+    ///
+    /// Existing children are reused when they have equal actions.
+    /// Stops at the first action that fails to execute.
+    pub(crate) fn insert_actions(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        actions: &[A],
+        ctx: &mut C
+    )
+        where A: Clone + PartialEq
+    {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+        if actions.is_empty() {return};
 
-        if let Some(i) = root.optimal() {
-            let i = if self.settings.greed_elim {
-                if self.settings.analysis {
-                    self.analysis.node_count -= root.children.len() - 1;
+        let a = &actions[0];
+        if let Some(data) = self.try_execute(&root.data, a, ctx) {
+            let i = match root.children.iter().position(|(b, _)| b == a) {
+                Some(i) => i,
+                None => {
+                    let utility = self.utility_with_settings(&data, depth + 1, ctx);
+                    self.count_node(&data, a, utility, depth + 1);
+                    root.children.push((a.clone(), Node {
+                        max: utility,
+                        data,
+                        children: vec![],
+                        partial: false,
+                    }));
+                    root.children.len() - 1
                 }
-                root.children.swap(i, 0);
-                root.children.truncate(1);
-                0
-            } else {i};
+            };
 
-            let a = &root.children[i].0;
-            if let Ok(_) = (self.execute)(&root.data, a, ctx) {
-                let ch = &mut root.children[i].1;
-                self.greedy(ch, depth + 1, ctx);
+            let ch = &mut root.children[i].1;
+            self.insert_actions(ch, depth + 1, &actions[1..], ctx);
 
-                // Undo changes made to context to reset state.
-                (self.undo)(&ch.data, ctx);
+            // Undo changes made to context to reset state.
+            self.revert(&ch.data, ctx);
 
-                // Update maximum utility since children are changed.
-                if ch.max > root.max {
-                    root.max = ch.max;
-                }
+            // Update maximum utility since children are changed.
+            if ch.max > root.max {
+                root.max = ch.max;
             }
         }
     }
 
-    /// Performs a full construction of the entire maximum tree.
-    pub fn full(&mut self, root: &mut Node<T, A>, depth: usize, ctx: &mut C)
+    /// A sub-procedure constructing maximum tree of all available actions.
+    ///
+    /// Uses by other search algorithms.
+    pub fn sub_breadth(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C)
         where A: Clone
     {
-        if root.max.is_nan() {
-            root.max = self.utility_with_settings(&root.data, depth, ctx);
-        }
-
-        self.sub_breadth(root, depth, ctx);
-
-        if depth >= self.settings.max_depth {return};
-        if self.memory_exceeded() {return};
+        self.sub_breadth_in(&mut NodeStorage::new(root), depth, ctx);
+    }
 
-        for (ref a, ref mut ch) in &mut root.children {
-            if let Ok(_) = (self.execute)(&root.data, a, ctx) {
-                self.full(ch, depth + 1, ctx);
+    /// Same as `sub_breadth`, but constructs children of the current node in storage.
+    pub(crate) fn sub_breadth_in<S: Storage<T, A, U>>(&mut self, storage: &mut S, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        self.sub_breadth_with(storage, depth, ctx, &mut |_: &Self, _: &T, _: &C| true);
+    }
 
-                // Undo changes made to context to reset state.
-                (self.undo)(&ch.data, ctx);
+    /// Keeps only the children with highest utility, see `AiSettings::max_children`.
+    ///
+    /// The maximum utility of node before expanding is used to recompute maximum utility.
+    /// Returns which children were kept, in order, when any children were removed.
+    pub(crate) fn limit_children<S: Storage<T, A, U>>(
+        &mut self,
+        storage: &mut S,
+        depth: usize,
+        max: U,
+    ) -> Option<Vec<bool>> {
+        let k = match self.max_children_at(depth) {
+            Some(k) if storage.child_count() > k => k,
+            _ => return None,
+        };
+        let n = storage.child_count();
+        let mut indices: Vec<usize> = (0..n).collect();
+        indices.sort_by(|&i, &j| storage.child(j).2.partial_cmp(&storage.child(i).2)
+            .unwrap_or(std::cmp::Ordering::Equal));
+        let mut keep = vec![false; n];
+        for &i in &indices[..k] {keep[i] = true}
+        storage.retain(&mut |i, a, data, _| {
+            if !keep[i] {self.count_pruned(data, a, depth + 1)};
+            keep[i]
+        });
+        self.pruned(storage.data(), n - k);
 
-                // Update maximum utility since children are changed.
-                if ch.max > root.max {
-                    root.max = ch.max;
-                }
+        // The eliminated children might have higher utility.
+        let mut max = max;
+        for i in 0..k {
+            let ch_max = storage.child(i).2;
+            if ch_max > max {
+                max = ch_max;
             }
         }
+        storage.set_max(max);
+        Some(keep)
+    }
+
+    /// Same as `sub_breadth`, but skips children returning to a state on the current path.
+    ///
+    /// This is only done when `AiSettings::avoid_cycles` is activated and `Ai::state_hash` is set.
+    pub(crate) fn sub_breadth_acyclic<S: Storage<T, A, U>>(
+        &mut self,
+        storage: &mut S,
+        depth: usize,
+        ctx: &mut C,
+        path: &HashSet<u64>,
+    )
+        where A: Clone
+    {
+        if self.state_hash.is_some() && self.settings.avoid_cycles {
+            self.sub_breadth_with(storage, depth, ctx, &mut |ai: &Self, data: &T, ctx: &C| {
+                ai.state_hash.as_ref().map(|f| !path.contains(&f(data, ctx))).unwrap_or(true)
+            });
+        } else {
+            self.sub_breadth_in(storage, depth, ctx);
+        }
+    }
+
+    /// Same as `sub_breadth`, but calls expansion callbacks for every action and new child,
+    /// see `Expansion`.
+    ///
+    /// Children are limited by `AiSettings::max_children` after expanding.
+    pub(crate) fn sub_breadth_with<S: Storage<T, A, U>>(
+        &mut self,
+        storage: &mut S,
+        depth: usize,
+        ctx: &mut C,
+        f: &mut dyn Expansion<T, A, C, U, E>,
+    )
+        where A: Clone
+    {
+        let max = storage.max();
+        storage.clear();
+        storage.set_partial(false);
+        if self.goal_reached(storage.data(), ctx) {return};
+
+        let mut actions = (self.actions)(storage.data(), ctx);
+        if let Some(order) = &self.order {
+            let mut scored: Vec<(f64, A)> = actions.into_iter()
+                .map(|a| (order(storage.data(), ctx, &a), a))
+                .collect();
+            scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            actions = scored.into_iter().map(|(_, a)| a).collect();
+        }
+        for a in &actions {
+            if self.expansion_stopped() {
+                storage.set_partial(true);
+                break;
+            }
+            f.action(self, storage.data(), a, ctx);
+            if let Some(data) = self.try_execute(storage.data(), a, ctx) {
+                let mut utility = self.child_utility(&data, depth + 1, ctx);
+                let keep = f.child(self, &data, ctx, &mut utility);
+
+                // Undo changes made to context to reset state.
+                self.revert(&data, ctx);
+
+                if !keep {continue};
+                if utility > storage.max() {
+                    storage.set_max(utility);
+                }
+                self.improved(&data, utility, depth + 1);
+                self.count_node(&data, a, utility, depth + 1);
+                storage.push(a.clone(), data, utility);
+            }
+        }
+        self.expanded(storage.data(), storage.child_count(), depth);
+        if let Some(keep) = self.limit_children(storage, depth, max) {
+            f.retain(&keep);
+        }
+    }
+
+    /// Returns `true` when estimated memory usage is exceeded, `false` otherwise.
+    ///
+    /// Returns `false` when analysis is deactivated.
+    pub fn memory_exceeded(&self) -> bool {
+        if self.settings.analysis {
+            if let Some(limit) = self.settings.max_mib {
+                let (_, node_count) = self.search_counts();
+                // Same as `AiAnalysis::mib`, including nodes of other workers.
+                node_count as f64 * self.node_size() as f64 / 1048576.0 >= limit
+            } else {false}
+        } else {false}
+    }
+
+    /// Returns `true` when time spent on current search is exceeded, `false` otherwise.
+    pub fn time_exceeded(&self) -> bool {
+        match (self.settings.max_time, self.analysis.search_start) {
+            (Some(limit), Some(start)) => start.elapsed() >= limit,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` when any limit of search is exceeded, `false` otherwise.
+    ///
+    /// This includes when the search is aborted by an error, see `ExecuteError::abort`,
+    /// cancelled, see `AiSettings::cancel`, or reached its target, see `AiSettings::target_utility`.
+    /// The reason is recorded in `AiAnalysis::stop`, since the search stops here.
+    pub fn limit_exceeded(&mut self) -> bool {
+        self.publish_counts();
+        let reason = self.limit_reason();
+        self.record_stop(reason)
+    }
+
+    /// Returns the reason a limit of search is exceeded, without recording it.
+    pub(crate) fn limit_reason(&self) -> Option<StopReason> {
+        if self.analysis.aborted {Some(StopReason::Aborted)}
+        else if self.cancelled() {Some(StopReason::Cancelled)}
+        else if self.analysis.target_reached {Some(StopReason::TargetReached)}
+        else if let Some(reason) = self.shared_stop() {Some(reason)}
+        else if self.nodes_exceeded() {Some(StopReason::NodeLimit)}
+        else if self.memory_exceeded() {Some(StopReason::MemoryLimit)}
+        else if self.time_exceeded() {Some(StopReason::TimeLimit)}
+        else {None}
+    }
+
+    /// Returns `true` when expanding children must stop, recording the reason.
+    ///
+    /// Memory and time are not checked, since they are checked between expansions.
+    pub(crate) fn expansion_stopped(&mut self) -> bool {
+        self.publish_counts();
+        let reason = if self.cancelled() {Some(StopReason::Cancelled)}
+            else if self.analysis.target_reached {Some(StopReason::TargetReached)}
+            else if let Some(reason) = self.shared_stop() {Some(reason)}
+            else if self.nodes_exceeded() {Some(StopReason::NodeLimit)}
+            else {None};
+        self.record_stop(reason)
+    }
+
+    /// Records the reason the current search stopped early, keeping the first reason.
+    ///
+    /// Returns `true` if there is a reason.
+    pub(crate) fn record_stop(&mut self, reason: Option<StopReason>) -> bool {
+        if self.analysis.stop.is_none() {self.analysis.stop = reason};
+        #[cfg(feature = "parallel")]
+        if let (Some(worker), Some(reason)) = (&self.analysis.worker, reason) {
+            // Other workers stop too.
+            worker.stop(reason);
+        }
+        reason.is_some()
+    }
+
+    /// Returns the number of created nodes and the number of nodes of the current search,
+    /// including other workers when searching on a worker thread.
+    pub(crate) fn search_counts(&self) -> (usize, usize) {
+        #[cfg(feature = "parallel")]
+        if let Some(worker) = &self.analysis.worker {
+            return worker.totals(self.analysis.nodes_created, self.analysis.node_count);
+        }
+        (self.analysis.nodes_created, self.analysis.node_count)
+    }
+
+    /// Makes the counts of this worker visible to other workers.
+    fn publish_counts(&mut self) {
+        #[cfg(feature = "parallel")]
+        if let Some(worker) = &mut self.analysis.worker {
+            worker.publish(self.analysis.nodes_created, self.analysis.node_count);
+        }
+    }
+
+    /// Returns the reason another worker stopped the search.
+    fn shared_stop(&self) -> Option<StopReason> {
+        #[cfg(feature = "parallel")]
+        if let Some(worker) = &self.analysis.worker {
+            return worker.stopped();
+        }
+        None
+    }
+
+    /// Returns `true` when an action is forbidden, see `Ai::forbid`.
+    pub fn forbidden(&self, data: &T, a: &A, ctx: &C) -> bool {
+        self.forbid.as_ref().map(|f| f(data, a, ctx)).unwrap_or(false)
+    }
+
+    /// Returns `true` when node data is in a terminal state, see `Ai::is_goal`.
+    pub fn goal_reached(&self, data: &T, ctx: &C) -> bool {
+        self.is_goal.as_ref().map(|f| f(data, ctx)).unwrap_or(false)
+    }
+
+    /// Returns `true` when the number of created nodes is exceeded, `false` otherwise.
+    pub fn nodes_exceeded(&self) -> bool {
+        self.settings.max_nodes.map(|n| self.search_counts().0 >= n).unwrap_or(false)
+    }
+
+    /// Returns `true` when search is cancelled, `false` otherwise.
+    pub fn cancelled(&self) -> bool {
+        self.settings.cancel.as_ref().map(|c| c.load(Ordering::Relaxed)).unwrap_or(false)
+    }
+
+    /// Starts measuring time of search, unless it is already started.
+    ///
+    /// Returns `true` if started by this call,
+    /// such that nested searches share the time limit of the outer search.
+    pub(crate) fn start_timer(&mut self) -> bool {
+        if self.analysis.search_start.is_none() {
+            self.analysis.search_start = Some(Instant::now());
+            self.analysis.aborted = false;
+            self.analysis.abort_error = None;
+            self.analysis.target_reached = false;
+            self.analysis.stop = None;
+            self.analysis.nodes_created = 0;
+            self.analysis.pruned = 0;
+            self.analysis.max_depth_reached = 0;
+            self.analysis.failed_executes = 0;
+            self.analysis.depth_histogram.clear();
+            self.analysis.heap_bytes = 0;
+            self.hooks.best = None;
+            self.hooks.expansions = 0;
+            self.hooks.last_progress = None;
+            true
+        } else {false}
+    }
+
+    /// Stops measuring time of search, if it was started by the same search.
+    pub(crate) fn stop_timer(&mut self, started: bool) {
+        if started {
+            if let Some(start) = self.analysis.search_start.take() {
+                self.analysis.search_time = start.elapsed();
+            }
+        }
+    }
+
+    /// Only picks choices that increases utility.
+    ///
+    /// In order to find global maximum, it requires utility gradient to be convex.
+    ///
+    /// Returns why the search stopped, see `SearchOutcome`.
+    pub fn greedy(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C) -> SearchOutcome
+        where A: Clone
+    {
+        let started = self.start_timer();
+        self.greedy_with(&mut NodeStorage::new(root), depth, ctx, &mut |node, _| node.optimal());
+        self.finish(started)
+    }
+
+    /// Same as `greedy`, but with probability `epsilon`
+    /// follows a random non-optimal child instead.
+    ///
+    /// When the node is terminal, a random child is followed with probability `epsilon`.
+    /// This helps escaping plateaus and local maxima.
+    pub fn epsilon_greedy(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        epsilon: f64,
+        rng: &mut Rng,
+    )
+        where A: Clone
+    {
+        let started = self.start_timer();
+        self.greedy_with(&mut NodeStorage::new(root), depth, ctx, &mut |node, _| node.epsilon_optimal(epsilon, rng));
+        self.stop_timer(started);
+    }
+
+    /// Runs greedy search multiple times, returning the tree with highest utility.
+    ///
+    /// The first run is a normal greedy search.
+    /// Every restart uses stochastic hill climbing,
+    /// which picks a random child among those that increase utility.
+    /// This explores different local maxima.
+    ///
+    /// The root factory is called for every run to construct a new root.
+    /// Trees that are not returned are counted as removed in analysis.
+    pub fn greedy_restarts(
+        &mut self,
+        mut root_factory: impl FnMut() -> Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        restarts: usize,
+        rng: &mut Rng,
+    ) -> Node<T, A, U>
+        where A: Clone
+    {
+        let started = self.start_timer();
+        let mut best = root_factory();
+        self.greedy_with(&mut NodeStorage::new(&mut best), depth, ctx, &mut |node, _| node.optimal());
+        for _ in 0..restarts {
+            if self.limit_exceeded() {break};
+
+            let mut root = root_factory();
+            self.greedy_with(&mut NodeStorage::new(&mut root), depth, ctx, &mut |node, utility| {
+                let improving: Vec<usize> = (0..node.child_count())
+                    .filter(|&i| node.child(i).2 > utility)
+                    .collect();
+                if improving.is_empty() {node.optimal()}
+                else {Some(improving[rng.next_usize(improving.len())])}
+            });
+            let worst = if root.max > best.max {std::mem::replace(&mut best, root)} else {root};
+            if self.settings.analysis {
+                self.analysis.node_count -= worst.descendants();
+            }
+        }
+        self.stop_timer(started);
+        best
+    }
+
+    /// Same as `greedy`, but uses a function to choose which child to follow.
+    ///
+    /// The function is called with the storage at the node and the utility of the node itself.
+    pub(crate) fn greedy_with<S, F>(&mut self, storage: &mut S, depth: usize, ctx: &mut C, choose: &mut F)
+        where A: Clone, S: Storage<T, A, U>, F: FnMut(&S, U) -> Option<usize>
+    {
+        self.depth_first(storage, depth, ctx, &mut Greedy {choose, path: HashSet::new()});
+    }
+
+    /// Expands only the `width` nodes with highest utility at each depth.
+    ///
+    /// This is a middle ground between `greedy` (width 1) and `full` (unbounded width).
+    /// Children that are not selected are kept as leaves.
+    ///
+    /// The context is restored for every expanded node by replaying actions from root.
+    pub fn beam(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C, width: usize)
+        where A: Clone
+    {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let started = self.start_timer();
+        let mut frontier: Vec<Vec<usize>> = vec![vec![]];
+        let mut depth = depth;
+        while !frontier.is_empty() {
+            let mut candidates: Vec<(U, Vec<usize>)> = vec![];
+            for path in &frontier {
+                let n = self.replay(root, path, ctx);
+                if n == path.len() {
+                    let node = root.node_mut(path);
+                    self.sub_breadth(node, depth, ctx);
+                    for (i, ch) in node.children.iter().enumerate() {
+                        let mut ch_path = path.clone();
+                        ch_path.push(i);
+                        candidates.push((ch.1.max, ch_path));
+                    }
+                }
+                self.rollback(root, &path[..n], ctx);
+            }
+
+            if depth >= self.settings.max_depth {break};
+            if self.limit_exceeded() {break};
+
+            candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+            candidates.truncate(self.depth_settings(depth + 1).width.unwrap_or(width));
+            frontier = candidates.into_iter().map(|(_, path)| path).collect();
+            depth += 1;
+        }
+
+        // Update maximum utility since children are changed.
+        root.update_max();
+        self.stop_timer(started);
+    }
+
+    /// Performs a full construction of the entire maximum tree.
+    ///
+    /// When `Ai::bound` is set, subtrees are skipped when their upper bound
+    /// is not higher than the best utility found so far (branch-and-bound).
+    ///
+    /// When `AiSettings::transpositions` is activated and `Ai::state_hash` is set,
+    /// states that are reached by multiple sequences of actions are only expanded once.
+    /// A state reached again at the same or larger depth than a completely searched subtree
+    /// is kept as a partially expanded leaf with its own utility,
+    /// since the maximum utility is reachable through the searched subtree.
+    ///
+    /// Returns why the search stopped, see `SearchOutcome`.
+    pub fn full(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C) -> SearchOutcome
+        where A: Clone
+    {
+        let started = self.start_timer();
+        self.depth_first(&mut NodeStorage::new(root), depth, ctx, &mut Full::new());
+        self.finish(started)
+    }
+
+    /// Runs depth-limited full searches with increasing maximum depth.
+    ///
+    /// Starts with maximum depth equal to `depth` and increases it
+    /// by one for every iteration, until `AiSettings::max_depth` is reached.
+    /// The tree is reused between iterations, such that nodes are only expanded once.
+    ///
+    /// When a limit of memory or time is exceeded, the search terminates.
+    /// Since every iteration but the last is complete,
+    /// the tree is complete down to at least one level above the deepest leaves.
+    pub fn iterative_deepening(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        self.iterative_deepening_in(&mut NodeStorage::new(root), depth, ctx);
+    }
+
+    /// Same as `iterative_deepening`, but constructs the tree in storage.
+    pub(crate) fn iterative_deepening_in<S: Storage<T, A, U>>(&mut self, storage: &mut S, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        let started = self.start_timer();
+        let max_depth = self.settings.max_depth;
+        for d in depth..=max_depth {
+            self.settings.max_depth = d;
+            self.deepen_in(storage, depth, ctx);
+            if self.limit_exceeded() {break};
+        }
+        self.settings.max_depth = max_depth;
+        self.stop_timer(started);
+    }
+
+    /// Runs `greedy` for `greedy_depth` levels, then `full` for `full_depth` levels
+    /// from the leaf of the greedy trunk.
+    ///
+    /// Both searches are constructed in the same tree,
+    /// where the children of the trunk leaf are reused by the full search.
+    /// The maximum utility is updated along the trunk afterwards.
+    pub fn greedy_then_full(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        greedy_depth: usize,
+        full_depth: usize,
+    )
+        where A: Clone
+    {
+        let started = self.start_timer();
+        let max_depth = self.settings.max_depth;
+        self.settings.max_depth = depth + greedy_depth;
+        self.greedy_with(&mut NodeStorage::new(root), depth, ctx, &mut |node, _| node.optimal());
+
+        if !self.limit_exceeded() {
+            // The trunk follows the only child that is expanded.
+            let mut path = vec![];
+            let mut node: &Node<T, A, U> = root;
+            while let Some(i) = node.children.iter().position(|ch| !ch.1.children.is_empty()) {
+                path.push(i);
+                node = &node.children[i].1;
+            }
+
+            let n = self.replay(root, &path, ctx);
+            if n == path.len() {
+                self.settings.max_depth = depth + path.len() + full_depth;
+                self.deepen(root.node_mut(&path), depth + path.len(), ctx);
+            }
+            self.rollback(root, &path[..n], ctx);
+
+            // Update maximum utility since children are changed.
+            root.update_max();
+        }
+
+        self.settings.max_depth = max_depth;
+        self.stop_timer(started);
+    }
+
+    /// Continues a full search from a node in an existing tree.
+    ///
+    /// The context is restored to the node by replaying actions along the path from root,
+    /// and reset afterwards.
+    /// Children of nodes that are already expanded are reused.
+    /// The maximum utility is re-propagated along the path to root.
+    ///
+    /// Returns `false` if an action along the path failed to execute,
+    /// in which case the tree is not changed.
+    /// Panics if the path does not exist in the tree.
+    pub fn resume_full(&mut self, root: &mut Node<T, A, U>, path: &[usize], depth: usize, ctx: &mut C) -> bool
+        where A: Clone
+    {
+        let started = self.start_timer();
+        let n = self.replay(root, path, ctx);
+        if n == path.len() {
+            self.deepen(root.node_mut(path), depth + path.len(), ctx);
+        }
+        self.rollback(root, &path[..n], ctx);
+
+        // Update maximum utility along path since children are changed.
+        for k in (0..n).rev() {
+            let node = root.node_mut(&path[..k]);
+            let max = node.children[path[k]].1.max;
+            if max > node.max {
+                node.max = max;
+            }
+        }
+        self.stop_timer(started);
+        n == path.len()
+    }
+
+    /// Recomputes utility of every node in an existing tree with a new utility function.
+    ///
+    /// The context is restored to each node by executing actions from root,
+    /// and maximum utility is re-propagated without expanding new nodes.
+    /// This is faster than a new search when tuning e.g. utility weights.
+    /// Children whose actions fail to execute keep their old utility.
+    /// The new utility function replaces `Ai::utility`.
+    pub fn rescore(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        new_utility: impl Fn(&T, &C) -> U + Send + Sync + 'static
+    ) {
+        self.utility = Box::new(new_utility);
+        let started = self.start_timer();
+        self.depth_first(&mut NodeStorage::new(root), depth, ctx, &mut Rescore);
+        self.stop_timer(started);
+    }
+
+    /// Same as `full`, but reuses children of nodes that are already expanded.
+    pub(crate) fn deepen(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        self.deepen_in(&mut NodeStorage::new(root), depth, ctx);
+    }
+
+    /// Same as `deepen`, but constructs the tree in storage.
+    fn deepen_in<S: Storage<T, A, U>>(&mut self, storage: &mut S, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        if storage.max().is_unknown() {
+            storage.set_max(self.utility_with_settings(storage.data(), depth, ctx));
+        }
+        self.depth_first(storage, depth, ctx, &mut Deepen);
+    }
+}
+
+impl<T, A, C, E: ExecuteError> Ai<T, A, C, f64, E> {
+    /// Same as `greedy`, but samples children with probability
+    /// proportional to `exp(utility / temperature)`.
+    ///
+    /// The temperature is set by `AiSettings::temperature`.
+    /// This gives smoother exploration than `epsilon_greedy`,
+    /// since children with higher utility are more likely to be followed.
+    pub fn softmax(&mut self, root: &mut Node<T, A>, depth: usize, ctx: &mut C, rng: &mut Rng)
+        where A: Clone
+    {
+        let started = self.start_timer();
+        let temperature = self.settings.temperature;
+        self.greedy_with(&mut NodeStorage::new(root), depth, ctx, &mut |node, _| {
+            if node.child_count() == 0 {return None};
+
+            // Subtract maximum utility to avoid overflow.
+            let n = node.child_count();
+            let max = (0..n).map(|i| node.child(i).2).fold(f64::NEG_INFINITY, f64::max);
+            let weights: Vec<f64> = (0..n)
+                .map(|i| ((node.child(i).2 - max) / temperature).exp())
+                .collect();
+            Some(rng.weighted(&weights))
+        });
+        self.stop_timer(started);
+    }
+}
+
+/// Depth-first callbacks of `Ai::greedy`.
+struct Greedy<'a, F> {
+    choose: &'a mut F,
+    /// The state hashes of nodes on the current path, used to avoid cycles.
+    path: HashSet<u64>,
+}
+
+impl<'a, T, A, C, U, E, S, F> DepthFirst<T, A, C, U, E, S> for Greedy<'a, F>
+    where A: Clone, U: Utility, E: ExecuteError, S: Storage<T, A, U>, F: FnMut(&S, U) -> Option<usize>
+{
+    fn enter(&mut self, ai: &mut Ai<T, A, C, U, E>, root: &mut S, depth: usize, ctx: &mut C) -> Vec<usize> {
+        if root.max().is_unknown() {
+            root.set_max(ai.utility_with_settings(root.data(), depth, ctx));
+        }
+        let utility = root.max();
+
+        enter_path(ai, &mut self.path, root.data(), ctx);
+        ai.sub_breadth_acyclic(root, depth, ctx, &self.path);
+
+        if depth >= ai.settings.max_depth {return vec![]};
+        if ai.limit_exceeded() {return vec![]};
+
+        let choice = (self.choose)(root, utility).filter(|&i| {
+            utility.is_unknown() || ai.settings.min_gain <= 0.0 ||
+            root.child(i).2.to_f64() - utility.to_f64() >= ai.settings.min_gain
+        });
+        match choice {
+            Some(i) if ai.settings.greed_elim => {
+                let removed = root.child_count() - 1;
+                root.retain(&mut |j, a, data, _| {
+                    if j != i {ai.count_pruned(data, a, depth + 1)};
+                    j == i
+                });
+                ai.pruned(root.data(), removed);
+
+                // The eliminated children might have higher utility.
+                if root.child(0).2 < root.max() {
+                    root.set_max(ai.utility_with_settings(root.data(), depth, ctx));
+                }
+                vec![0]
+            }
+            Some(i) => vec![i],
+            None => vec![],
+        }
+    }
+
+    fn leave(&mut self, ai: &mut Ai<T, A, C, U, E>, root: &mut S, _depth: usize, ctx: &C) {
+        leave_path(ai, &mut self.path, root.data(), ctx);
+    }
+}
+
+/// Depth-first callbacks of `Ai::rescore`.
+struct Rescore;
+
+impl<T, A, C, U, E, S> DepthFirst<T, A, C, U, E, S> for Rescore
+    where U: Utility, E: ExecuteError, S: Storage<T, A, U>
+{
+    fn enter(&mut self, ai: &mut Ai<T, A, C, U, E>, root: &mut S, depth: usize, ctx: &mut C) -> Vec<usize> {
+        // Maximum utility is re-propagated from children after visiting them.
+        root.set_max(ai.utility_with_settings(root.data(), depth, ctx));
+        (0..root.child_count()).collect()
+    }
+}
+
+/// Depth-first callbacks of `Ai::deepen`.
+struct Deepen;
+
+impl<T, A, C, U, E, S> DepthFirst<T, A, C, U, E, S> for Deepen
+    where A: Clone, U: Utility, E: ExecuteError, S: Storage<T, A, U>
+{
+    fn enter(&mut self, ai: &mut Ai<T, A, C, U, E>, root: &mut S, depth: usize, ctx: &mut C) -> Vec<usize> {
+        if root.child_count() == 0 || root.partial() {
+            // The children of a partially expanded node are constructed again.
+            let removed = root.clear();
+            if ai.settings.analysis {
+                ai.analysis.node_count -= removed;
+            }
+            ai.sub_breadth_in(root, depth, ctx);
+        }
+
+        if depth < ai.settings.max_depth && !ai.limit_exceeded() {
+            (0..root.child_count()).collect()
+        } else {vec![]}
+    }
+}
+
+/// Depth-first callbacks of `Ai::full`.
+pub(crate) struct Full<U> {
+    /// The best utility found so far, used by branch-and-bound.
+    best: Option<U>,
+    /// Transposition table of state hashes, storing the depth of completely searched subtrees.
+    table: HashMap<u64, usize>,
+    /// The visited nodes on the current path, to insert into table when leaving.
+    frames: Vec<TableFrame>,
+    /// The state hashes of nodes on the current path, used to avoid cycles.
+    path: HashSet<u64>,
+}
+
+impl<U: Utility> Full<U> {
+    pub(crate) fn new() -> Full<U> {
+        Full {best: None, table: HashMap::new(), frames: vec![], path: HashSet::new()}
+    }
+
+    /// Returns callbacks for searching a subtree on a worker thread,
+    /// starting with the best utility and the current path.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn fork(&self) -> Full<U> {
+        Full {best: self.best, table: HashMap::new(), frames: vec![], path: self.path.clone()}
+    }
+}
+
+/// Keeps track of whether the subtree of a visited node is completely searched.
+struct TableFrame {
+    /// The state hash of the node.
+    hash: Option<u64>,
+    /// Whether the node and the children left so far are completely searched.
+    complete: bool,
+    /// The number of children to visit.
+    visits: usize,
+    /// The number of children left after visiting.
+    left: usize,
+}
+
+impl<T, A, C, U, E, S> DepthFirst<T, A, C, U, E, S> for Full<U>
+    where A: Clone, U: Utility, E: ExecuteError, S: Storage<T, A, U>
+{
+    fn enter(&mut self, ai: &mut Ai<T, A, C, U, E>, root: &mut S, depth: usize, ctx: &mut C) -> Vec<usize> {
+        if root.max().is_unknown() {
+            root.set_max(ai.utility_with_settings(root.data(), depth, ctx));
+        }
+        enter_path(ai, &mut self.path, root.data(), ctx);
+
+        let hash = if ai.settings.transpositions {
+            ai.state_hash.as_ref().map(|f| f(root.data(), ctx))
+        } else {None};
+        if let Some(&d) = hash.and_then(|hash| self.table.get(&hash)) {
+            if d <= depth {
+                // The state is searched completely elsewhere, so the node is kept as a leaf.
+                root.clear();
+                root.set_partial(true);
+                if self.best.map(|b| root.max() > b).unwrap_or(true) {self.best = Some(root.max())};
+                self.frames.push(TableFrame {hash: None, complete: false, visits: 0, left: 0});
+                return vec![];
+            }
+        }
+
+        ai.sub_breadth_acyclic(root, depth, ctx, &self.path);
+        if self.best.map(|b| root.max() > b).unwrap_or(true) {self.best = Some(root.max())};
+
+        let order: Vec<usize> = if depth < ai.settings.max_depth && !ai.limit_exceeded() {
+            (0..root.child_count()).collect()
+        } else {vec![]};
+        // Children are not searched when a limit is exceeded.
+        let complete = !root.partial() && (depth >= ai.settings.max_depth || order.len() == root.child_count());
+        self.frames.push(TableFrame {hash, complete, visits: order.len(), left: 0});
+        order
+    }
+
+    fn descend(&mut self, ai: &mut Ai<T, A, C, U, E>, child: &S, depth: usize, ctx: &C) -> bool {
+        // Skip subtrees with upper bound not higher than best utility (branch-and-bound).
+        match (&ai.bound, self.best) {
+            (Some(bound), Some(b)) => bound(child.data(), ctx, depth) > b,
+            _ => true,
+        }
+    }
+
+    fn leave(&mut self, ai: &mut Ai<T, A, C, U, E>, root: &mut S, depth: usize, ctx: &C) {
+        let frame = self.frames.pop().expect("There should be a frame");
+        // Children that are skipped by branch-and-bound or fail to execute are not left.
+        let complete = frame.complete && frame.left == frame.visits;
+        if let (Some(hash), true) = (frame.hash, complete) {
+            self.table.insert(hash, depth);
+        }
+        if let Some(parent) = self.frames.last_mut() {
+            parent.left += 1;
+            parent.complete &= complete;
+        }
+        leave_path(ai, &mut self.path, root.data(), ctx);
+    }
+}
+
+/// Callbacks of `Ai::sub_breadth_with`.
+///
+/// A function of the AI setup, node data and context is called for every new child,
+/// which skips the child when it returns `false`.
+pub(crate) trait Expansion<T, A, C, U, E> {
+    /// Called before executing an action, with context in the state of the node.
+    fn action(&mut self, _ai: &Ai<T, A, C, U, E>, _data: &T, _a: &A, _ctx: &C) {}
+
+    /// Called for every new child, with context in the state of the child.
+    ///
+    /// The utility of the child can be adjusted.
+    /// Returns `false` to skip the child.
+    fn child(&mut self, ai: &Ai<T, A, C, U, E>, data: &T, ctx: &C, utility: &mut U) -> bool;
+
+    /// Called when children are removed by `AiSettings::max_children`,
+    /// with `true` for every kept child in order of construction.
+    fn retain(&mut self, _keep: &[bool]) {}
+}
+
+impl<T, A, C, U, E, F> Expansion<T, A, C, U, E> for F
+    where F: FnMut(&Ai<T, A, C, U, E>, &T, &C) -> bool
+{
+    fn child(&mut self, ai: &Ai<T, A, C, U, E>, data: &T, ctx: &C, _utility: &mut U) -> bool {
+        self(ai, data, ctx)
+    }
+}
+
+/// Collects a value for every new child, keeping values aligned with children.
+pub(crate) struct Collect<F, X> {
+    f: F,
+    /// The values of children.
+    pub(crate) values: Vec<X>,
+}
+
+impl<F, X> Collect<F, X> {
+    pub(crate) fn new(f: F) -> Collect<F, X> {Collect {f, values: vec![]}}
+}
+
+impl<T, A, C, U, E, F, X> Expansion<T, A, C, U, E> for Collect<F, X>
+    where F: FnMut(&Ai<T, A, C, U, E>, &T, &C) -> X
+{
+    fn child(&mut self, ai: &Ai<T, A, C, U, E>, data: &T, ctx: &C, _utility: &mut U) -> bool {
+        self.values.push((self.f)(ai, data, ctx));
+        true
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        let mut i = 0;
+        self.values.retain(|_| {i += 1; keep[i - 1]});
+    }
+}
+
+/// Adds state hash of node to the current path, when avoiding cycles.
+fn enter_path<T, A, C, U, E>(ai: &Ai<T, A, C, U, E>, path: &mut HashSet<u64>, data: &T, ctx: &C) {
+    if let (Some(state_hash), true) = (&ai.state_hash, ai.settings.avoid_cycles) {
+        path.insert(state_hash(data, ctx));
+    }
+}
+
+/// Removes state hash of node from the current path, when avoiding cycles.
+fn leave_path<T, A, C, U, E>(ai: &Ai<T, A, C, U, E>, path: &mut HashSet<u64>, data: &T, ctx: &C) {
+    if let (Some(state_hash), true) = (&ai.state_hash, ai.settings.avoid_cycles) {
+        path.remove(&state_hash(data, ctx));
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use super::*;
+
+    /// A line world where the agent moves left or right within `[-5, 5]`.
+    ///
+    /// Node data stores the last move, which is used to undo changes.
+    /// The maximum utility is at position `3`.
+    pub(crate) fn line_ai(max_depth: usize) -> Ai<i32, i32, i32> {
+        fn utility(_: &i32, pos: &i32) -> f64 {-(*pos - 3).abs() as f64}
+        fn actions(_: &i32, _: &i32) -> Vec<i32> {vec![-1, 1]}
+        fn execute(_: &i32, a: &i32, pos: &mut i32) -> Result<i32, ()> {
+            if (*pos + a).abs() > 5 {return Err(())};
+            *pos += a;
+            Ok(*a)
+        }
+        fn undo(a: &i32, pos: &mut i32) {*pos -= a}
+
+        Ai {
+            utility: Box::new(utility),
+            actions: Box::new(actions),
+            execute: Box::new(execute),
+            undo: Box::new(undo),
+            heuristic: None,
+            bound: None,
+            player: None,
+            node_kind: None,
+            state_hash: None,
+            cost: None,
+            order: None,
+            is_goal: None,
+            forbid: None,
+            approve: None,
+            impact: None,
+            value_estimator: None,
+            data_size: None,
+            action_size: None,
+            trace: None,
+            audit: None,
+            undo_check: None,
+            undo_strategy: UndoStrategy::Delta,
+            settings: AiSettings::new(max_depth, 0.0001),
+            analysis: AiAnalysis::new(),
+            hooks: Hooks::new(),
+        }
+    }
+
     #[test]
     fn it_works() {
         assert_eq!(2 + 2, 4);
     }
+
+    #[test]
+    fn full_finds_global_maximum() {
+        let mut ai = line_ai(4);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.optimal_path().len(), 3);
+        assert!((root.max - (-0.0003)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn full_branch_and_bound() {
+        let mut ai = line_ai(4);
+        // Utility can increase by at most one per remaining step.
+        ai.bound = Some(Box::new(|_, pos, depth| (-(*pos - 3).abs() + (4 - depth as i32 + 1)).min(0) as f64));
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.optimal_path().len(), 3);
+        assert!((root.max - (-0.0003)).abs() < 1e-9);
+        assert!(ai.analysis.node_count < 62);
+    }
+
+    #[test]
+    fn full_time_limit() {
+        let mut ai = line_ai(20);
+        ai.settings.max_time = Some(Duration::from_secs(0));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 2);
+        assert!(root.children.iter().all(|ch| ch.1.children.is_empty()));
+        assert!(ai.analysis.search_start.is_none());
+    }
+
+    #[test]
+    fn resume_full_from_leaf() {
+        let mut ai = line_ai(1);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        // Position 2 is reached by moving right twice.
+        let path = vec![1, 1];
+        assert!(root.max < -0.9);
+        ai.settings.max_depth = 3;
+        assert!(ai.resume_full(&mut root, &path, 0, &mut pos));
+        assert_eq!(pos, 0);
+        assert!(root.max > -0.001);
+        assert_eq!(root.optimal_path()[..2], path[..]);
+    }
+
+    #[test]
+    fn deep_horizon() {
+        // Recursive search or drop would overflow the call stack.
+        let mut ai = line_ai(100_000);
+        ai.settings.eps_depth = 0.0;
+        ai.utility = Box::new(|_, pos| *pos as f64);
+        ai.actions = Box::new(|_, _| vec![1]);
+        ai.execute = Box::new(|_, a, pos| {*pos += a; Ok(*a)});
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max, 100_001.0);
+        ai.greedy(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max, 100_001.0);
+        assert!(ai.resume_full(&mut root, &[], 0, &mut pos));
+        assert_eq!(root.max, 100_001.0);
+        root.update_max();
+        assert_eq!(root.clear(), 100_001);
+    }
+
+    #[test]
+    fn order_children() {
+        let mut ai = line_ai(2);
+        ai.order = Some(Box::new(|_, _, a| *a as f64));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.sub_breadth(&mut root, 0, &mut pos);
+        assert_eq!(root.children[0].0, 1);
+        assert_eq!(root.children[1].0, -1);
+    }
+
+    #[test]
+    fn full_avoid_cycles() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        ai.settings.avoid_cycles = true;
+        ai.state_hash = Some(Box::new(|_, pos| *pos as u64));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        // Only paths moving in one direction are constructed.
+        assert_eq!(ai.analysis.node_count, 10);
+        assert!(root.max > -0.001);
+    }
+
+    #[test]
+    fn full_max_nodes() {
+        let mut ai = line_ai(20);
+        ai.settings.max_nodes = Some(7);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.analysis.nodes_created, 7);
+        assert_eq!(root.descendants(), 7);
+    }
+
+    #[test]
+    fn full_max_children() {
+        let mut ai = line_ai(3);
+        ai.actions = Box::new(|_, _| vec![-2, -1, 1, 2]);
+        ai.settings.max_children = Some(2);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        let actions: Vec<i32> = root.children.iter().map(|ch| ch.0).collect();
+        assert_eq!(actions, vec![1, 2]);
+        assert!(root.iter_bfs().all(|(_, node)| node.children.len() <= 2));
+        assert!(root.max > -0.001);
+    }
+
+    #[test]
+    fn full_stops_at_goal() {
+        let mut ai = line_ai(6);
+        ai.is_goal = Some(Box::new(|_, pos| *pos == 3));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        let path = root.optimal_path();
+        assert_eq!(path.len(), 3);
+        assert!(root.get(&path).unwrap().children.is_empty());
+    }
+
+    #[test]
+    fn full_forbid() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        // Forbid moving to the right of position 1.
+        ai.forbid = Some(Box::new(|_, a, pos| *pos + a > 1));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert!((root.max - (-2.0 - 0.0001)).abs() < 1e-9);
+        assert!(ai.analysis.errors.is_empty());
+    }
+
+    #[test]
+    fn greedy_min_gain() {
+        let mut ai = line_ai(10);
+        // Utility improves by a tiny amount after position 2.
+        ai.utility = Box::new(|_, pos| if *pos <= 2 {*pos as f64} else {2.0 + 0.001 * *pos as f64});
+        ai.settings.eps_depth = 0.0;
+        ai.settings.min_gain = 0.5;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.greedy(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.optimal_path().len(), 3);
+    }
+
+    #[test]
+    fn clamp_utility() {
+        let mut ai = line_ai(4);
+        ai.utility = Box::new(|_, pos| if *pos == 3 {f64::INFINITY} else {-(*pos as f64 - 3.0).abs()});
+        ai.settings.eps_depth = 0.0;
+        ai.settings.utility_clamp = Some((-2.0, 10.0));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.max, 10.0);
+        assert!(root.children.iter().all(|(_, ch)| ch.max >= -2.0));
+    }
+
+    #[test]
+    fn partial_expansion() {
+        let mut ai = line_ai(2);
+        ai.settings.analysis = true;
+        ai.settings.max_nodes = Some(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(ai.analysis.nodes_created, 3);
+        assert!(!root.partial);
+        assert!(root.children[0].1.partial);
+        assert_eq!(root.children[0].1.children.len(), 1);
+
+        // Partially expanded nodes are expanded again.
+        ai.settings.max_nodes = None;
+        let started = ai.start_timer();
+        ai.deepen(&mut root, 0, &mut pos);
+        ai.stop_timer(started);
+        assert_eq!(pos, 0);
+        assert!(root.iter_preorder().all(|(_, node)| !node.partial));
+        assert_eq!(root.children[0].1.children.len(), 2);
+        assert_eq!(ai.analysis.node_count, 14);
+    }
+
+    #[test]
+    fn analysis_statistics() {
+        let mut ai = line_ai(6);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(ai.analysis.depth_histogram.iter().sum::<usize>(), ai.analysis.node_count);
+        assert_eq!(ai.analysis.depth_histogram[1], 2);
+        assert_eq!(ai.analysis.max_depth_reached, 7);
+        assert!(ai.analysis.failed_executes > 0);
+        assert_eq!(ai.analysis.failed_executes, ai.analysis.errors.len());
+
+        ai.analysis.node_count = 0;
+        let mut root = Node::root(0);
+        ai.greedy(&mut root, 0, &mut pos);
+        assert_eq!(ai.analysis.pruned, 3);
+        assert_eq!(ai.analysis.failed_executes, 0);
+        // Pruned nodes are removed from the histogram.
+        assert_eq!(ai.analysis.depth_histogram.iter().sum::<usize>(), ai.analysis.node_count);
+
+        ai.analysis.node_count = 0;
+        ai.settings.max_children = Some(1);
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(ai.analysis.pruned, 7);
+        assert_eq!(ai.analysis.depth_histogram.iter().sum::<usize>(), ai.analysis.node_count);
+    }
+
+    #[test]
+    fn heap_memory() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        let size = ai.node_size();
+        ai.data_size = Some(Box::new(|_| 100));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(ai.analysis.heap_bytes, 100 * ai.analysis.node_count);
+        assert_eq!(ai.node_size(), size + 100);
+    }
+
+    #[test]
+    fn counterfactual_prefix() {
+        let mut ai = line_ai(4);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        // Turning left first makes the goal take two more steps.
+        let left = root.children.iter().position(|(a, _)| *a == -1).unwrap();
+        let (max, path) = root.counterfactual(&[left]).unwrap();
+        assert!(max < root.max);
+        assert_eq!(path.len(), 4);
+        assert_eq!(root.counterfactual(&[]), Some((root.max, root.optimal_path())));
+        assert_eq!(root.counterfactual(&[9]), None);
+    }
+
+    #[test]
+    fn rescore_tree() {
+        let mut ai = line_ai(4);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let n = root.descendants();
+        ai.rescore(&mut root, 0, &mut pos, |_, pos| -(*pos + 2).abs() as f64);
+        assert_eq!(pos, 0);
+        assert_eq!(root.descendants(), n);
+
+        let mut fresh = Node::root(0);
+        ai.full(&mut fresh, 0, &mut pos);
+        assert_eq!(root.max, fresh.max);
+        assert_eq!(root.optimal_path(), fresh.optimal_path());
+    }
+
+    #[test]
+    fn full_target_utility() {
+        let mut ai = line_ai(6);
+        ai.settings.target_utility = Some(-1.5);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert!(ai.analysis.target_reached);
+        assert!(root.max >= -1.5 && root.max < -0.5);
+        let mut full = Node::root(0);
+        ai.settings.target_utility = None;
+        ai.full(&mut full, 0, &mut pos);
+        assert!(!ai.analysis.target_reached);
+        assert!(root.descendants() < full.descendants());
+    }
+
+    #[test]
+    fn interrupt_expansion() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut ai = line_ai(1);
+        ai.settings.cancel = Some(cancel.clone());
+        ai.actions = Box::new(|_, _| vec![1; 100]);
+        // Interrupt after the first action, like from another thread.
+        ai.execute = Box::new(move |_, a, pos| {
+            cancel.store(true, Ordering::Relaxed);
+            *pos += a;
+            Ok(*a)
+        });
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 1);
+    }
+
+    #[test]
+    fn approve_commit() {
+        let mut ai = line_ai(3);
+        // Only moving right from origin is approved.
+        ai.approve = Some(Box::new(|_, a, pos| *a > 0 && *pos == 0));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        // Search is not affected by approval.
+        assert_eq!(root.optimal_path().len(), 3);
+
+        let path = root.optimal_path();
+        assert_eq!(ai.execute_path(&root, &path, &mut pos), 1);
+        assert_eq!(pos, 1);
+        let node = root.get(&path[..1]).unwrap();
+        assert_eq!(ai.update(node, &mut pos), None);
+        assert_eq!(pos, 1);
+    }
+
+    #[test]
+    fn impact_penalty() {
+        let mut ai = line_ai(4);
+        ai.settings.eps_depth = 0.0;
+        // Moving left has a side effect.
+        ai.utility = Box::new(|_, pos| -(*pos - 1).abs() as f64);
+        ai.impact = Some(Box::new(|_, pos| if *pos < 0 {1.0} else {0.0}));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(ai.utility_with_settings(&0, 0, &-1), -3.0);
+        ai.settings.impact_weight = 0.5;
+        assert_eq!(ai.utility_with_settings(&0, 0, &-1), -2.5);
+        // The penalty can not push utility outside the clamped range.
+        ai.settings.utility_clamp = Some((-2.0, 0.0));
+        assert_eq!(ai.utility_with_settings(&0, 0, &-1), -2.0);
+        let path = root.optimal_path();
+        assert!((1..=path.len()).all(|n| root.get(&path[..n]).unwrap().max == 0.0));
+    }
+
+    #[test]
+    fn apply_optimal_path() {
+        let mut ai = line_ai(4);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let applied = ai.apply_optimal_path(&root, &mut pos);
+        assert_eq!(pos, 3);
+        assert_eq!(applied, vec![(1, -2.0), (1, -1.0), (1, 0.0)]);
+
+        // Stops when execute fails.
+        ai.execute = Box::new(|_, a, pos| if *pos >= 1 {Err(())} else {*pos += a; Ok(*a)});
+        let mut pos = 0;
+        assert_eq!(ai.apply_optimal_path(&root, &mut pos), vec![(1, -2.0)]);
+        assert_eq!(pos, 1);
+    }
+
+    #[test]
+    fn restore_context() {
+        let mut ai = line_ai(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let path = root.optimal_path();
+        assert!(ai.restore(&root, &path, &mut pos));
+        assert_eq!(pos, 3);
+        ai.rollback(&root, &path, &mut pos);
+        assert_eq!(pos, 0);
+        assert!(!ai.restore(&root, &[5], &mut pos));
+
+        // Fails at second action, undoing the first.
+        ai.execute = Box::new(|_, a, pos| if *pos >= 1 {Err(())} else {*pos += a; Ok(*a)});
+        assert!(!ai.restore(&root, &path, &mut pos));
+        assert_eq!(pos, 0);
+    }
+
+    #[test]
+    fn full_cancel() {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let mut ai = line_ai(20);
+        ai.settings.cancel = Some(cancel.clone());
+        // Cancel after the first expansion, like from another thread.
+        ai.hooks.on_expand = Some(Box::new(move |_, _, _| cancel.store(true, Ordering::Relaxed)));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 2);
+        assert!(root.children.iter().all(|ch| ch.1.children.is_empty()));
+        assert!(ai.cancelled());
+    }
+
+    #[test]
+    fn iterative_deepening_reuses_tree() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.iterative_deepening(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.settings.max_depth, 4);
+        assert_eq!(root.optimal_path().len(), 3);
+        assert_eq!(ai.analysis.node_count, 62);
+    }
+
+    #[test]
+    fn epsilon_greedy_explores() {
+        let mut ai = line_ai(4);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.epsilon_greedy(&mut root, 0, &mut pos, 1.0, &mut Rng::new(0));
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].0, -1);
+        assert!(root.max >= root.children[0].1.max);
+    }
+
+    #[test]
+    fn softmax_low_temperature() {
+        let mut ai = line_ai(3);
+        ai.settings.temperature = 0.01;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.softmax(&mut root, 0, &mut pos, &mut Rng::new(0));
+        assert_eq!(pos, 0);
+        assert!(root.max > -0.001);
+    }
+
+    #[test]
+    fn greedy_restarts_keeps_best() {
+        let mut ai = line_ai(6);
+        ai.settings.analysis = true;
+        let mut pos = 0;
+        let root = ai.greedy_restarts(|| Node::root(0), 0, &mut pos, 5, &mut Rng::new(0));
+        assert_eq!(pos, 0);
+        assert!(root.max > -0.001);
+        assert_eq!(ai.analysis.node_count, root.descendants());
+    }
+
+    #[test]
+    fn beam_width() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.beam(&mut root, 0, &mut pos, 1);
+        assert_eq!(pos, 0);
+        assert_eq!(root.optimal_path(), vec![1, 1, 1]);
+        assert_eq!(ai.analysis.node_count, 10);
+    }
+
+    #[test]
+    fn greedy_then_full_seam() {
+        let mut ai = line_ai(10);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.greedy_then_full(&mut root, 0, &mut pos, 1, 2);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.settings.max_depth, 10);
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.optimal_path().len(), 3);
+        assert!(root.max > -0.001);
+        assert_eq!(ai.analysis.node_count, root.descendants());
+    }
+
+    #[test]
+    fn get_by_path_and_actions() {
+        let mut ai = line_ai(2);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.get(&[1, 1]).map(|n| n.data), Some(1));
+        assert!(root.get(&[1, 2]).is_none());
+        assert!(std::ptr::eq(root.get_by_actions(&[1, -1]).unwrap(), root.get(&[1, 0]).unwrap()));
+        assert!(root.get_by_actions(&[2]).is_none());
+        root.get_mut(&[0]).unwrap().max = 10.0;
+        assert_eq!(root.children[0].1.max, 10.0);
+    }
+
+    #[test]
+    fn prune_below_threshold() {
+        let mut ai = line_ai(3);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let max = root.max;
+        let end = root.get(&root.optimal_path()).unwrap().data;
+        ai.analysis.node_count -= root.prune_below(-0.5);
+        assert_eq!(ai.analysis.node_count, root.descendants());
+        assert_eq!(root.max, max);
+        assert_eq!(root.get(&root.optimal_path()).unwrap().data, end);
+        assert!(root.iter_preorder().all(|(_, n)| n.max >= -0.5 || n.children.is_empty()));
+        assert_eq!(root.children.len(), 1);
+    }
+
+    #[test]
+    fn prune_to_optimal_plan() {
+        let mut ai = line_ai(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let plan = root.optimal_subtree();
+        let removed = root.prune_to_optimal();
+        assert_eq!(plan.descendants(), root.descendants());
+        assert_eq!(removed + root.descendants(), 2 + 4 + 8 + 16);
+        assert!(root.iter_preorder().all(|(_, n)| n.max == root.max));
+        assert_eq!(root.get(&root.optimal_path()).unwrap().data, 1);
+    }
+
+    #[test]
+    fn merge_greedy_into_full() {
+        let mut ai = line_ai(2);
+        let mut pos = 0;
+        let mut a = Node::root(0);
+        ai.greedy(&mut a, 0, &mut pos);
+        let mut b = Node::root(0);
+        ai.settings.max_depth = 0;
+        ai.full(&mut b, 0, &mut pos);
+        assert!(b.max < a.max);
+        let merged = b.merge(a);
+        assert_eq!(merged, 2);
+        assert!(b.check_unique_actions());
+        assert_eq!(b.children.len(), 2);
+        assert_eq!(b.descendants(), 2 + 2 + 2 - 1);
+        assert!(b.max > -0.001);
+    }
+
+    #[test]
+    fn full_transpositions() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        ai.state_hash = Some(Box::new(|_, pos| *pos as u64));
+        let mut pos = 0;
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        let max = root.max;
+        let count = ai.analysis.node_count;
+
+        ai.analysis.node_count = 0;
+        ai.settings.transpositions = true;
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max, max);
+        assert_eq!(root.optimal_path().len(), 3);
+        assert!(ai.analysis.node_count < count);
+        assert!(root.validate().is_ok());
+        // Transpositions are leaves with their own utility.
+        let mut transpositions = 0;
+        for (path, node) in root.iter_preorder().filter(|(_, node)| node.partial) {
+            transpositions += 1;
+            assert!(node.children.is_empty());
+            let pos: i32 = (1..=path.len()).map(|n| root.get(&path[..n]).unwrap().data).sum();
+            assert_eq!(node.max, ai.utility_with_settings(&node.data, path.len(), &pos));
+        }
+        assert!(transpositions > 0);
+        assert_eq!(root.plan().utility, max);
+    }
+
+    #[test]
+    fn top_k_paths() {
+        let mut ai = line_ai(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let paths = root.optimal_paths(3);
+        assert_eq!(paths.len(), 3);
+        assert_eq!(paths[0], (root.max, root.optimal_path()));
+        assert!(paths[0].0 >= paths[1].0 && paths[1].0 >= paths[2].0);
+        assert!(paths.iter().all(|(max, path)| root.get(path).unwrap().max == *max));
+    }
+
+    #[test]
+    fn reuse_subtree_after_acting() {
+        let mut ai = line_ai(3);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let i = root.optimal().unwrap();
+        let rest = root.children[i].1.optimal_path();
+        ai.update(&root, &mut pos);
+        let root = root.into_child(i);
+        assert_eq!(root.data, 1);
+        assert_eq!(root.optimal_path(), rest);
+        let leaf = root.into_descendant(&rest);
+        assert!(leaf.terminal());
+    }
+
+    #[test]
+    fn closures_capture_configuration() {
+        let mut ai = line_ai(4);
+        let target = -2;
+        ai.utility = Box::new(move |_, pos| -(*pos - target).abs() as f64);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.get(&root.optimal_path()).unwrap().max, -0.0002);
+        assert_eq!(pos, 0);
+    }
 }
@@ -0,0 +1,152 @@
+//! Macro-actions (temporally extended actions).
+//!
+//! A macro-action expands into a sequence of primitive actions,
+//! which are executed atomically and appear as one edge in the tree.
+//! This makes long control horizons reachable within depth limits,
+//! e.g. "burn for 10 steps" instead of 10 separate burns.
+//!
+//! Node data is wrapped in `MacroData`, which stores the node data of every primitive step,
+//! such that undo of a macro-action undoes its steps in reverse order.
+//! When a step fails to execute, the steps executed so far are undone
+//! and the macro-action fails with the error of the step.
+//!
+//! Macro-actions are generated by the actions function,
+//! so the sequence can depend on node data and context.
+//! Depth, and therefore `AiSettings::eps_depth`, counts macro-actions and not primitive actions.
+
+use std::sync::Arc;
+
+use crate::builder::AiBuilder;
+
+/// An action that expands into a sequence of primitive actions.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MacroAction<A> {
+    /// A single primitive action.
+    Step(A),
+    /// Repeats a primitive action a number of times.
+    Repeat(A, usize),
+    /// A sequence of primitive actions.
+    Sequence(Vec<A>),
+}
+
+impl<A> MacroAction<A> {
+    /// Returns the primitive actions in order of execution.
+    pub fn primitives(&self) -> Vec<&A> {
+        match self {
+            MacroAction::Step(a) => vec![a],
+            MacroAction::Repeat(a, n) => vec![a; *n],
+            MacroAction::Sequence(actions) => actions.iter().collect(),
+        }
+    }
+
+    /// Returns the number of primitive actions.
+    pub fn len(&self) -> usize {
+        match self {
+            MacroAction::Step(_) => 1,
+            MacroAction::Repeat(_, n) => *n,
+            MacroAction::Sequence(actions) => actions.len(),
+        }
+    }
+
+    /// Returns `true` if there are no primitive actions.
+    pub fn is_empty(&self) -> bool {self.len() == 0}
+}
+
+/// Node data in macro-action mode.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MacroData<T> {
+    /// The node data of every primitive step, where the last is the node data of the node.
+    ///
+    /// For root, this is the node data of root only.
+    pub steps: Vec<T>,
+}
+
+impl<T> MacroData<T> {
+    /// Creates node data of a root.
+    pub fn root(data: T) -> MacroData<T> {MacroData {steps: vec![data]}}
+
+    /// Returns the node data after the last primitive step.
+    pub fn data(&self) -> &T {self.steps.last().expect("There should be at least one step")}
+}
+
+impl<T: 'static, A: 'static, C: 'static, U: 'static, E: 'static> AiBuilder<MacroData<T>, MacroAction<A>, C, U, E> {
+    /// Sets utility, actions, execute and undo for macro-actions.
+    ///
+    /// Execute and undo work on primitive actions.
+    /// Empty macro-actions are ignored.
+    pub fn macro_actions(
+        self,
+        utility: impl Fn(&T, &C) -> U + Send + Sync + 'static,
+        actions: impl Fn(&T, &C) -> Vec<MacroAction<A>> + Send + Sync + 'static,
+        execute: impl Fn(&T, &A, &mut C) -> Result<T, E> + Send + Sync + 'static,
+        undo: impl Fn(&T, &mut C) + Send + Sync + 'static,
+    ) -> Self {
+        // Undo is shared, since failed macro-actions are undone by execute.
+        let undo = Arc::new(undo);
+        let undo_steps = undo.clone();
+        self.utility(move |data, ctx| utility(data.data(), ctx))
+            .actions(move |data, ctx| {
+                actions(data.data(), ctx).into_iter().filter(|m| !m.is_empty()).collect()
+            })
+            .execute(move |data, m, ctx| {
+                let mut steps: Vec<T> = Vec::with_capacity(m.len());
+                for a in m.primitives() {
+                    match execute(steps.last().unwrap_or(data.data()), a, ctx) {
+                        Ok(data) => steps.push(data),
+                        Err(err) => {
+                            // Undo steps executed so far, such that the macro-action is atomic.
+                            while let Some(data) = steps.pop() {
+                                undo_steps(&data, ctx);
+                            }
+                            return Err(err);
+                        }
+                    }
+                }
+                Ok(MacroData {steps})
+            })
+            .undo(move |data, ctx| {
+                for step in data.steps.iter().rev() {
+                    undo(step, ctx);
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ai, Node};
+
+    #[test]
+    fn executes_atomically() {
+        let mut ai: Ai<MacroData<i32>, MacroAction<i32>, i32> = Ai::builder()
+            .macro_actions(
+                |_, pos: &i32| -(*pos - 3).abs() as f64,
+                |_, _| vec![MacroAction::Step(-1), MacroAction::Repeat(1, 3),
+                            MacroAction::Repeat(1, 6), MacroAction::Sequence(vec![])],
+                |_, a, pos| {
+                    if (*pos + a).abs() > 5 {return Err(())};
+                    *pos += a;
+                    Ok(*a)
+                },
+                |a, pos| *pos -= a,
+            )
+            .max_depth(1)
+            .build();
+        let mut root = Node::root(MacroData::root(0));
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        // The failed and empty macro-actions are skipped.
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.max, 0.0);
+        let (a, ch) = &root.children[root.optimal().unwrap()];
+        assert_eq!(a, &MacroAction::Repeat(1, 3));
+        assert_eq!(ch.data.steps, vec![1, 1, 1]);
+        assert_eq!(pos, 0);
+
+        ai.update(&root, &mut pos);
+        assert_eq!(pos, 3);
+    }
+}
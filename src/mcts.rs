@@ -0,0 +1,360 @@
+//! Monte Carlo Tree Search (UCT).
+//!
+//! The maximum tree is constructed by repeating four steps:
+//!
+//! - Selection: Descend through expanded nodes, picking children by UCT score
+//! - Expansion: Construct children of a visited leaf using `Ai::sub_breadth`
+//! - Rollout: Simulate random actions until maximum depth
+//! - Backpropagation: Update visit statistics and maximum utility
+//!
+//! Visit statistics are stored in a structure parallel to the maximum tree,
+//! such that `Node` stays the same for all search algorithms.
+//!
+//! When there are many available actions, expanding all children of a node
+//! uses lots of memory. Progressive widening, set by `AiSettings::widening`,
+//! expands children gradually as the node is visited more times.
+//!
+//! Since a maximum tree optimizes for the best reachable node,
+//! the value of a rollout is the maximum utility along the simulated path.
+//! Nodes visited by rollouts are not stored in the maximum tree.
+//!
+//! Visit statistics average the values of rollouts, so MCTS requires `f64` as utility.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::rng::Rng;
+
+/// Progressive widening parameters.
+///
+/// The number of children expanded at a node with `n` visits is `ceil(c * n^alpha)`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Widening {
+    /// Scales the number of children.
+    pub c: f64,
+    /// Controls how fast the number of children grows, usually between `0` and `1`.
+    pub alpha: f64,
+}
+
+impl Widening {
+    /// Creates new progressive widening parameters.
+    pub fn new(c: f64, alpha: f64) -> Widening {
+        Widening {c, alpha}
+    }
+
+    /// Returns the maximum number of children for a number of visits.
+    ///
+    /// This is always at least one.
+    pub fn limit(&self, visits: usize) -> usize {
+        ((self.c * (visits as f64).powf(self.alpha)).ceil() as usize).max(1)
+    }
+}
+
+/// Computes a confidence interval from mean, variance and number of samples.
+///
+/// Returns `None` if there are no samples.
+pub(crate) fn confidence_interval(mean: f64, variance: f64, n: usize, z: f64) -> Option<(f64, f64)> {
+    if n == 0 {return None};
+    let err = z * (variance / n as f64).sqrt();
+    Some((mean - err, mean + err))
+}
+
+/// Stores visit statistics of a node.
+///
+/// Each child corresponds to the child with same index in `Node::children`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stats {
+    /// Number of times the node has been visited.
+    pub visits: usize,
+    /// Sum of rollout values.
+    pub total: f64,
+    /// Sum of squared rollout values, used to compute variance.
+    pub total_sq: f64,
+    /// Number of available actions that has been tried to expand.
+    ///
+    /// This is used by progressive widening.
+    pub actions_tried: usize,
+    /// Statistics of children.
+    pub children: Vec<Stats>,
+}
+
+impl Default for Stats {
+    fn default() -> Stats {Stats::new()}
+}
+
+impl Stats {
+    /// Creates new statistics for an unvisited node.
+    pub fn new() -> Stats {
+        Stats {
+            visits: 0,
+            total: 0.0,
+            total_sq: 0.0,
+            actions_tried: 0,
+            children: vec![],
+        }
+    }
+
+    /// Returns the mean rollout value.
+    ///
+    /// Returns `NaN` (not a number) if the node is not visited.
+    pub fn mean(&self) -> f64 {
+        if self.visits == 0 {f64::NAN}
+        else {self.total / self.visits as f64}
+    }
+
+    /// Returns the variance of rollout values.
+    ///
+    /// Returns `NaN` (not a number) if the node is not visited.
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean();
+        (self.total_sq / self.visits as f64 - mean * mean).max(0.0)
+    }
+
+    /// Returns a confidence interval of the mean rollout value,
+    /// using the number of standard errors `z`, e.g. `1.96` for 95%.
+    ///
+    /// Returns `None` if the node is not visited.
+    pub fn confidence_interval(&self, z: f64) -> Option<(f64, f64)> {
+        confidence_interval(self.mean(), self.variance(), self.visits, z)
+    }
+
+    /// Computes the UCT score of a child.
+    ///
+    /// Unvisited children gets infinite score, such that they are visited first.
+    pub fn uct(&self, child: &Stats, exploration: f64) -> f64 {
+        if child.visits == 0 {return f64::INFINITY}
+        child.mean() +
+        exploration * ((self.visits as f64).ln() / child.visits as f64).sqrt()
+    }
+}
+
+impl<T, A, C, E: ExecuteError> Ai<T, A, C, f64, E> {
+    /// Constructs maximum tree using Monte Carlo Tree Search (UCT).
+    ///
+    /// Runs the specified number of iterations,
+    /// or until a limit of memory or time is exceeded.
+    /// The `stats` must be parallel to `root`,
+    /// which is the case when starting with `Stats::new()` and an unexpanded root.
+    /// Calling this method again with same tree and statistics continues the search.
+    ///
+    /// The exploration constant is set by `AiSettings::exploration`.
+    pub fn mcts(
+        &mut self,
+        root: &mut Node<T, A>,
+        stats: &mut Stats,
+        depth: usize,
+        ctx: &mut C,
+        iterations: usize,
+        rng: &mut Rng,
+    )
+        where A: Clone
+    {
+        let started = self.start_timer();
+        for _ in 0..iterations {
+            if self.limit_exceeded() {break};
+            self.mcts_iteration(root, stats, depth, ctx, rng);
+        }
+        self.stop_timer(started);
+    }
+
+    fn mcts_iteration(
+        &mut self,
+        root: &mut Node<T, A>,
+        stats: &mut Stats,
+        depth: usize,
+        ctx: &mut C,
+        rng: &mut Rng,
+    ) -> f64
+        where A: Clone
+    {
+        if root.max.is_nan() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let value = if stats.visits == 0 || depth >= self.settings.max_depth {
+            self.mcts_rollout(&root.data, depth, ctx, rng)
+        } else {
+            if let Some(widening) = self.settings.widening {
+                self.mcts_widen(root, stats, depth, ctx, widening.limit(stats.visits));
+            } else if root.children.is_empty() {
+                self.sub_breadth(root, depth, ctx);
+                stats.children = vec![Stats::new(); root.children.len()];
+            }
+
+            let mut best: Option<(usize, f64)> = None;
+            for (i, ch) in stats.children.iter().enumerate() {
+                let score = stats.uct(ch, self.settings.exploration);
+                if best.map(|(_, s)| score > s).unwrap_or(true) {
+                    best = Some((i, score));
+                }
+            }
+
+            if let Some((i, _)) = best {
+                let a = &root.children[i].0;
+                if self.try_execute(&root.data, a, ctx).is_some() {
+                    let ch = &mut root.children[i].1;
+                    let value = self.mcts_iteration(ch, &mut stats.children[i], depth + 1, ctx, rng);
+
+                    // Undo changes made to context to reset state.
+                    self.revert(&ch.data, ctx);
+
+                    // Update maximum utility since children are changed.
+                    if ch.max > root.max {
+                        root.max = ch.max;
+                    }
+                    value
+                } else {
+                    // The child can not be visited, so it is removed to not be selected again.
+                    let (_, ch) = root.children.remove(i);
+                    stats.children.remove(i);
+                    if self.settings.analysis {
+                        self.analysis.node_count -= ch.descendants() + 1;
+                    }
+
+                    // Update maximum utility since children are changed.
+                    root.max = self.utility_with_settings(&root.data, depth, ctx);
+                    root.update_max();
+                    self.mcts_rollout(&root.data, depth, ctx, rng)
+                }
+            } else {
+                // There are no available actions.
+                self.mcts_rollout(&root.data, depth, ctx, rng)
+            }
+        };
+
+        stats.visits += 1;
+        stats.total += value;
+        stats.total_sq += value * value;
+        value
+    }
+
+    /// Expands children until the limit is reached or all actions are tried.
+    fn mcts_widen(&mut self, root: &mut Node<T, A>, stats: &mut Stats, depth: usize, ctx: &mut C, limit: usize)
+        where A: Clone
+    {
+        // Visited children are not removed, so widening stops at the limit of children.
+        let limit = self.max_children_at(depth).map_or(limit, |k| limit.min(k));
+        if root.children.len() >= limit {return};
+
+        let actions = (self.actions)(&root.data, ctx);
+        while root.children.len() < limit && stats.actions_tried < actions.len() {
+            let a = &actions[stats.actions_tried];
+            stats.actions_tried += 1;
+            if let Some(data) = self.try_execute(&root.data, a, ctx) {
+                let utility = self.utility_with_settings(&data, depth + 1, ctx);
+                if utility > root.max {
+                    root.max = utility;
+                }
+
+                // Undo changes made to context to reset state.
+                self.revert(&data, ctx);
+
+                self.count_node(&data, a, utility, depth + 1);
+                root.children.push((a.clone(), Node {
+                    max: utility,
+                    data,
+                    children: vec![],
+                    partial: false,
+                }));
+                stats.children.push(Stats::new());
+            }
+        }
+    }
+
+    /// Simulates random actions until maximum depth,
+    /// returning the maximum utility along the path.
+    fn mcts_rollout(&mut self, data: &T, depth: usize, ctx: &mut C, rng: &mut Rng) -> f64 {
+        let mut value = self.utility_with_settings(data, depth, ctx);
+        let mut stack: Vec<T> = vec![];
+        let mut depth = depth;
+        while depth < self.settings.max_depth {
+            let next = {
+                let data = stack.last().unwrap_or(data);
+                self.execute_random(data, ctx, rng)
+            };
+            if let Some((_, new_data)) = next {
+                depth += 1;
+                let utility = self.utility_with_settings(&new_data, depth, ctx);
+                if utility > value {value = utility};
+                stack.push(new_data);
+            } else {
+                break;
+            }
+        }
+
+        // Undo changes made to context to reset state.
+        while let Some(data) = stack.pop() {
+            self.revert(&data, ctx);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn finds_maximum() {
+        let mut ai = line_ai(5);
+        let mut root = Node::root(0);
+        let mut stats = Stats::new();
+        let mut pos = 0;
+        ai.mcts(&mut root, &mut stats, 0, &mut pos, 200, &mut Rng::new(0));
+        assert_eq!(pos, 0);
+        assert_eq!(stats.visits, 200);
+        assert_eq!(stats.children.len(), root.children.len());
+        assert!(root.max > -0.001);
+        let (lo, hi) = stats.confidence_interval(1.96).unwrap();
+        assert!(lo <= stats.mean() && stats.mean() <= hi);
+        assert_eq!(Stats::new().confidence_interval(1.96), None);
+    }
+
+    #[test]
+    fn progressive_widening() {
+        let mut ai = line_ai(5);
+        ai.settings.widening = Some(Widening::new(1.0, 0.0));
+        let mut root = Node::root(0);
+        let mut stats = Stats::new();
+        let mut pos = 0;
+        ai.mcts(&mut root, &mut stats, 0, &mut pos, 50, &mut Rng::new(0));
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(stats.children.len(), 1);
+
+        // The limit of children stops widening.
+        ai.settings.widening = Some(Widening::new(10.0, 0.0));
+        ai.settings.max_children = Some(1);
+        let mut root = Node::root(0);
+        let mut stats = Stats::new();
+        ai.mcts(&mut root, &mut stats, 0, &mut pos, 50, &mut Rng::new(0));
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(stats.children.len(), 1);
+    }
+
+    #[test]
+    fn removes_failed_child() {
+        let mut ai = line_ai(5);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.sub_breadth(&mut root, 0, &mut pos);
+        let mut stats = Stats::new();
+        stats.visits = 1;
+        stats.children = vec![Stats::new(); root.children.len()];
+
+        // Moving right fails after the children are created.
+        ai.execute = Box::new(|_, a, pos| {
+            if *a == 1 {return Err(())};
+            *pos += a;
+            Ok(*a)
+        });
+        ai.mcts(&mut root, &mut stats, 0, &mut pos, 20, &mut Rng::new(0));
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].0, -1);
+        assert_eq!(stats.children.len(), 1);
+        assert_eq!(stats.children[0].visits, 19);
+    }
+}
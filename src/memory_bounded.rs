@@ -0,0 +1,169 @@
+//! Memory-bounded best-first search (SMA*-style).
+//!
+//! Nodes are expanded in order of utility, like `Ai::best_first` without heuristic.
+//! When the memory limit `AiSettings::max_mib` is exceeded,
+//! the leaves with lowest utility are dropped instead of terminating the search.
+//! This makes it possible to keep searching within a fixed memory budget.
+//!
+//! Since each node stores the maximum utility of itself or any children,
+//! the values of dropped leaves are already backed up into their parents.
+//! Only leaves with lower utility than their parent are dropped,
+//! such that `Node::optimal` is still valid after dropping them.
+//! When all children of a node are dropped, the node becomes a leaf that can be expanded again.
+
+use std::collections::BinaryHeap;
+
+use crate::{Ai, Node};
+use crate::best_first::Frontier;
+use crate::execute_error::ExecuteError;
+use crate::outcome::StopReason;
+use crate::utility::Utility;
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Expands nodes in order of utility, dropping leaves when memory limit is exceeded.
+    ///
+    /// Requires `AiSettings::analysis` and `AiSettings::max_mib` to be set,
+    /// otherwise this is equivalent to `Ai::best_first` without heuristic.
+    /// Stops after the specified number of expansions,
+    /// when there are no more nodes to expand,
+    /// when the time limit is exceeded,
+    /// or when no more leaves can be dropped.
+    pub fn memory_bounded(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C, expansions: usize)
+        where A: Clone
+    {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let started = self.start_timer();
+        let mut queue = BinaryHeap::new();
+        queue.push(Frontier {priority: root.max, cost: 0.0, depth, path: vec![]});
+        for _ in 0..expansions {
+            let f = match queue.pop() {
+                Some(f) => f,
+                None => break,
+            };
+
+            let n = self.replay(root, &f.path, ctx);
+            if n == f.path.len() {
+                let node = root.node_mut(&f.path);
+                self.sub_breadth(node, f.depth, ctx);
+                if f.depth < self.settings.max_depth {
+                    for (i, ch) in node.children.iter().enumerate() {
+                        let mut path = f.path.clone();
+                        path.push(i);
+                        queue.push(Frontier {priority: ch.1.max, cost: 0.0, depth: f.depth + 1, path});
+                    }
+                }
+            }
+            self.rollback(root, &f.path[..n], ctx);
+
+            // Update maximum utility since children are changed.
+            root.update_max();
+
+            if self.time_exceeded() {
+                self.record_stop(Some(StopReason::TimeLimit));
+                break;
+            }
+            if self.memory_exceeded() {
+                if !self.drop_leaves(root, depth) {
+                    self.record_stop(Some(StopReason::MemoryLimit));
+                    break;
+                }
+
+                // Paths in queue are invalid after dropping leaves.
+                queue.clear();
+                let mut leaves = vec![];
+                leaves_of(root, &mut vec![], &mut leaves);
+                for (priority, path) in leaves {
+                    let depth = depth + path.len();
+                    if path.is_empty() || depth <= self.settings.max_depth {
+                        queue.push(Frontier {priority, cost: 0.0, depth, path});
+                    }
+                }
+            }
+        }
+        self.stop_timer(started);
+    }
+
+    /// Drops leaves with lowest utility until memory limit is no longer exceeded.
+    ///
+    /// Returns `false` if no leaves could be dropped.
+    fn drop_leaves(&mut self, root: &mut Node<T, A, U>, depth: usize) -> bool {
+        let limit = match self.settings.max_mib {
+            Some(limit) => limit,
+            None => return false,
+        };
+        let max_nodes = (limit * 1048576.0 / self.node_size() as f64) as usize;
+        let needed = (self.analysis.node_count + 1).saturating_sub(max_nodes).max(1);
+
+        let mut leaves = vec![];
+        droppable_leaves(root, &mut vec![], &mut leaves);
+        if leaves.is_empty() {return false};
+
+        leaves.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        leaves.truncate(needed);
+
+        // Remove in descending order of paths, such that indices stay valid.
+        let mut paths: Vec<Vec<usize>> = leaves.into_iter().map(|(_, path)| path).collect();
+        paths.sort_by(|a, b| b.cmp(a));
+        for path in &paths {
+            let (&i, parent) = path.split_last().unwrap();
+            let (a, ch) = root.node_mut(parent).children.remove(i);
+            self.count_pruned(&ch.data, &a, depth + path.len());
+        }
+        self.pruned(&root.data, paths.len());
+        true
+    }
+}
+
+/// Collects utility and path of all leaves.
+fn leaves_of<T, A, U: Utility>(node: &Node<T, A, U>, path: &mut Vec<usize>, out: &mut Vec<(U, Vec<usize>)>) {
+    if node.children.is_empty() {
+        out.push((node.max, path.clone()));
+    }
+    for (i, ch) in node.children.iter().enumerate() {
+        path.push(i);
+        leaves_of(&ch.1, path, out);
+        path.pop();
+    }
+}
+
+/// Collects utility and path of leaves with lower utility than their parent.
+fn droppable_leaves<T, A, U: Utility>(node: &Node<T, A, U>, path: &mut Vec<usize>, out: &mut Vec<(U, Vec<usize>)>) {
+    for (i, ch) in node.children.iter().enumerate() {
+        path.push(i);
+        if ch.1.children.is_empty() {
+            if ch.1.max < node.max {
+                out.push((ch.1.max, path.clone()));
+            }
+        } else {
+            droppable_leaves(&ch.1, path, out);
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn stays_within_memory() {
+        let mut ai = line_ai(8);
+        ai.settings.analysis = true;
+        let max_nodes = 12;
+        ai.settings.max_mib = Some((max_nodes * ai.node_size()) as f64 / 1048576.0);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.memory_bounded(&mut root, 0, &mut pos, 100);
+        assert_eq!(pos, 0);
+        assert!(ai.analysis.node_count <= max_nodes);
+        assert_eq!(ai.analysis.node_count, root.descendants());
+        assert!(ai.analysis.pruned > 0);
+        assert_eq!(ai.analysis.depth_histogram.iter().sum::<usize>(), ai.analysis.node_count);
+        assert!(root.max > -0.001);
+        assert!(root.optimal().is_some());
+    }
+}
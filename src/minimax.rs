@@ -0,0 +1,130 @@
+//! Adversarial two-player search (minimax with alpha-beta pruning).
+//!
+//! The maximizing player tries to increase utility,
+//! while the minimizing player tries to decrease it.
+//! Utility is always computed from the perspective of the maximizing player.
+//!
+//! The value of each node is stored in `Node::max`,
+//! which is the minimax value instead of the maximum utility.
+//! Children that are never explored because of alpha-beta pruning are removed.
+//! The child with the best value for the player is moved first,
+//! such that `Node::optimal_path` returns the principal variation.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// The player that chooses action at a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Player {
+    /// Chooses actions that maximize utility.
+    Max,
+    /// Chooses actions that minimize utility.
+    Min,
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Constructs a minimax tree using alpha-beta pruning.
+    ///
+    /// The player at a node is decided by `Ai::player`.
+    /// When this is not set, players alternate by depth,
+    /// starting with the maximizing player at `depth`.
+    pub fn minimax(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C)
+        where A: Clone
+    {
+        let started = self.start_timer();
+        self.alpha_beta(root, depth, depth, ctx, None, None);
+        self.stop_timer(started);
+    }
+
+    fn alpha_beta(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        start_depth: usize,
+        ctx: &mut C,
+        mut alpha: Option<U>,
+        mut beta: Option<U>,
+    ) -> U
+        where A: Clone
+    {
+        let player = match &self.player {
+            Some(player) => player(&root.data, ctx),
+            None => if (depth - start_depth).is_multiple_of(2) {Player::Max} else {Player::Min},
+        };
+
+        self.sub_breadth(root, depth, ctx);
+        if root.children.is_empty() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+            return root.max;
+        }
+
+        let expand = depth < self.settings.max_depth && !self.limit_exceeded();
+        let n = root.children.len();
+        let mut cut = n;
+        let mut best: Option<(usize, U)> = None;
+        for i in 0..n {
+            let value = if expand {
+                let a = &root.children[i].0;
+                if self.try_execute(&root.data, a, ctx).is_some() {
+                    let ch = &mut root.children[i].1;
+                    let value = self.alpha_beta(ch, depth + 1, start_depth, ctx, alpha, beta);
+
+                    // Undo changes made to context to reset state.
+                    self.revert(&ch.data, ctx);
+                    value
+                } else {root.children[i].1.max}
+            } else {root.children[i].1.max};
+
+            match player {
+                Player::Max => {
+                    if best.map(|(_, v)| value > v).unwrap_or(true) {best = Some((i, value))};
+                    if alpha.map(|alpha| value > alpha).unwrap_or(true) {alpha = Some(value)};
+                }
+                Player::Min => {
+                    if best.map(|(_, v)| value < v).unwrap_or(true) {best = Some((i, value))};
+                    if beta.map(|beta| value < beta).unwrap_or(true) {beta = Some(value)};
+                }
+            }
+            if alpha.zip(beta).map(|(alpha, beta)| alpha >= beta).unwrap_or(false) {
+                cut = i + 1;
+                break;
+            }
+        }
+
+        // Remove children that are not explored.
+        for (a, ch) in &root.children[cut..] {
+            self.count_pruned(&ch.data, a, depth + 1);
+        }
+        root.children.truncate(cut);
+        self.pruned(&root.data, n - cut);
+
+        let (i, value) = best.expect("There should be at least one child");
+        root.children.swap(0, i);
+        root.max = value;
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn opponent_pulls_back() {
+        let mut ai = line_ai(3);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.minimax(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.analysis.node_count, root.descendants());
+        assert!(ai.analysis.pruned > 0);
+        // Max moves right twice, while min moves left twice.
+        assert!((root.max - (-3.0004)).abs() < 1e-9);
+        assert_eq!(root.optimal_path(), vec![0, 0, 0, 0]);
+        assert_eq!(root.children[0].0, 1);
+        assert_eq!(root.children[0].1.children[0].0, -1);
+    }
+}
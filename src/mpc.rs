@@ -0,0 +1,117 @@
+//! Receding-horizon control (model-predictive control).
+//!
+//! A controller does not follow a whole plan,
+//! since the world might not behave as the model predicts.
+//! Instead, it plans to a limited depth (the horizon), commits the first action
+//! of the optimal path to context and then replans from the new state.
+//!
+//! Unlike `Ai::realtime`, no values are learned between steps,
+//! so each plan is a full search to the horizon.
+//!
+//! Each plan is a separate search, so analysis and limits of nodes and memory apply per plan,
+//! while `AiSettings::max_time` is the time budget of all steps.
+
+use std::time::Instant;
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::outcome::StopReason;
+use crate::utility::Utility;
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Runs receding-horizon control for a number of steps, committing actions to context.
+    ///
+    /// Each step runs `full` from the current state with maximum depth `plan_depth`,
+    /// then commits the first action of the optimal path.
+    ///
+    /// Stops when the current state is better than any plan (it is terminal),
+    /// an action is not approved (see `Ai::approve`) or fails to execute,
+    /// the time budget is spent, or a plan is cancelled or aborted.
+    /// Returns the executed actions with the utility of the resulting node data,
+    /// and the node data of the final state.
+    pub fn mpc(&mut self, data: T, ctx: &mut C, steps: usize, plan_depth: usize) -> (Vec<(A, U)>, T)
+        where A: Clone
+    {
+        let start = Instant::now();
+        let max_time = self.settings.max_time;
+        let max_depth = self.settings.max_depth;
+        self.settings.max_depth = plan_depth;
+        let mut trajectory = vec![];
+        let mut data = data;
+        for _ in 0..steps {
+            // Each plan gets the remaining time budget.
+            if let Some(limit) = max_time {
+                let elapsed = start.elapsed();
+                if elapsed >= limit {break};
+                self.settings.max_time = Some(limit - elapsed);
+            }
+
+            let mut root = Node::root(data);
+            let outcome = self.full(&mut root, 0, ctx);
+            if self.settings.analysis {
+                self.analysis.node_count -= root.descendants();
+            }
+            if matches!(outcome.stop, StopReason::Aborted | StopReason::Cancelled) {
+                data = root.into_data();
+                break;
+            }
+            let i = match root.optimal() {
+                Some(i) => i,
+                None => {
+                    data = root.into_data();
+                    break;
+                }
+            };
+
+            // Commit first action to context and replan from the new state.
+            let a = root.children.swap_remove(i).0;
+            let new_data = self.commit(&root.data, &a, ctx);
+            let old_data = root.into_data();
+            match new_data {
+                Some(new_data) => {
+                    let utility = (self.utility)(&new_data, ctx);
+                    trajectory.push((a, utility));
+                    data = new_data;
+                }
+                None => {
+                    data = old_data;
+                    break;
+                }
+            }
+        }
+        self.settings.max_depth = max_depth;
+        self.settings.max_time = max_time;
+        (trajectory, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::line_ai;
+
+    #[test]
+    fn replans_each_step() {
+        let mut ai = line_ai(5);
+        ai.settings.eps_depth = 0.0001;
+        let mut pos = 0;
+        let (trajectory, _) = ai.mpc(0, &mut pos, 10, 2);
+        // Reaches the maximum beyond the horizon of the first plan and stays there.
+        assert_eq!(trajectory, vec![(1, -2.0), (1, -1.0), (1, 0.0)]);
+        assert_eq!(pos, 3);
+        assert_eq!(ai.settings.max_depth, 5);
+    }
+
+    #[test]
+    fn node_limit_per_plan() {
+        let mut ai = line_ai(5);
+        ai.settings.eps_depth = 0.0001;
+        // A plan to depth 2 creates 14 nodes.
+        ai.settings.max_nodes = Some(14);
+        let mut pos = 0;
+        let (trajectory, _) = ai.mpc(0, &mut pos, 10, 2);
+        assert_eq!(trajectory.len(), 3);
+        assert_eq!(pos, 3);
+        // Analysis is of the last plan.
+        assert!(ai.analysis.nodes_created <= 14);
+    }
+}
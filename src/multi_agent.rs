@@ -0,0 +1,138 @@
+//! Planning for multiple cooperating agents.
+//!
+//! There are two ways to plan for multiple agents sharing the same utility:
+//!
+//! - Joint actions, where every agent acts at each step.
+//!   An action is a `Vec<A>` with one action per agent,
+//!   constructed as the cross-product of the actions of each agent.
+//!   Use `AiBuilder::joint_actions` or `joint_actions`.
+//! - Turn-taking, where one agent acts at each step.
+//!   Node data is wrapped in `Turn`, which tells which agent acts,
+//!   such that agents take turns by depth.
+//!   Use `AiBuilder::turn_taking`.
+//!
+//! Joint actions grow exponentially with the number of agents,
+//! while turn-taking requires deeper trees for the same number of steps.
+
+use crate::builder::AiBuilder;
+
+/// Returns the cross-product of actions of each agent.
+///
+/// When some agent has no actions, there are no joint actions.
+pub fn joint_actions<A: Clone>(per_agent: &[Vec<A>]) -> Vec<Vec<A>> {
+    let mut joint = vec![vec![]];
+    for actions in per_agent {
+        joint = joint.iter().flat_map(|prefix: &Vec<A>| actions.iter().map(move |a| {
+            let mut prefix = prefix.clone();
+            prefix.push(a.clone());
+            prefix
+        })).collect();
+    }
+    joint
+}
+
+/// Node data in turn-taking mode.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Turn<T> {
+    /// The node data of the application.
+    pub data: T,
+    /// The agent that acts at this node.
+    pub agent: usize,
+}
+
+impl<T> Turn<T> {
+    /// Creates node data of a root, where the first agent acts.
+    pub fn root(data: T) -> Turn<T> {Turn {data, agent: 0}}
+}
+
+impl<T: 'static, A: Clone + 'static, C: 'static, U, E> AiBuilder<T, Vec<A>, C, U, E> {
+    /// Sets actions to joint actions of a number of agents.
+    ///
+    /// The actions of each agent are listed by agent index.
+    /// Execute receives one action per agent, in the same order.
+    pub fn joint_actions(
+        self,
+        agents: usize,
+        actions: impl Fn(&T, &C, usize) -> Vec<A> + Send + Sync + 'static,
+    ) -> Self {
+        self.actions(move |data, ctx| {
+            let per_agent: Vec<Vec<A>> = (0..agents).map(|agent| actions(data, ctx, agent)).collect();
+            joint_actions(&per_agent)
+        })
+    }
+}
+
+impl<T: 'static, A: 'static, C: 'static, U: 'static, E: 'static> AiBuilder<Turn<T>, A, C, U, E> {
+    /// Sets utility, actions, execute and undo for agents taking turns.
+    ///
+    /// Actions and execute receive the index of the acting agent.
+    /// After an agent acts, the next agent acts, starting over with the first agent.
+    /// Utility and undo are the same for every agent.
+    pub fn turn_taking(
+        self,
+        agents: usize,
+        utility: impl Fn(&T, &C) -> U + Send + Sync + 'static,
+        actions: impl Fn(&T, &C, usize) -> Vec<A> + Send + Sync + 'static,
+        execute: impl Fn(&T, &A, &mut C, usize) -> Result<T, E> + Send + Sync + 'static,
+        undo: impl Fn(&T, &mut C) + Send + Sync + 'static,
+    ) -> Self {
+        self.utility(move |turn, ctx| utility(&turn.data, ctx))
+            .actions(move |turn, ctx| actions(&turn.data, ctx, turn.agent))
+            .execute(move |turn, a, ctx| Ok(Turn {
+                data: execute(&turn.data, a, ctx, turn.agent)?,
+                agent: (turn.agent + 1) % agents,
+            }))
+            .undo(move |turn, ctx| undo(&turn.data, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ai, Node};
+
+    // Two rovers moving on a line, trying to meet at position 2.
+    fn rovers(pos: &[i32; 2]) -> f64 {-pos.iter().map(|p| (p - 2).abs()).sum::<i32>() as f64}
+
+    #[test]
+    fn joint_and_turns() {
+        assert_eq!(joint_actions(&[vec![0, 1], vec![2]]), vec![vec![0, 2], vec![1, 2]]);
+        assert!(joint_actions(&[vec![0, 1], vec![]]).is_empty());
+
+        let mut ai: Ai<Vec<i32>, Vec<i32>, [i32; 2]> = Ai::builder()
+            .utility(|_, pos| rovers(pos))
+            .joint_actions(2, |_, _, _| vec![-1, 1])
+            .execute(|_, a: &Vec<i32>, pos| {
+                for i in 0..2 {pos[i] += a[i]};
+                Ok(a.clone())
+            })
+            .undo(|a, pos| for i in 0..2 {pos[i] -= a[i]})
+            .max_depth(2)
+            .eps_depth(0.0001)
+            .build();
+        let mut root = Node::root(vec![]);
+        let mut pos = [0, 0];
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.children.len(), 4);
+        assert_eq!(root.optimal_path().len(), 2);
+        assert_eq!(root.children[root.optimal().unwrap()].0, vec![1, 1]);
+
+        // Node data stores the agent that acted with the action, for undo.
+        let mut ai: Ai<Turn<(usize, i32)>, i32, [i32; 2]> = Ai::builder()
+            .turn_taking(2, |_, pos| rovers(pos), |_, _, _| vec![-1, 1], |_, a, pos, agent| {
+                pos[agent] += a;
+                Ok((agent, *a))
+            }, |&(agent, a), pos| pos[agent] -= a)
+            .max_depth(4)
+            .eps_depth(0.0001)
+            .build();
+        let mut root = Node::root(Turn::root((0, 0)));
+        ai.full(&mut root, 0, &mut pos);
+        let path = root.optimal_path();
+        assert_eq!(path.len(), 4);
+        assert_eq!(root.children[path[0]].1.data.agent, 1);
+        assert_eq!(root.max, -4.0 * 0.0001);
+        assert_eq!(pos, [0, 0]);
+    }
+}
@@ -0,0 +1,130 @@
+//! Outcomes of searches.
+//!
+//! A search might stop because the tree is exhausted or the depth limit is reached,
+//! but also early because of a limit of memory, time or nodes, cancellation or an error.
+//! The tree constructed so far is kept in all cases,
+//! so `SearchOutcome` tells whether it is the result of a finished search.
+
+use std::time::Duration;
+
+use crate::Ai;
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// The reason a search stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopReason {
+    /// There were no more actions to explore.
+    Exhausted,
+    /// Some nodes were not expanded because of `AiSettings::max_depth`.
+    DepthLimit,
+    /// The search stopped early because of `AiSettings::max_mib`.
+    MemoryLimit,
+    /// The search stopped early because of `AiSettings::max_time`.
+    TimeLimit,
+    /// The search stopped early because of `AiSettings::max_nodes`.
+    NodeLimit,
+    /// The search stopped early because of `AiSettings::cancel`.
+    Cancelled,
+    /// The search stopped early because of an error, see `ExecuteError::abort`.
+    Aborted,
+    /// The search stopped early because of `AiSettings::target_utility`.
+    TargetReached,
+}
+
+impl StopReason {
+    /// Returns `true` if the search finished without stopping early.
+    pub fn finished(&self) -> bool {
+        matches!(self, StopReason::Exhausted | StopReason::DepthLimit)
+    }
+}
+
+/// Stores why a search stopped, with basic statistics.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchOutcome {
+    /// The reason the search stopped.
+    pub stop: StopReason,
+    /// The number of nodes created in the search.
+    pub nodes_created: usize,
+    /// The deepest depth of created nodes in the search.
+    pub max_depth_reached: usize,
+    /// The wall-clock time of the search.
+    pub search_time: Duration,
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Returns the reason the current search stopped.
+    ///
+    /// Limits are not checked again, since the reason is recorded where the search stopped,
+    /// see `AiAnalysis::stop`.
+    fn stop_reason(&self) -> StopReason {
+        if let Some(stop) = self.analysis.stop {stop}
+        // An aborting error or reaching the target in the last expansion
+        // stops the search without checking limits.
+        else if self.analysis.aborted {StopReason::Aborted}
+        else if self.analysis.target_reached {StopReason::TargetReached}
+        // Nodes deeper than maximum depth are not expanded.
+        else if self.analysis.max_depth_reached > self.settings.max_depth {StopReason::DepthLimit}
+        else {StopReason::Exhausted}
+    }
+
+    /// Stops the timer, returning the outcome of the current search.
+    pub(crate) fn finish(&mut self, started: bool) -> SearchOutcome {
+        let stop = self.stop_reason();
+        let search_time = self.analysis.search_start.map(|t| t.elapsed()).unwrap_or_default();
+        self.stop_timer(started);
+        SearchOutcome {
+            stop,
+            nodes_created: self.analysis.nodes_created,
+            max_depth_reached: self.analysis.max_depth_reached,
+            search_time,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn reports_stop_reason() {
+        let mut ai = line_ai(2);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        let outcome = ai.full(&mut root, 0, &mut pos);
+        assert_eq!(outcome.stop, StopReason::DepthLimit);
+        assert_eq!(outcome.nodes_created, 14);
+        assert_eq!(outcome.max_depth_reached, 3);
+
+        ai.actions = Box::new(|_, _| vec![]);
+        let mut root = Node::root(0);
+        assert_eq!(ai.greedy(&mut root, 0, &mut pos).stop, StopReason::Exhausted);
+
+        ai.actions = Box::new(|_, _| vec![-1, 1]);
+        ai.settings.max_nodes = Some(3);
+        let mut root = Node::root(0);
+        let outcome = ai.full(&mut root, 0, &mut pos);
+        assert_eq!(outcome.stop, StopReason::NodeLimit);
+        assert!(!outcome.stop.finished());
+        assert_eq!(ai.analysis.stop, Some(StopReason::NodeLimit));
+    }
+
+    #[test]
+    fn time_limit_after_last_expansion() {
+        let mut ai = line_ai(0);
+        ai.utility = Box::new(|_, pos| {
+            std::thread::sleep(Duration::from_millis(2));
+            -(*pos as f64 - 3.0).abs()
+        });
+        ai.settings.max_time = Some(Duration::from_millis(1));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        // The time limit is exceeded, but the search finished before checking it.
+        assert_eq!(ai.greedy(&mut root, 0, &mut pos).stop, StopReason::DepthLimit);
+        assert_eq!(ai.analysis.stop, None);
+    }
+}
@@ -0,0 +1,481 @@
+//! Parallel search using a rayon thread pool (requires the `parallel` feature).
+//!
+//! Full search is embarrassingly parallel at the root:
+//! The root is expanded by `Ai::full`, then each child is searched by a worker,
+//! with a cloned context.
+//!
+//! Greedy search is parallelized by running multiple searches with different random seeds,
+//! keeping the tree with highest utility.
+//!
+//! Workers run the same depth-first search as `Ai::full` and `Ai::greedy`,
+//! each with its own AI setup sharing the closures and hooks of the original.
+//! Hooks, schedules, traces and undo checks are used by every worker.
+//! The numbers of created nodes and nodes are shared by atomic counters,
+//! such that limits apply to all workers together,
+//! and a worker that stops the search, e.g. by a limit or an aborting error, stops the others.
+//! Analysis of workers is merged into the AI setup afterwards.
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+
+use crate::{
+    ActionTestFn,
+    ActionsFn,
+    Ai,
+    AiAnalysis,
+    AiSettings,
+    BoundFn,
+    CostFn,
+    ExecuteFn,
+    Full,
+    GoalFn,
+    HeuristicFn,
+    Node,
+    NodeKindFn,
+    OrderFn,
+    PlayerFn,
+    SizeFn,
+    StateHashFn,
+    UndoFn,
+    UtilityFn,
+};
+use crate::depth_first::{DepthFirst, NodeStorage};
+use crate::execute_error::ExecuteError;
+use crate::hooks::{ExpandFn, Hooks, ImproveFn, ProgressFn, PruneFn};
+use crate::outcome::StopReason;
+use crate::rng::Rng;
+use crate::schedule::Schedule;
+use crate::utility::Utility;
+use crate::value::ValueEstimator;
+
+/// Counters shared by the workers of a parallel search.
+struct Counters {
+    nodes_created: AtomicUsize,
+    node_count: AtomicUsize,
+    /// The reason the first worker stopped the search.
+    stop: Mutex<Option<StopReason>>,
+}
+
+/// Counters of a worker, shared with other workers of a parallel search.
+pub(crate) struct WorkerCounters {
+    shared: Arc<Counters>,
+    /// The number of created nodes of this worker added to the shared counter.
+    nodes_created: usize,
+    /// The number of nodes of this worker added to the shared counter.
+    node_count: usize,
+}
+
+impl WorkerCounters {
+    /// Returns the numbers of created nodes and nodes of all workers,
+    /// using the current numbers of this worker.
+    pub(crate) fn totals(&self, nodes_created: usize, node_count: usize) -> (usize, usize) {
+        let shared = &self.shared;
+        (
+            (shared.nodes_created.load(Ordering::Relaxed) + nodes_created).saturating_sub(self.nodes_created),
+            (shared.node_count.load(Ordering::Relaxed) + node_count).saturating_sub(self.node_count),
+        )
+    }
+
+    /// Adds changes of the numbers of this worker to the shared counters.
+    pub(crate) fn publish(&mut self, nodes_created: usize, node_count: usize) {
+        publish(&self.shared.nodes_created, &mut self.nodes_created, nodes_created);
+        publish(&self.shared.node_count, &mut self.node_count, node_count);
+    }
+
+    /// Stops other workers, keeping the first reason.
+    pub(crate) fn stop(&self, reason: StopReason) {
+        let mut stop = self.shared.stop.lock().unwrap();
+        if stop.is_none() {*stop = Some(reason)};
+    }
+
+    /// Returns the reason a worker stopped the search.
+    pub(crate) fn stopped(&self) -> Option<StopReason> {*self.shared.stop.lock().unwrap()}
+}
+
+fn publish(counter: &AtomicUsize, published: &mut usize, value: usize) {
+    if value > *published {
+        counter.fetch_add(value - *published, Ordering::Relaxed);
+    } else {
+        counter.fetch_sub(*published - value, Ordering::Relaxed);
+    }
+    *published = value;
+}
+
+/// Estimates values, see `Ai::value_estimator`.
+type EstimatorBox<T, C, U> = Box<dyn ValueEstimator<T, C, U> + Send + Sync>;
+
+/// The closures and hooks of an AI setup, moved out to be shared by workers.
+struct Shared<T, A, C, U, E> {
+    utility: Arc<UtilityFn<T, C, U>>,
+    actions: Arc<ActionsFn<T, A, C>>,
+    execute: Arc<ExecuteFn<T, A, C, E>>,
+    undo: Arc<UndoFn<T, C>>,
+    heuristic: Option<Arc<HeuristicFn<T, C>>>,
+    bound: Option<Arc<BoundFn<T, C, U>>>,
+    player: Option<Arc<PlayerFn<T, C>>>,
+    node_kind: Option<Arc<NodeKindFn<T, A, C>>>,
+    state_hash: Option<Arc<StateHashFn<T, C>>>,
+    cost: Option<Arc<CostFn<T, A, C>>>,
+    order: Option<Arc<OrderFn<T, A, C>>>,
+    is_goal: Option<Arc<GoalFn<T, C>>>,
+    forbid: Option<Arc<ActionTestFn<T, A, C>>>,
+    approve: Option<Arc<ActionTestFn<T, A, C>>>,
+    impact: Option<Arc<HeuristicFn<T, C>>>,
+    value_estimator: Option<Arc<EstimatorBox<T, C, U>>>,
+    data_size: Option<Arc<SizeFn<T>>>,
+    action_size: Option<Arc<SizeFn<A>>>,
+    on_expand: Option<Arc<ExpandFn<T>>>,
+    on_improve: Option<Arc<ImproveFn<T, U>>>,
+    on_prune: Option<Arc<PruneFn<T>>>,
+    on_progress: Option<Arc<ProgressFn<U>>>,
+    schedule: Option<Arc<Schedule>>,
+    counters: Arc<Counters>,
+}
+
+impl<T, A, C, U, E> Shared<T, A, C, U, E>
+    where T: 'static, A: 'static, C: 'static, U: Utility + 'static, E: ExecuteError + 'static
+{
+    /// Moves closures and hooks out of the AI setup, until restored.
+    fn new(ai: &mut Ai<T, A, C, U, E>) -> Shared<T, A, C, U, E> {
+        let (nodes_created, node_count) = ai.search_counts();
+        Shared {
+            utility: Arc::new(std::mem::replace(&mut ai.utility,
+                Box::new(|_, _| unreachable!("Utility should be restored")))),
+            actions: Arc::new(std::mem::replace(&mut ai.actions,
+                Box::new(|_, _| unreachable!("Actions should be restored")))),
+            execute: Arc::new(std::mem::replace(&mut ai.execute,
+                Box::new(|_, _, _| unreachable!("Execute should be restored")))),
+            undo: Arc::new(std::mem::replace(&mut ai.undo,
+                Box::new(|_, _| unreachable!("Undo should be restored")))),
+            heuristic: ai.heuristic.take().map(Arc::new),
+            bound: ai.bound.take().map(Arc::new),
+            player: ai.player.take().map(Arc::new),
+            node_kind: ai.node_kind.take().map(Arc::new),
+            state_hash: ai.state_hash.take().map(Arc::new),
+            cost: ai.cost.take().map(Arc::new),
+            order: ai.order.take().map(Arc::new),
+            is_goal: ai.is_goal.take().map(Arc::new),
+            forbid: ai.forbid.take().map(Arc::new),
+            approve: ai.approve.take().map(Arc::new),
+            impact: ai.impact.take().map(Arc::new),
+            value_estimator: ai.value_estimator.take().map(Arc::new),
+            data_size: ai.data_size.take().map(Arc::new),
+            action_size: ai.action_size.take().map(Arc::new),
+            on_expand: ai.hooks.on_expand.take().map(Arc::new),
+            on_improve: ai.hooks.on_improve.take().map(Arc::new),
+            on_prune: ai.hooks.on_prune.take().map(Arc::new),
+            on_progress: ai.hooks.on_progress.take().map(Arc::new),
+            schedule: ai.settings.schedule.take().map(Arc::new),
+            counters: Arc::new(Counters {
+                nodes_created: AtomicUsize::new(nodes_created),
+                node_count: AtomicUsize::new(node_count),
+                stop: Mutex::new(ai.analysis.stop),
+            }),
+        }
+    }
+
+    /// Returns an AI setup for a worker, calling the shared closures and hooks.
+    fn worker(&self, ai: &Ai<T, A, C, U, E>) -> Ai<T, A, C, U, E> {
+        let utility = self.utility.clone();
+        let actions = self.actions.clone();
+        let execute = self.execute.clone();
+        let undo = self.undo.clone();
+        let mut hooks = Hooks::new();
+        hooks.on_expand = self.on_expand.clone().map(|f| Box::new(move |data: &_, n, depth| f(data, n, depth)) as _);
+        hooks.on_improve = self.on_improve.clone().map(|f| Box::new(move |data: &_, u, depth| f(data, u, depth)) as _);
+        hooks.on_prune = self.on_prune.clone().map(|f| Box::new(move |data: &_, n| f(data, n)) as _);
+        hooks.on_progress = self.on_progress.clone().map(|f| Box::new(move |progress: &_| f(progress)) as _);
+        hooks.progress_interval = ai.hooks.progress_interval;
+        hooks.best = ai.hooks.best;
+        let mut analysis = AiAnalysis::new();
+        analysis.search_start = ai.analysis.search_start;
+        analysis.worker = Some(WorkerCounters {shared: self.counters.clone(), nodes_created: 0, node_count: 0});
+        Ai {
+            utility: Box::new(move |data, ctx| utility(data, ctx)),
+            actions: Box::new(move |data, ctx| actions(data, ctx)),
+            execute: Box::new(move |data, a, ctx| execute(data, a, ctx)),
+            undo: Box::new(move |data, ctx| undo(data, ctx)),
+            heuristic: self.heuristic.clone().map(|f| Box::new(move |data: &_, ctx: &_| f(data, ctx)) as _),
+            bound: self.bound.clone().map(|f| Box::new(move |data: &_, ctx: &_, depth| f(data, ctx, depth)) as _),
+            player: self.player.clone().map(|f| Box::new(move |data: &_, ctx: &_| f(data, ctx)) as _),
+            node_kind: self.node_kind.clone().map(|f| Box::new(move |data: &_, ctx: &_| f(data, ctx)) as _),
+            state_hash: self.state_hash.clone().map(|f| Box::new(move |data: &_, ctx: &_| f(data, ctx)) as _),
+            cost: self.cost.clone().map(|f| Box::new(move |data: &_, a: &_, ctx: &_| f(data, a, ctx)) as _),
+            order: self.order.clone().map(|f| Box::new(move |data: &_, ctx: &_, a: &_| f(data, ctx, a)) as _),
+            is_goal: self.is_goal.clone().map(|f| Box::new(move |data: &_, ctx: &_| f(data, ctx)) as _),
+            forbid: self.forbid.clone().map(|f| Box::new(move |data: &_, a: &_, ctx: &_| f(data, a, ctx)) as _),
+            approve: self.approve.clone().map(|f| Box::new(move |data: &_, a: &_, ctx: &_| f(data, a, ctx)) as _),
+            impact: self.impact.clone().map(|f| Box::new(move |data: &_, ctx: &_| f(data, ctx)) as _),
+            value_estimator: self.value_estimator.clone()
+                .map(|f| Box::new(move |data: &T, ctx: &C| f.estimate(data, ctx)) as _),
+            data_size: self.data_size.clone().map(|f| Box::new(move |data: &_| f(data)) as _),
+            action_size: self.action_size.clone().map(|f| Box::new(move |a: &_| f(a)) as _),
+            trace: ai.trace.as_ref().map(|trace| trace.fork()),
+            // Workers do not commit actions.
+            audit: None,
+            undo_check: ai.undo_check.as_ref().map(|check| check.fork()),
+            undo_strategy: ai.undo_strategy.fork(),
+            settings: AiSettings {
+                cancel: ai.settings.cancel.clone(),
+                schedule: self.schedule.clone().map(|f| Box::new(move |depth| f(depth)) as _),
+                ..ai.settings
+            },
+            analysis,
+            hooks,
+        }
+    }
+
+    /// Moves closures and hooks back into the AI setup.
+    ///
+    /// Panics if a worker is not dropped.
+    fn restore(self, ai: &mut Ai<T, A, C, U, E>) {
+        fn unwrap<F>(f: Arc<F>) -> F {
+            Arc::try_unwrap(f).ok().expect("Workers should be dropped")
+        }
+
+        ai.utility = unwrap(self.utility);
+        ai.actions = unwrap(self.actions);
+        ai.execute = unwrap(self.execute);
+        ai.undo = unwrap(self.undo);
+        ai.heuristic = self.heuristic.map(unwrap);
+        ai.bound = self.bound.map(unwrap);
+        ai.player = self.player.map(unwrap);
+        ai.node_kind = self.node_kind.map(unwrap);
+        ai.state_hash = self.state_hash.map(unwrap);
+        ai.cost = self.cost.map(unwrap);
+        ai.order = self.order.map(unwrap);
+        ai.is_goal = self.is_goal.map(unwrap);
+        ai.forbid = self.forbid.map(unwrap);
+        ai.approve = self.approve.map(unwrap);
+        ai.impact = self.impact.map(unwrap);
+        ai.value_estimator = self.value_estimator.map(unwrap);
+        ai.data_size = self.data_size.map(unwrap);
+        ai.action_size = self.action_size.map(unwrap);
+        ai.hooks.on_expand = self.on_expand.map(unwrap);
+        ai.hooks.on_improve = self.on_improve.map(unwrap);
+        ai.hooks.on_prune = self.on_prune.map(unwrap);
+        ai.hooks.on_progress = self.on_progress.map(unwrap);
+        ai.settings.schedule = self.schedule.map(unwrap);
+    }
+}
+
+impl<T, A, C, U, E> Ai<T, A, C, U, E>
+    where T: 'static, A: 'static, C: 'static, U: Utility + 'static, E: ExecuteError + 'static
+{
+    /// Performs a full construction of the entire maximum tree,
+    /// searching children of the root on separate threads.
+    ///
+    /// The root is expanded like `Ai::full`,
+    /// then each child is searched by a worker like `Ai::full`, with a clone of the context.
+    /// Branch-and-bound starts from the utility of the root,
+    /// and transpositions are only detected within the subtree of a worker.
+    pub fn full_par(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C)
+        where T: Send + Sync, A: Clone + Send, C: Clone + Send, U: Send, E: Send
+    {
+        let started = self.start_timer();
+        let mut full = Full::new();
+        let order = full.enter(self, &mut NodeStorage::new(root), depth, ctx);
+
+        let mut children: Vec<_> = std::mem::take(&mut root.children).into_iter().map(Some).collect();
+        let shared = Shared::new(self);
+        let tasks: Vec<_> = order.into_iter().map(|i| {
+            let (a, ch) = children[i].take().expect("Child should only be visited once");
+            (i, a, ch, ctx.clone(), shared.worker(self), full.fork())
+        }).collect();
+        let data = &root.data;
+        let results: Vec<_> = tasks.into_par_iter().map(|(i, a, mut ch, mut ctx, mut ai, mut dfs)| {
+            if ai.try_execute(data, &a, &mut ctx).is_some() {
+                if dfs.descend(&mut ai, &NodeStorage::new(&mut ch), depth + 1, &ctx) {
+                    ai.depth_first(&mut NodeStorage::new(&mut ch), depth + 1, &mut ctx, &mut dfs);
+                }
+
+                // Undo changes made to context to reset state.
+                ai.revert(&ch.data, &mut ctx);
+            }
+            (i, a, ch, ai)
+        }).collect();
+
+        for (i, a, ch, ai) in results {
+            self.join_worker(ai, true);
+            children[i] = Some((a, ch));
+        }
+        shared.restore(self);
+        root.children = children.into_iter().map(|ch| ch.expect("Child should be restored")).collect();
+
+        // Update maximum utility since children are changed.
+        for (_, ch) in &root.children {
+            if ch.max > root.max {
+                root.max = ch.max;
+            }
+        }
+        full.leave(self, &mut NodeStorage::new(root), depth, ctx);
+        self.stop_timer(started);
+    }
+
+    /// Runs multiple greedy searches on separate threads, returning the tree with highest utility.
+    ///
+    /// The first run is a normal greedy search.
+    /// Every other run follows a random non-optimal child with probability `epsilon`,
+    /// like `Ai::epsilon_greedy`, using `seed` plus the index of the run as random seed.
+    /// This explores different local maxima.
+    ///
+    /// The root factory is called for every run to construct a new root.
+    /// Each run uses a clone of the context.
+    /// Nodes created by every run are counted in `AiAnalysis::nodes_created`,
+    /// but only nodes of the returned tree are counted in `AiAnalysis::node_count`.
+    pub fn greedy_par(
+        &mut self,
+        mut root_factory: impl FnMut() -> Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        runs: usize,
+        epsilon: f64,
+        seed: u64,
+    ) -> Node<T, A, U>
+        where T: Send, A: Clone + Send, C: Clone + Send, U: Send, E: Send
+    {
+        let started = self.start_timer();
+        let shared = Shared::new(self);
+        let tasks: Vec<_> = (0..runs.max(1))
+            .map(|k| (k, root_factory(), ctx.clone(), shared.worker(self)))
+            .collect();
+        let results: Vec<_> = tasks.into_par_iter().map(|(k, mut root, mut ctx, mut ai)| {
+            let epsilon = if k == 0 {0.0} else {epsilon};
+            let mut rng = Rng::new(seed.wrapping_add(k as u64));
+            ai.epsilon_greedy(&mut root, depth, &mut ctx, epsilon, &mut rng);
+            (root, ai)
+        }).collect();
+
+        let mut best = 0;
+        for (k, (root, _)) in results.iter().enumerate() {
+            if root.max > results[best].0.max {best = k};
+        }
+        let mut best_root = None;
+        for (k, (root, ai)) in results.into_iter().enumerate() {
+            self.join_worker(ai, k == best);
+            if k == best {best_root = Some(root)};
+        }
+        shared.restore(self);
+        self.stop_timer(started);
+        best_root.expect("There should be at least one run")
+    }
+
+    /// Merges analysis, traces and undo checks of a worker.
+    ///
+    /// The nodes of the worker are only counted when its tree is kept.
+    fn join_worker(&mut self, mut worker: Ai<T, A, C, U, E>, keep: bool) {
+        let analysis = &mut worker.analysis;
+        self.analysis.nodes_created += analysis.nodes_created;
+        if keep && self.settings.analysis {
+            self.analysis.node_count += analysis.node_count;
+        }
+        self.merge_worker_errors(std::mem::take(&mut analysis.errors), analysis.aborted,
+            analysis.abort_error);
+        if analysis.target_reached {self.analysis.target_reached = true};
+        self.record_stop(analysis.stop);
+        self.analysis.pruned += analysis.pruned;
+        self.analysis.max_depth_reached = self.analysis.max_depth_reached.max(analysis.max_depth_reached);
+        self.analysis.failed_executes += analysis.failed_executes;
+        let histogram = &mut self.analysis.depth_histogram;
+        if histogram.len() < analysis.depth_histogram.len() {
+            histogram.resize(analysis.depth_histogram.len(), 0);
+        }
+        for (n, m) in histogram.iter_mut().zip(&analysis.depth_histogram) {*n += m}
+        self.analysis.heap_bytes += analysis.heap_bytes;
+
+        self.hooks.expansions += worker.hooks.expansions;
+        if let Some(best) = worker.hooks.best {
+            if self.hooks.best.map(|b| best > b).unwrap_or(true) {self.hooks.best = Some(best)};
+        }
+        if let (Some(trace), Some(other)) = (&mut self.trace, worker.trace.take()) {trace.join(other)};
+        if let (Some(check), Some(other)) = (&mut self.undo_check, worker.undo_check.take()) {check.join(other)};
+    }
+
+    /// Collects errors of a worker, keeping the first aborting error.
+    fn merge_worker_errors(&mut self, errors: Vec<E>, aborted: bool, abort_error: Option<usize>) {
+        if aborted {self.analysis.aborted = true};
+        if let Some(i) = abort_error {
+            if self.analysis.abort_error.is_none() {
+                self.analysis.abort_error = Some(self.analysis.errors.len() + i);
+            }
+        }
+        self.analysis.errors.extend(errors);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn same_as_full() {
+        let mut ai = line_ai(5);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let node_count = ai.analysis.node_count;
+
+        let mut ai = line_ai(5);
+        ai.settings.analysis = true;
+        let mut par_root = Node::root(0);
+        ai.full_par(&mut par_root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(ai.analysis.node_count, node_count);
+        assert_eq!(ai.analysis.nodes_created, node_count);
+        assert_eq!(ai.analysis.depth_histogram.iter().sum::<usize>(), node_count);
+        assert_eq!(par_root.max, root.max);
+        assert_eq!(par_root.optimal_path(), root.optimal_path());
+    }
+
+    #[test]
+    fn merges_worker_statistics() {
+        let mut ai = line_ai(4);
+        ai.settings.analysis = true;
+        ai.settings.max_children = Some(1);
+        let mut pos = 0;
+        ai.full(&mut Node::root(0), 0, &mut pos);
+        let pruned = ai.analysis.pruned;
+        let histogram = ai.analysis.depth_histogram.clone();
+
+        ai.full_par(&mut Node::root(0), 0, &mut pos);
+        assert_eq!(ai.analysis.pruned, pruned);
+        assert_eq!(ai.analysis.depth_histogram, histogram);
+        assert_eq!(ai.analysis.max_depth_reached, 5);
+    }
+
+    #[test]
+    fn full_par_shares_node_limit() {
+        let mut ai = line_ai(5);
+        ai.settings.max_nodes = Some(20);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full_par(&mut root, 0, &mut pos);
+        assert_eq!(ai.analysis.stop, Some(StopReason::NodeLimit));
+        // Workers might create a few nodes each before seeing the shared count.
+        assert!(ai.analysis.nodes_created < 20 + 2 * 2);
+        assert_eq!(root.descendants(), ai.analysis.nodes_created);
+        // The closures are restored.
+        ai.full(&mut Node::root(0), 0, &mut pos);
+    }
+
+    #[test]
+    fn greedy_par_escapes_local_maximum() {
+        let mut ai = line_ai(8);
+        // Local maximum at position 3, global maximum at position -4.
+        ai.utility = Box::new(|_, pos| if *pos <= -4 {10.0} else {-(*pos as f64 - 3.0).abs()});
+        let mut pos = 0;
+        let root = ai.greedy_par(|| Node::root(0), 0, &mut pos, 1, 0.5, 0);
+        assert!(root.max < 1.0);
+        ai.settings.analysis = true;
+        let root = ai.greedy_par(|| Node::root(0), 0, &mut pos, 16, 0.5, 0);
+        assert!(root.max > 9.0);
+        assert_eq!(pos, 0);
+        // Only the returned tree is counted, but every run creates nodes.
+        assert_eq!(ai.analysis.node_count, root.descendants());
+        assert!(ai.analysis.nodes_created > ai.analysis.node_count);
+    }
+}
@@ -0,0 +1,73 @@
+//! Multi-objective planning with Pareto fronts.
+//!
+//! When there are several objectives, e.g. fuel, time and landing speed,
+//! scalarizing them into a single utility up front hides plans with interesting trade-offs.
+//! Instead, objectives can be computed as a vector from node data,
+//! and the nondominated plans are extracted from the tree after search.
+//!
+//! A plan is a path from root, ending at any node.
+//! A plan dominates another when it is at least as good in every objective,
+//! and strictly better in at least one, where higher is better.
+//! The Pareto front is the set of plans that are not dominated by any other plan.
+//!
+//! Since search algorithms prune using scalar utility,
+//! use `Ai::full` (without `Ai::bound`) to keep every plan in the tree.
+
+use crate::Node;
+
+/// Returns `true` if `a` dominates `b`.
+pub fn dominates<const N: usize>(a: &[f64; N], b: &[f64; N]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x >= y) && a.iter().zip(b).any(|(x, y)| x > y)
+}
+
+/// Reduces a list of values to the Pareto front, keeping the first of equal values.
+pub fn pareto_front<const N: usize, P>(values: Vec<([f64; N], P)>) -> Vec<([f64; N], P)> {
+    let mut front: Vec<([f64; N], P)> = vec![];
+    for (v, p) in values {
+        if front.iter().any(|(w, _)| dominates(w, &v) || *w == v) {continue};
+        front.retain(|(w, _)| !dominates(&v, w));
+        front.push((v, p));
+    }
+    front
+}
+
+impl<T, A, U> Node<T, A, U> {
+    /// Returns the nondominated plans, with objectives and path of child indices.
+    ///
+    /// Objectives are computed from node data.
+    /// Plans are returned in pre-order, keeping the first plan of equal objectives.
+    pub fn pareto_paths<const N: usize>(&self, objectives: impl Fn(&T) -> [f64; N]) -> Vec<([f64; N], Vec<usize>)> {
+        pareto_front(self.iter_preorder().map(|(path, node)| (objectives(&node.data), path)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ai;
+
+    #[test]
+    fn trade_offs() {
+        assert!(dominates(&[1.0, 1.0], &[1.0, 0.0]));
+        assert!(!dominates(&[1.0, 1.0], &[1.0, 1.0]));
+        assert!(!dominates(&[2.0, 0.0], &[1.0, 1.0]));
+
+        let mut ai: Ai<i32, i32, ()> = Ai::builder()
+            .utility(|_, _| 0.0)
+            .actions(|_, _| vec![-1, 1])
+            .execute(|pos, a, _| Ok(pos + a))
+            .undo(|_, _| {})
+            .max_depth(3)
+            .build();
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut ());
+        // Being far right trades off against being close to origin.
+        let front = root.pareto_paths(|pos| [*pos as f64, -(*pos as f64).abs()]);
+        let mut values: Vec<[f64; 2]> = front.iter().map(|(v, _)| *v).collect();
+        values.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap());
+        assert_eq!(values, vec![[0.0, 0.0], [1.0, -1.0], [2.0, -2.0], [3.0, -3.0], [4.0, -4.0]]);
+        for (v, path) in &front {
+            assert_eq!(root.get(path).unwrap().data as f64, v[0]);
+        }
+    }
+}
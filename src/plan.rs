@@ -0,0 +1,89 @@
+//! Plans extracted from a maximum tree.
+//!
+//! A path of child indices, e.g. from `Node::optimal_path`,
+//! requires traversing the tree again to recover actions and utilities.
+//! A `Plan` stores the actions along a path together with utilities,
+//! such that it can be inspected or executed without the tree.
+
+use crate::Node;
+use crate::utility::Utility;
+
+/// A sequence of actions from root, with utilities.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Plan<A, U = f64> {
+    /// The path of child indices from root.
+    pub path: Vec<usize>,
+    /// The actions to execute, in order.
+    pub actions: Vec<A>,
+    /// The maximum utility of the node reached by each action.
+    pub utilities: Vec<U>,
+    /// The maximum utility of the endpoint, which is the maximum utility of root for empty plans.
+    pub utility: U,
+    /// Whether the endpoint is terminal, see `Node::terminal`.
+    pub terminal: bool,
+}
+
+impl<A, U> Plan<A, U> {
+    /// Returns the number of actions.
+    pub fn len(&self) -> usize {self.actions.len()}
+
+    /// Returns `true` if the plan has no actions.
+    pub fn is_empty(&self) -> bool {self.actions.is_empty()}
+}
+
+impl<T, A: Clone, U: Utility> Node<T, A, U> {
+    /// Returns the plan along the optimal path, see `Node::optimal_path`.
+    ///
+    /// The endpoint of the optimal path is always terminal.
+    pub fn plan(&self) -> Plan<A, U> {
+        self.plan_along(&self.optimal_path()).unwrap()
+    }
+
+    /// Returns the plan along a path of child indices.
+    ///
+    /// Returns `None` if an index is out of bounds.
+    pub fn plan_along(&self, path: &[usize]) -> Option<Plan<A, U>> {
+        let mut node = self;
+        let mut actions = Vec::with_capacity(path.len());
+        let mut utilities = Vec::with_capacity(path.len());
+        for &i in path {
+            let (a, ch) = node.children.get(i)?;
+            actions.push(a.clone());
+            utilities.push(ch.max);
+            node = ch;
+        }
+        Some(Plan {
+            path: path.to_vec(),
+            actions,
+            utilities,
+            utility: node.max,
+            terminal: node.terminal(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn extracts_plan() {
+        let mut ai = line_ai(4);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let plan = root.plan();
+        assert_eq!(plan.path, root.optimal_path());
+        assert_eq!(plan.actions, vec![1, 1, 1]);
+        assert_eq!(plan.utilities.len(), 3);
+        assert_eq!(plan.utility, root.max);
+        assert!(plan.terminal);
+
+        let plan = root.plan_along(&plan.path[..1]).unwrap();
+        assert_eq!(plan.len(), 1);
+        assert!(!plan.terminal);
+        assert!(root.plan_along(&[5]).is_none());
+    }
+}
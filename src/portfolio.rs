@@ -0,0 +1,174 @@
+//! Portfolio search, running multiple algorithms and keeping the best tree.
+//!
+//! Each strategy in a portfolio constructs its own maximum tree,
+//! using a clone of the context such that strategies do not interfere.
+//! The tree with highest maximum utility at root is kept,
+//! together with a report for every strategy for comparison.
+//!
+//! Every strategy starts with fresh analysis,
+//! so memory and time limits apply to each strategy separately.
+
+use std::time::{Duration, Instant};
+
+use crate::{Ai, AiAnalysis, Node};
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// A custom search algorithm.
+///
+/// The arguments are AI, root, depth and context.
+pub type SearchFn<T, A, C, U = f64, E = ()> = fn(&mut Ai<T, A, C, U, E>, &mut Node<T, A, U>, usize, &mut C);
+
+/// A search strategy in a portfolio.
+pub enum Strategy<T, A, C, U = f64, E = ()> {
+    /// Runs `Ai::greedy`.
+    Greedy,
+    /// Runs `Ai::beam` with the specified width.
+    Beam(usize),
+    /// Runs `Ai::full`.
+    Full,
+    /// Runs `Ai::iterative_deepening`.
+    IterativeDeepening,
+    /// Runs a custom search algorithm.
+    Custom(SearchFn<T, A, C, U, E>),
+}
+
+/// Stores a strategy with name and settings.
+pub struct PortfolioEntry<T, A, C, U = f64, E = ()> {
+    /// The name used in reports.
+    pub name: String,
+    /// The search strategy.
+    pub strategy: Strategy<T, A, C, U, E>,
+    /// Overrides `AiSettings::max_depth` when set.
+    pub max_depth: Option<usize>,
+}
+
+/// Stores a list of strategies.
+pub struct Portfolio<T, A, C, U = f64, E = ()> {
+    /// The strategies in order of execution.
+    pub entries: Vec<PortfolioEntry<T, A, C, U, E>>,
+}
+
+impl<T, A, C, U, E> Default for Portfolio<T, A, C, U, E> {
+    fn default() -> Portfolio<T, A, C, U, E> {Portfolio::new()}
+}
+
+impl<T, A, C, U, E> Portfolio<T, A, C, U, E> {
+    /// Creates a new empty portfolio.
+    pub fn new() -> Portfolio<T, A, C, U, E> {
+        Portfolio {entries: vec![]}
+    }
+
+    /// Adds a strategy using the maximum depth of AI settings.
+    pub fn add(&mut self, name: &str, strategy: Strategy<T, A, C, U, E>) -> &mut Self {
+        self.entries.push(PortfolioEntry {name: name.into(), strategy, max_depth: None});
+        self
+    }
+
+    /// Adds a strategy with a maximum depth.
+    pub fn add_with_depth(&mut self, name: &str, strategy: Strategy<T, A, C, U, E>, max_depth: usize) -> &mut Self {
+        self.entries.push(PortfolioEntry {name: name.into(), strategy, max_depth: Some(max_depth)});
+        self
+    }
+}
+
+/// Stores results of running a strategy.
+pub struct PortfolioReport<U = f64, E = ()> {
+    /// The name of strategy.
+    pub name: String,
+    /// The maximum utility at root of the constructed tree.
+    pub max: U,
+    /// The analysis of the strategy.
+    ///
+    /// The node count is only tracked when `AiSettings::analysis` is activated.
+    pub analysis: AiAnalysis<E>,
+    /// The time spent on search.
+    pub elapsed: Duration,
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Runs every strategy in portfolio, returning the tree with highest utility
+    /// and a report for every strategy.
+    ///
+    /// The root factory is called for every strategy to construct a new root.
+    /// When several trees have equal utility, the first one is kept.
+    /// After search, the node count of the kept tree is added to analysis.
+    pub fn portfolio(
+        &mut self,
+        portfolio: &Portfolio<T, A, C, U, E>,
+        mut root_factory: impl FnMut() -> Node<T, A, U>,
+        depth: usize,
+        ctx: &C,
+    ) -> (Node<T, A, U>, Vec<PortfolioReport<U, E>>)
+        where A: Clone, C: Clone
+    {
+        let max_depth = self.settings.max_depth;
+        let analysis = std::mem::take(&mut self.analysis);
+        let mut best: Option<(Node<T, A, U>, usize)> = None;
+        let mut reports = Vec::with_capacity(portfolio.entries.len());
+        for entry in &portfolio.entries {
+            let mut root = root_factory();
+            let mut ctx = ctx.clone();
+            self.settings.max_depth = entry.max_depth.unwrap_or(max_depth);
+            let instant = Instant::now();
+            match entry.strategy {
+                Strategy::Greedy => {self.greedy(&mut root, depth, &mut ctx);}
+                Strategy::Beam(width) => self.beam(&mut root, depth, &mut ctx, width),
+                Strategy::Full => {self.full(&mut root, depth, &mut ctx);}
+                Strategy::IterativeDeepening => self.iterative_deepening(&mut root, depth, &mut ctx),
+                Strategy::Custom(f) => f(self, &mut root, depth, &mut ctx),
+            }
+            let elapsed = instant.elapsed();
+            if root.max.is_unknown() {
+                root.max = self.utility_with_settings(&root.data, depth, &ctx);
+            }
+
+            let run = std::mem::take(&mut self.analysis);
+            let node_count = run.node_count;
+            reports.push(PortfolioReport {
+                name: entry.name.clone(),
+                max: root.max,
+                analysis: AiAnalysis {search_start: None, ..run},
+                elapsed,
+            });
+            if best.as_ref().map(|b| root.max > b.0.max).unwrap_or(true) {
+                best = Some((root, node_count));
+            }
+        }
+        self.settings.max_depth = max_depth;
+        self.analysis = analysis;
+
+        match best {
+            Some((root, node_count)) => {
+                self.analysis.node_count += node_count;
+                (root, reports)
+            }
+            None => (root_factory(), reports),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn keeps_best() {
+        let mut ai = line_ai(1);
+        ai.settings.analysis = true;
+        let mut portfolio = Portfolio::new();
+        portfolio
+            .add("greedy", Strategy::Greedy)
+            .add("beam", Strategy::Beam(2))
+            .add_with_depth("full", Strategy::Full, 4);
+        let pos = 0;
+        let (root, reports) = ai.portfolio(&portfolio, || Node::root(0), 0, &pos);
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[2].name, "full");
+        assert!(reports[2].max > reports[0].max);
+        assert_eq!(ai.settings.max_depth, 1);
+        assert_eq!(root.max, reports[2].max);
+        assert_eq!(ai.analysis.node_count, root.descendants());
+    }
+}
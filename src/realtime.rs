@@ -0,0 +1,157 @@
+//! Real-time search (LRTA*-style).
+//!
+//! Instead of finishing a whole search before acting,
+//! real-time search interleaves a limited lookahead with committing to one action.
+//! The action is executed in the context and is not undone.
+//!
+//! To avoid getting stuck in local maxima,
+//! value estimates of states are learned between steps.
+//! After the lookahead, the value of the current state is updated to the best child value.
+//! When the lookahead reaches a state with a learned value,
+//! the learned value is used instead of utility.
+//! States are identified by `Ai::state_hash`.
+
+use std::collections::HashMap;
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Runs real-time search for a number of steps, committing actions to context.
+    ///
+    /// The lookahead depth of each step is `AiSettings::max_depth`.
+    /// Learned values are stored in `values` by state hash,
+    /// such that they can be reused across calls.
+    /// Requires `Ai::state_hash`, otherwise no values are learned.
+    ///
+    /// Stops when there are no available actions, an action fails to execute,
+    /// or a limit of memory or time is exceeded.
+    /// Returns the executed actions and the node data of the final state.
+    pub fn realtime(
+        &mut self,
+        data: T,
+        ctx: &mut C,
+        steps: usize,
+        values: &mut HashMap<u64, U>,
+    ) -> (Vec<A>, T)
+        where A: Clone
+    {
+        let started = self.start_timer();
+        let mut executed = vec![];
+        let mut data = data;
+        for _ in 0..steps {
+            if self.limit_exceeded() {break};
+
+            let mut root = Node::root(data);
+            root.max = self.utility_with_settings(&root.data, 0, ctx);
+            self.sub_breadth(&mut root, 0, ctx);
+            for (a, ch) in &mut root.children {
+                if self.try_execute(&root.data, a, ctx).is_some() {
+                    self.realtime_lookahead(ch, 1, ctx, values);
+
+                    // Undo changes made to context to reset state.
+                    self.revert(&ch.data, ctx);
+                }
+            }
+
+            let mut best: Option<usize> = None;
+            for (i, ch) in root.children.iter().enumerate() {
+                if best.map(|j| ch.1.max > root.children[j].1.max).unwrap_or(true) {
+                    best = Some(i);
+                }
+            }
+
+            if self.settings.analysis {
+                self.analysis.node_count -= root.descendants();
+            }
+            let i = match best {
+                Some(i) => i,
+                None => {
+                    data = root.into_data();
+                    break;
+                }
+            };
+
+            // Update learned value of current state.
+            if let Some(state_hash) = &self.state_hash {
+                values.insert(state_hash(&root.data, ctx), root.children[i].1.max);
+            }
+
+            // Commit action to context.
+            let a = root.children.swap_remove(i).0;
+            let new_data = self.commit(&root.data, &a, ctx);
+            let old_data = root.into_data();
+            match new_data {
+                Some(new_data) => {
+                    data = new_data;
+                    executed.push(a);
+                }
+                None => {
+                    data = old_data;
+                    break;
+                }
+            }
+        }
+        self.stop_timer(started);
+        (executed, data)
+    }
+
+    fn realtime_lookahead(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        values: &HashMap<u64, U>,
+    )
+        where A: Clone
+    {
+        if depth >= self.settings.max_depth || self.limit_exceeded() {
+            if let Some(state_hash) = &self.state_hash {
+                if let Some(&value) = values.get(&state_hash(&root.data, ctx)) {
+                    root.max = value;
+                }
+            }
+            return;
+        }
+
+        self.sub_breadth(root, depth, ctx);
+        for (a, ch) in &mut root.children {
+            if self.try_execute(&root.data, a, ctx).is_some() {
+                self.realtime_lookahead(ch, depth + 1, ctx, values);
+
+                // Undo changes made to context to reset state.
+                self.revert(&ch.data, ctx);
+            }
+        }
+
+        // Learned values might be lower than utility,
+        // so maximum utility is computed after expanding children.
+        root.max = self.utility_with_settings(&root.data, depth, ctx);
+        for (_, ch) in &root.children {
+            if ch.max > root.max {
+                root.max = ch.max;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn commits_actions() {
+        let mut ai = line_ai(1);
+        ai.state_hash = Some(Box::new(|_, pos| *pos as u64));
+        let mut values = HashMap::new();
+        let mut pos = 0;
+        let (executed, _) = ai.realtime(0, &mut pos, 6, &mut values);
+        assert_eq!(executed.len(), 6);
+        // Reaches the maximum and then moves around it.
+        assert_eq!(&executed[..3], &[1, 1, 1]);
+        assert!((pos - 3).abs() <= 1);
+        assert!(values.contains_key(&3));
+    }
+}
@@ -0,0 +1,174 @@
+//! Refinement of continuous actions.
+//!
+//! For continuous action spaces, e.g. thrust, picking a fixed set of values by hand
+//! either misses good actions or creates too many children.
+//! Instead, actions are sampled by a user-supplied function with increasing resolution.
+//! At each node, a coarse set of actions is sampled first,
+//! then actions around the best child are sampled at finer resolution a number of times.
+//!
+//! The search follows the best child after refinement, similar to `Ai::greedy`.
+//! For actions in a range of numbers, use `grid` as sampling function.
+
+use crate::{Ai, Node};
+use crate::depth_first::NodeStorage;
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// Samples numbers in a range for refinement, see `Ai::refine`.
+///
+/// Without a center, returns `n` numbers evenly spaced from `min` to `max`.
+/// With a center, returns up to `n` numbers around the center,
+/// where the spacing is halved for every level of resolution.
+/// Numbers outside the range are skipped.
+pub fn grid(min: f64, max: f64, center: Option<&f64>, resolution: usize, n: usize) -> Vec<f64> {
+    if n < 2 {return vec![center.copied().unwrap_or((min + max) / 2.0)]};
+    let step = (max - min) / (n - 1) as f64;
+    match center {
+        None => (0..n).map(|i| min + i as f64 * step).collect(),
+        Some(&center) => {
+            let step = step / 2.0_f64.powi(resolution as i32);
+            let mut res = vec![];
+            for k in 1..=n.div_ceil(2) {
+                for x in [center - k as f64 * step, center + k as f64 * step] {
+                    if res.len() < n && x >= min && x <= max {res.push(x)};
+                }
+            }
+            res
+        }
+    }
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Constructs a path of nodes with actions refined around the best child.
+    ///
+    /// The arguments of `sample` are node data, context,
+    /// the action of the best child (`None` for the coarse actions) and resolution,
+    /// which is `0` for coarse actions and increases by one for every level up to `levels`.
+    /// Actions that already have a child are skipped.
+    ///
+    /// Stops when the maximum depth is reached, a goal is reached,
+    /// or a limit of memory or time is exceeded.
+    pub fn refine(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        sample: impl Fn(&T, &C, Option<&A>, usize) -> Vec<A>,
+        levels: usize,
+    )
+        where A: PartialEq
+    {
+        let started = self.start_timer();
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let mut path = vec![];
+        let mut depth = depth;
+        loop {
+            let node = root.node_mut(&path);
+            if self.goal_reached(&node.data, ctx) {break};
+
+            let max = node.max;
+            let actions = sample(&node.data, ctx, None, 0);
+            self.refine_children(node, depth, ctx, actions);
+            for level in 1..=levels {
+                if self.limit_exceeded() {break};
+                let best = match best_child(node) {
+                    Some(i) => i,
+                    None => break,
+                };
+                let actions = sample(&node.data, ctx, Some(&node.children[best].0), level);
+                self.refine_children(node, depth, ctx, actions);
+            }
+            self.expanded(&node.data, node.children.len(), depth);
+            self.limit_children(&mut NodeStorage::new(node), depth, max);
+
+            if depth >= self.settings.max_depth || self.limit_exceeded() {break};
+            let i = match node.optimal() {
+                Some(i) => i,
+                None => break,
+            };
+            if self.try_execute(&node.data, &node.children[i].0, ctx).is_none() {break};
+            path.push(i);
+            depth += 1;
+        }
+
+        // Undo changes made to context to reset state.
+        self.rollback(root, &path, ctx);
+
+        // Update maximum utility since children are changed.
+        root.update_max();
+        self.stop_timer(started);
+    }
+
+    /// Adds children of actions that do not already have a child.
+    fn refine_children(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C, actions: Vec<A>)
+        where A: PartialEq
+    {
+        for a in actions {
+            if self.expansion_stopped() {
+                root.partial = true;
+                break;
+            }
+            if root.children.iter().any(|(b, _)| *b == a) {continue};
+            if let Some(data) = self.try_execute(&root.data, &a, ctx) {
+                let utility = self.child_utility(&data, depth + 1, ctx);
+
+                // Undo changes made to context to reset state.
+                self.revert(&data, ctx);
+
+                if utility > root.max {
+                    root.max = utility;
+                }
+                self.improved(&data, utility, depth + 1);
+                self.count_node(&data, &a, utility, depth + 1);
+                root.children.push((a, Node {
+                    max: utility,
+                    data,
+                    children: vec![],
+                    partial: false,
+                }));
+            }
+        }
+    }
+}
+
+/// Returns the index of the child with highest maximum utility.
+fn best_child<T, A, U: Utility>(node: &Node<T, A, U>) -> Option<usize> {
+    let mut best: Option<usize> = None;
+    for (i, ch) in node.children.iter().enumerate() {
+        if best.map(|j| ch.1.max > node.children[j].1.max).unwrap_or(true) {
+            best = Some(i);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refines_around_best() {
+        assert_eq!(grid(0.0, 1.0, None, 0, 3), vec![0.0, 0.5, 1.0]);
+        assert_eq!(grid(0.0, 1.0, Some(&1.0), 1, 3), vec![0.75, 0.5]);
+
+        // Find thrust that reaches a target position in one step.
+        let mut ai: Ai<f64, f64, f64> = Ai::builder()
+            .utility(|_, pos: &f64| -(*pos - 0.3).abs())
+            .actions(|_, _| vec![])
+            .execute(|_, a, pos| {*pos += a; Ok(*a)})
+            .undo(|a, pos| *pos -= a)
+            .max_depth(0)
+            .build();
+        let mut root = Node::root(0.0);
+        let mut pos = 0.0;
+        ai.refine(&mut root, 0, &mut pos, |_, _, center, res| grid(0.0, 1.0, center, res, 3), 3);
+        assert_eq!(pos, 0.0);
+        let best = root.children[root.optimal().unwrap()].0;
+        assert!((best - 0.3).abs() < 0.05);
+        // Three coarse thrusts, then two for every level.
+        assert_eq!(root.children.len(), 9);
+    }
+}
@@ -0,0 +1,77 @@
+//! Regret of a plan compared to a full search.
+//!
+//! Given a tree constructed by `Ai::full` and a path chosen by another algorithm,
+//! e.g. `Ai::greedy` or `Ai::beam`, the regret measures how much utility was lost.
+//! Nodes are matched by the sequence of actions from root, like in `diff`.
+//!
+//! The regret of a step is the maximum utility of the node before the step,
+//! minus the maximum utility of the chosen child.
+//! Since maximum utility is what is achievable from a node,
+//! the total regret is the sum of regret of every step.
+
+use crate::Node;
+
+/// Stores the regret of a path.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Regret {
+    /// The regret of each step.
+    pub steps: Vec<f64>,
+    /// The total regret.
+    pub total: f64,
+}
+
+/// Computes the regret of a sequence of actions in a full tree.
+///
+/// Returns `None` if some action is not found in the tree.
+pub fn regret<T, A>(full: &Node<T, A>, actions: &[A]) -> Option<Regret>
+    where A: PartialEq
+{
+    let mut steps = Vec::with_capacity(actions.len());
+    let mut node = full;
+    for a in actions {
+        let ch = &node.children.iter().find(|(b, _)| b == a)?.1;
+        steps.push(node.max - ch.max);
+        node = ch;
+    }
+    Some(Regret {total: full.max - node.max, steps})
+}
+
+/// Computes the regret of the optimal path of another tree in a full tree.
+///
+/// Returns `None` if some action on the optimal path is not found in the full tree.
+pub fn tree_regret<T, A>(full: &Node<T, A>, other: &Node<T, A>) -> Option<Regret>
+    where A: Clone + PartialEq
+{
+    let mut actions = vec![];
+    let mut node = other;
+    while let Some(i) = node.optimal() {
+        actions.push(node.children[i].0.clone());
+        node = &node.children[i].1;
+    }
+    regret(full, &actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn greedy_loses_utility() {
+        let mut ai = line_ai(3);
+        ai.settings.eps_depth = 0.0;
+        // A local maximum at -1 hides the global maximum at 3.
+        ai.utility = Box::new(|_, pos| match *pos {-1 => 1.0, 3 => 2.0, _ => 0.0});
+        let mut full = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut full, 0, &mut pos);
+        let mut greedy = Node::root(0);
+        ai.greedy(&mut greedy, 0, &mut pos);
+
+        let res = tree_regret(&full, &greedy).unwrap();
+        assert_eq!(res.steps, vec![1.0]);
+        assert_eq!(res.total, 1.0);
+        assert_eq!(tree_regret(&full, &full).unwrap().total, 0.0);
+        assert_eq!(regret(&full, &[7]), None);
+    }
+}
@@ -0,0 +1,156 @@
+//! Export of analysis reports as JSON or CSV.
+//!
+//! A report contains the counters collected in `AiAnalysis`
+//! together with the `AiSettings` used for the search,
+//! such that results from many runs can be aggregated by other tools.
+//!
+//! The number of errors is reported, but not the errors themselves.
+//! Settings that are not set are written as `null` in JSON and as empty fields in CSV.
+//! For CSV, use `AiAnalysis::csv_header` for the first line and `AiAnalysis::to_csv` per run.
+
+use std::fmt::Write;
+
+use crate::{AiAnalysis, AiSettings};
+
+/// A value in a report.
+enum Value {
+    Int(usize),
+    Float(f64),
+    Bool(bool),
+    List(Vec<usize>),
+    Null,
+}
+
+impl From<usize> for Value {
+    fn from(val: usize) -> Value {Value::Int(val)}
+}
+
+impl From<f64> for Value {
+    fn from(val: f64) -> Value {Value::Float(val)}
+}
+
+impl From<bool> for Value {
+    fn from(val: bool) -> Value {Value::Bool(val)}
+}
+
+impl<V: Into<Value>> From<Option<V>> for Value {
+    fn from(val: Option<V>) -> Value {
+        match val {
+            Some(val) => val.into(),
+            None => Value::Null,
+        }
+    }
+}
+
+impl<E> AiAnalysis<E> {
+    /// Returns a JSON object with analysis and settings used.
+    pub fn to_json(&self, settings: &AiSettings) -> String {
+        let mut s = String::from("{");
+        for (i, (key, val)) in self.report(settings).into_iter().enumerate() {
+            if i > 0 {s.push_str(", ")};
+            let _ = write!(s, "\"{}\": ", key);
+            match val {
+                Value::Int(x) => {let _ = write!(s, "{}", x);}
+                // JSON does not support infinity or not a number.
+                Value::Float(x) if !x.is_finite() => s.push_str("null"),
+                Value::Float(x) => {let _ = write!(s, "{:?}", x);}
+                Value::Bool(x) => {let _ = write!(s, "{}", x);}
+                Value::List(xs) => {
+                    let xs: Vec<String> = xs.iter().map(|x| x.to_string()).collect();
+                    let _ = write!(s, "[{}]", xs.join(", "));
+                }
+                Value::Null => s.push_str("null"),
+            }
+        }
+        s.push('}');
+        s
+    }
+
+    /// Returns the header line of CSV reports, see `AiAnalysis::to_csv`.
+    pub fn csv_header() -> String {
+        let settings = AiSettings::new(0, 0.0);
+        let keys: Vec<&str> = AiAnalysis::<E>::new().report(&settings).into_iter()
+            .map(|(key, _)| key).collect();
+        keys.join(",")
+    }
+
+    /// Returns a CSV line with analysis and settings used, without header.
+    ///
+    /// The depth histogram is written as numbers separated by `;`.
+    pub fn to_csv(&self, settings: &AiSettings) -> String {
+        let fields: Vec<String> = self.report(settings).into_iter().map(|(_, val)| {
+            match val {
+                Value::Int(x) => x.to_string(),
+                Value::Float(x) => x.to_string(),
+                Value::Bool(x) => x.to_string(),
+                Value::List(xs) => {
+                    let xs: Vec<String> = xs.iter().map(|x| x.to_string()).collect();
+                    xs.join(";")
+                }
+                Value::Null => String::new(),
+            }
+        }).collect();
+        fields.join(",")
+    }
+
+    fn report(&self, settings: &AiSettings) -> Vec<(&'static str, Value)> {
+        vec![
+            ("node_count", self.node_count.into()),
+            ("nodes_created", self.nodes_created.into()),
+            ("errors", self.errors.len().into()),
+            ("aborted", self.aborted.into()),
+            ("search_time", self.search_time.as_secs_f64().into()),
+            ("pruned", self.pruned.into()),
+            ("max_depth_reached", self.max_depth_reached.into()),
+            ("failed_executes", self.failed_executes.into()),
+            ("heap_bytes", self.heap_bytes.into()),
+            ("depth_histogram", Value::List(self.depth_histogram.clone())),
+            ("max_depth", settings.max_depth.into()),
+            ("eps_depth", settings.eps_depth.into()),
+            ("analysis", settings.analysis.into()),
+            ("greed_elim", settings.greed_elim.into()),
+            ("min_gain", settings.min_gain.into()),
+            ("max_mib", settings.max_mib.into()),
+            ("max_time", settings.max_time.map(|t| t.as_secs_f64()).into()),
+            ("max_nodes", settings.max_nodes.into()),
+            ("max_children", settings.max_children.into()),
+            ("exploration", settings.exploration.into()),
+            ("astar_weight", settings.astar_weight.into()),
+            ("temperature", settings.temperature.into()),
+            ("transpositions", settings.transpositions.into()),
+            ("avoid_cycles", settings.avoid_cycles.into()),
+            ("utility_min", settings.utility_clamp.map(|(min, _)| min).into()),
+            ("utility_max", settings.utility_clamp.map(|(_, max)| max).into()),
+            ("impact_weight", settings.impact_weight.into()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn exports_report() {
+        let mut ai = line_ai(2);
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+
+        let json = ai.analysis.to_json(&ai.settings);
+        assert!(json.starts_with("{\"node_count\": 14, "));
+        assert!(json.contains("\"depth_histogram\": [0, 2, 4, 8], "));
+        assert!(json.contains("\"max_time\": null, "));
+        assert!(json.contains("\"utility_max\": null, "));
+        assert!(json.ends_with("\"impact_weight\": 1.0}"));
+
+        let header = crate::AiAnalysis::<()>::csv_header();
+        let csv = ai.analysis.to_csv(&ai.settings);
+        assert_eq!(header.split(',').count(), csv.split(',').count());
+        assert!(header.starts_with("node_count,nodes_created,errors,aborted,"));
+        assert!(csv.starts_with("14,14,0,false,"));
+        assert!(csv.contains(",0;2;4;8,"));
+    }
+}
@@ -0,0 +1,104 @@
+//! Accumulated rewards.
+//!
+//! A maximum tree optimizes the final reward,
+//! but it is common to optimize the sum of rewards along a path instead.
+//! In accumulation mode, node data is wrapped in `Accumulated`,
+//! which stores the sum of rewards so far, discounted by a factor `gamma` per action.
+//! The accumulated reward is used as utility.
+//!
+//! Use `AiBuilder::accumulate` to construct an AI setup in accumulation mode:
+//!
+//! ```
+//! use max_tree::prelude::*;
+//!
+//! let mut ai = Ai::builder()
+//!     .accumulate(
+//!         0.9,
+//!         |_, a, pos: &i32| if *pos + a == 3 {10.0} else {-1.0},
+//!         |_, _| vec![-1, 1],
+//!         |_, a, pos| {*pos += a; Ok(*a)},
+//!         |a, pos| *pos -= a,
+//!     )
+//!     .max_depth(3)
+//!     .build();
+//! let mut root = Node::root(Accumulated::root(0));
+//! let mut pos = 0;
+//! ai.full(&mut root, 0, &mut pos);
+//! assert!((root.max - (-1.0 - 0.9 + 10.0 * 0.81)).abs() < 1e-9);
+//! ```
+
+use crate::builder::AiBuilder;
+
+/// Node data with accumulated reward.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Accumulated<T> {
+    /// The node data.
+    pub data: T,
+    /// The sum of discounted rewards along the path from root.
+    pub reward: f64,
+    /// The discount of the next reward, which is `gamma` to the power of depth.
+    pub discount: f64,
+}
+
+impl<T> Accumulated<T> {
+    /// Creates root node data without rewards.
+    pub fn root(data: T) -> Accumulated<T> {
+        Accumulated {data, reward: 0.0, discount: 1.0}
+    }
+}
+
+impl<T: 'static, A: 'static, C: 'static> AiBuilder<Accumulated<T>, A, C> {
+    /// Sets utility, actions, execute and undo, accumulating rewards along the path.
+    ///
+    /// The reward is computed from node data, action and context before executing the action.
+    /// Rewards are discounted by `gamma`, which is between `0` and `1`:
+    /// The reward after `n` actions is multiplied by `gamma` to the power of `n`.
+    pub fn accumulate(
+        self,
+        gamma: f64,
+        reward: impl Fn(&T, &A, &C) -> f64 + Send + Sync + 'static,
+        actions: impl Fn(&T, &C) -> Vec<A> + Send + Sync + 'static,
+        execute: impl Fn(&T, &A, &mut C) -> Result<T, ()> + Send + Sync + 'static,
+        undo: impl Fn(&T, &mut C) + Send + Sync + 'static,
+    ) -> Self {
+        self.utility(|acc, _| acc.reward)
+            .actions(move |acc, ctx| actions(&acc.data, ctx))
+            .execute(move |acc, a, ctx| {
+                let r = reward(&acc.data, a, ctx);
+                let data = execute(&acc.data, a, ctx)?;
+                Ok(Accumulated {
+                    data,
+                    reward: acc.reward + acc.discount * r,
+                    discount: acc.discount * gamma,
+                })
+            })
+            .undo(move |acc, ctx| undo(&acc.data, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ai, Node};
+
+    #[test]
+    fn sum_of_rewards() {
+        let mut ai: Ai<Accumulated<i32>, i32, i32> = Ai::builder()
+            .accumulate(
+                1.0,
+                |_, a, pos| if *pos + a == 3 {10.0} else {-1.0},
+                |_, _| vec![-1, 1],
+                |_, a, pos| {*pos += a; Ok(*a)},
+                |a, pos| *pos -= a,
+            )
+            .max_depth(2)
+            .build();
+        let mut root = Node::root(Accumulated::root(0));
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max, 8.0);
+        assert_eq!(root.optimal_path().len(), 3);
+    }
+}
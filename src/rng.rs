@@ -0,0 +1,62 @@
+//! A small seedable pseudo-random number generator.
+//!
+//! Stochastic search algorithms take a `Rng` argument,
+//! such that results can be reproduced by using the same seed.
+
+/// Seedable pseudo-random number generator (xorshift64*).
+///
+/// This is not suitable for cryptographic purposes.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a new generator from seed.
+    ///
+    /// Since the generator can not have zero state,
+    /// a zero seed is replaced by a fixed constant.
+    pub fn new(seed: u64) -> Rng {
+        Rng {state: if seed == 0 {0x9E37_79B9_7F4A_7C15} else {seed}}
+    }
+
+    /// Generates next random `u64`.
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Generates a random number in the range `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Generates a random index in the range `[0, n)`.
+    ///
+    /// Panics if `n` is zero.
+    pub fn next_usize(&mut self, n: usize) -> usize {
+        assert!(n > 0, "Range must be non-empty");
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Generates a random index with probability proportional to weight.
+    ///
+    /// When all weights are zero, the index is picked uniformly.
+    /// Panics if there are no weights.
+    pub fn weighted(&mut self, weights: &[f64]) -> usize {
+        let sum: f64 = weights.iter().sum();
+        if sum.is_nan() || sum <= 0.0 {return self.next_usize(weights.len())};
+
+        let mut x = self.next_f64() * sum;
+        for (i, &w) in weights.iter().enumerate() {
+            if x < w {return i};
+            x -= w;
+        }
+        // Rounding errors might cause the last weight to be skipped.
+        weights.iter().rposition(|&w| w > 0.0).unwrap()
+    }
+}
@@ -0,0 +1,142 @@
+//! Rollouts and policy evaluation.
+//!
+//! A rollout simulates a policy from a state for a number of steps (the horizon),
+//! returning the utility of the final state.
+//! Nodes visited by rollouts are not stored in any tree,
+//! and changes to the context are undone afterwards.
+//!
+//! A policy picks the index of an action among the available actions.
+//! A random policy is a common baseline, e.g. to compare with search algorithms,
+//! or to estimate values of leaves.
+//! Use `Ai::evaluate_policy` to average over repeated rollouts.
+//! Since values are averaged, rollouts require `f64` as utility.
+
+use crate::Ai;
+use crate::execute_error::ExecuteError;
+use crate::mcts::confidence_interval;
+use crate::rng::Rng;
+
+/// Picks the index of an action, from node data, context and available actions.
+pub type Policy<T, A, C> = fn(&T, &C, &[A]) -> usize;
+
+/// Stores results of evaluating a policy by repeated rollouts.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Evaluation {
+    /// The mean utility of final states.
+    pub mean: f64,
+    /// The variance of utility of final states.
+    pub variance: f64,
+    /// The number of rollouts.
+    pub samples: usize,
+}
+
+impl Evaluation {
+    /// Returns a confidence interval of the mean utility,
+    /// using the number of standard errors `z`, e.g. `1.96` for 95%.
+    ///
+    /// Returns `None` if there are no samples.
+    pub fn confidence_interval(&self, z: f64) -> Option<(f64, f64)> {
+        confidence_interval(self.mean, self.variance, self.samples, z)
+    }
+}
+
+impl<T, A, C, E: ExecuteError> Ai<T, A, C, f64, E> {
+    /// Simulates a policy for a number of steps, returning the utility of the final state.
+    ///
+    /// Stops early when there are no available actions, the picked index is out of bounds,
+    /// or the picked action fails to execute.
+    /// The utility is computed with settings, at the depth of the final state.
+    pub fn rollout(&mut self, data: &T, ctx: &mut C, policy: Policy<T, A, C>, horizon: usize) -> f64 {
+        self.rollout_with(data, ctx, horizon, &mut |ai, data, ctx| {
+            let actions = (ai.actions)(data, ctx);
+            let a = actions.get(policy(data, ctx, &actions))?;
+            ai.try_execute(data, a, ctx)
+        })
+    }
+
+    /// Same as `Ai::rollout`, but picks random actions.
+    ///
+    /// When an action fails to execute, the others are tried.
+    pub fn random_rollout(&mut self, data: &T, ctx: &mut C, horizon: usize, rng: &mut Rng) -> f64 {
+        self.rollout_with(data, ctx, horizon, &mut |ai, data, ctx| {
+            ai.execute_random(data, ctx, rng).map(|(_, data)| data)
+        })
+    }
+
+    /// Evaluates a policy by repeated rollouts, using random actions when no policy is given.
+    pub fn evaluate_policy(
+        &mut self,
+        data: &T,
+        ctx: &mut C,
+        policy: Option<Policy<T, A, C>>,
+        horizon: usize,
+        rollouts: usize,
+        rng: &mut Rng,
+    ) -> Evaluation {
+        let (mut sum, mut sum_sq) = (0.0, 0.0);
+        for _ in 0..rollouts {
+            let u = match policy {
+                Some(policy) => self.rollout(data, ctx, policy, horizon),
+                None => self.random_rollout(data, ctx, horizon, rng),
+            };
+            sum += u;
+            sum_sq += u * u;
+        }
+        let n = rollouts as f64;
+        let mean = if rollouts == 0 {f64::NAN} else {sum / n};
+        let variance = if rollouts == 0 {0.0} else {(sum_sq / n - mean * mean).max(0.0)};
+        Evaluation {mean, variance, samples: rollouts}
+    }
+
+    fn rollout_with(
+        &mut self,
+        data: &T,
+        ctx: &mut C,
+        horizon: usize,
+        step: &mut dyn FnMut(&mut Self, &T, &mut C) -> Option<T>,
+    ) -> f64 {
+        let mut stack: Vec<T> = vec![];
+        while stack.len() < horizon {
+            let next = step(self, stack.last().unwrap_or(data), ctx);
+            match next {
+                Some(new_data) => stack.push(new_data),
+                None => break,
+            }
+        }
+        let value = self.utility_with_settings(stack.last().unwrap_or(data), stack.len(), ctx);
+
+        // Undo changes made to context to reset state.
+        while let Some(data) = stack.pop() {
+            self.revert(&data, ctx);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn evaluates_policies() {
+        let mut ai = line_ai(10);
+        ai.settings.eps_depth = 0.0;
+        let mut pos = 0;
+        // Always move right.
+        assert_eq!(ai.rollout(&0, &mut pos, |_, _, actions| actions.len() - 1, 3), 0.0);
+        assert_eq!(ai.rollout(&0, &mut pos, |_, _, _| 5, 3), -3.0);
+        assert_eq!(pos, 0);
+
+        let mut rng = Rng::new(0);
+        let right = ai.evaluate_policy(&0, &mut pos, Some(|_, _, actions| actions.len() - 1), 3, 10, &mut rng);
+        assert_eq!(right.mean, 0.0);
+        assert_eq!(right.variance, 0.0);
+        let random = ai.evaluate_policy(&0, &mut pos, None, 3, 100, &mut rng);
+        assert_eq!(pos, 0);
+        assert!(random.mean < right.mean);
+        let (lo, hi) = random.confidence_interval(1.96).unwrap();
+        assert!(lo < random.mean && random.mean < hi);
+    }
+}
@@ -0,0 +1,116 @@
+//! Verification of terminal semantics.
+//!
+//! A node is terminal when it has no children with equal or greater maximum utility,
+//! see `Node::terminal`.
+//! Since a terminal node is where the AI agent considers the goal to be achieved,
+//! one must verify that achieving the goal is meaningful and safe,
+//! e.g. that the world does not end and that the true goal is still reachable.
+//!
+//! The semantics of a terminal node is application specific,
+//! so it is decided by a user supplied predicate.
+//! `verify_terminals` checks every terminal node in a tree and reports violations with paths,
+//! such that unsafe plans can be detected before they are executed.
+
+use crate::Node;
+use crate::utility::Utility;
+
+/// The verdict of a terminal node.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TerminalVerdict {
+    /// The terminal semantics is verified to be safe.
+    Safe,
+    /// The terminal semantics is unsafe, with a reason.
+    Unsafe(String),
+}
+
+/// A terminal node that violates terminal semantics.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// The path of child indices from root.
+    pub path: Vec<usize>,
+    /// The reason given by the predicate.
+    pub reason: String,
+}
+
+/// Stores results of verifying terminal nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SafetyReport {
+    /// The number of terminal nodes checked.
+    pub checked: usize,
+    /// The terminal nodes that violate terminal semantics, in pre-order.
+    pub violations: Vec<Violation>,
+}
+
+impl SafetyReport {
+    /// Returns `true` if no terminal node violates terminal semantics.
+    pub fn is_safe(&self) -> bool {self.violations.is_empty()}
+
+    /// Returns `true` if the optimal path ends at a violating terminal node.
+    pub fn optimal_is_unsafe<T, A, U: Utility>(&self, root: &Node<T, A, U>) -> bool {
+        let path = root.optimal_path();
+        self.violations.iter().any(|v| v.path == path)
+    }
+}
+
+/// Verifies terminal semantics of every terminal node in a tree.
+pub fn verify_terminals<T, A, U: Utility>(
+    root: &Node<T, A, U>,
+    predicate: impl Fn(&T) -> TerminalVerdict,
+) -> SafetyReport {
+    let mut checked = 0;
+    let mut violations = vec![];
+    for (path, node) in root.iter_preorder().filter(|(_, node)| node.terminal()) {
+        checked += 1;
+        if let TerminalVerdict::Unsafe(reason) = predicate(&node.data) {
+            violations.push(Violation {path, reason});
+        }
+    }
+    SafetyReport {checked, violations}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Ai;
+
+    #[test]
+    fn reports_unsafe_terminals() {
+        let mut ai: Ai<i32, i32, ()> = Ai::builder()
+            .utility(|pos: &i32, _| -(*pos - 3).abs() as f64)
+            .actions(|_, _| vec![-1, 1])
+            .execute(|pos, a, _| Ok(pos + a))
+            .undo(|_, _| {})
+            .max_depth(3)
+            .build();
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut ());
+        let end = 3;
+        let report = verify_terminals(&root, |pos| {
+            if *pos == end {TerminalVerdict::Unsafe("the world ends".into())}
+            else {TerminalVerdict::Safe}
+        });
+        assert!(report.checked > report.violations.len());
+        assert!(!report.is_safe());
+        assert!(report.optimal_is_unsafe(&root));
+        for v in &report.violations {
+            assert_eq!(root.get(&v.path).unwrap().data, 3);
+            assert_eq!(v.reason, "the world ends");
+        }
+
+        // Utility types other than `f64` are supported.
+        let mut ai: Ai<i32, i32, (), i32> = Ai::builder()
+            .utility(|pos: &i32, _| -(*pos - 3).abs())
+            .actions(|_, _| vec![-1, 1])
+            .execute(|pos, a, _| Ok(pos + a))
+            .undo(|_, _| {})
+            .max_depth(3)
+            .build();
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut ());
+        let report = verify_terminals(&root, |pos| {
+            if *pos == end {TerminalVerdict::Unsafe("the world ends".into())}
+            else {TerminalVerdict::Safe}
+        });
+        assert!(report.optimal_is_unsafe(&root));
+    }
+}
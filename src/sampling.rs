@@ -0,0 +1,156 @@
+//! Monte Carlo estimation of expected utility.
+//!
+//! When the environment is a noisy simulator, executing the same action twice
+//! might give different results.
+//! In sampling mode, execute is called several times per action with a seeded `Rng`,
+//! and node data is wrapped in `Sampled`, which stores the mean and variance of utility.
+//! The mean is used as utility, such that e.g. `Ai::greedy` and `Ai::full`
+//! maximize the estimated expected utility.
+//!
+//! Samples are computed from node data without changing the context,
+//! since executing an action again, e.g. when descending into a child, gives another sample.
+//! Therefore, the state of the environment must be stored in node data.
+//! The last successful sample is kept as the node data.
+//! Samples that fail to execute are ignored,
+//! and the action fails when all samples fail.
+//!
+//! Use `AiBuilder::sampled` to construct an AI setup in sampling mode:
+//!
+//! ```
+//! use max_tree::prelude::*;
+//! use max_tree::sampling::Sampled;
+//!
+//! let mut ai = Ai::builder()
+//!     .sampled(
+//!         16, 0,
+//!         |pos: &i32, _: &()| -(*pos - 3).abs() as f64,
+//!         |_, _| vec![-1, 1],
+//!         // The action is sometimes doubled by noise.
+//!         |pos, a, _, rng| Ok(if rng.next_f64() < 0.5 {pos + a} else {pos + 2 * a}),
+//!     )
+//!     .max_depth(1)
+//!     .build();
+//! let mut root = Node::root(Sampled::root(0));
+//! ai.full(&mut root, 0, &mut ());
+//! assert_eq!(root.children[root.optimal().unwrap()].0, 1);
+//! ```
+
+use std::sync::{Arc, Mutex};
+
+use crate::Node;
+use crate::builder::AiBuilder;
+use crate::mcts::confidence_interval;
+use crate::rng::Rng;
+
+/// Node data with estimated utility.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sampled<T> {
+    /// The node data of the last successful sample.
+    pub data: T,
+    /// The mean utility of samples.
+    pub mean: f64,
+    /// The variance of utility of samples.
+    pub variance: f64,
+    /// The number of successful samples, zero for root.
+    pub samples: usize,
+}
+
+impl<T> Sampled<T> {
+    /// Creates root node data without samples.
+    ///
+    /// The utility of root is computed directly from node data.
+    pub fn root(data: T) -> Sampled<T> {
+        Sampled {data, mean: f64::NAN, variance: 0.0, samples: 0}
+    }
+
+    /// Returns a confidence interval of the mean utility,
+    /// using the number of standard errors `z`, e.g. `1.96` for 95%.
+    ///
+    /// Returns `None` if there are no samples.
+    pub fn confidence_interval(&self, z: f64) -> Option<(f64, f64)> {
+        confidence_interval(self.mean, self.variance, self.samples, z)
+    }
+}
+
+impl<T, A> Node<Sampled<T>, A> {
+    /// Returns a confidence interval of the estimated utility of node, see `Sampled`.
+    ///
+    /// Two children whose intervals overlap might not be genuinely different.
+    /// Returns `None` for root, which is not sampled.
+    pub fn confidence_interval(&self, z: f64) -> Option<(f64, f64)> {
+        self.data.confidence_interval(z)
+    }
+}
+
+impl<T: 'static, A: 'static, C: 'static> AiBuilder<Sampled<T>, A, C> {
+    /// Sets utility, actions, execute and undo, sampling execute several times per action.
+    ///
+    /// Execute takes a random number generator, seeded once with `seed`,
+    /// such that the search can be reproduced.
+    pub fn sampled(
+        self,
+        samples: usize,
+        seed: u64,
+        utility: impl Fn(&T, &C) -> f64 + Send + Sync + 'static,
+        actions: impl Fn(&T, &C) -> Vec<A> + Send + Sync + 'static,
+        execute: impl Fn(&T, &A, &C, &mut Rng) -> Result<T, ()> + Send + Sync + 'static,
+    ) -> Self {
+        let utility = Arc::new(utility);
+        let rng = Mutex::new(Rng::new(seed));
+        let sample_utility = utility.clone();
+        self.utility(move |s, ctx| if s.samples == 0 {utility(&s.data, ctx)} else {s.mean})
+            .actions(move |s, ctx| actions(&s.data, ctx))
+            .execute(move |s, a, ctx| {
+                let mut rng = rng.lock().unwrap();
+                let mut last: Option<T> = None;
+                let (mut n, mut sum, mut sum_sq) = (0, 0.0, 0.0);
+                for _ in 0..samples {
+                    if let Ok(data) = execute(&s.data, a, ctx, &mut rng) {
+                        let u = sample_utility(&data, ctx);
+                        n += 1;
+                        sum += u;
+                        sum_sq += u * u;
+                        last = Some(data);
+                    }
+                }
+                let data = last.ok_or(())?;
+                let mean = sum / n as f64;
+                let variance = (sum_sq / n as f64 - mean * mean).max(0.0);
+                Ok(Sampled {data, mean, variance, samples: n})
+            })
+            .undo(|_, _| {})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ai, Node};
+
+    #[test]
+    fn mean_and_variance() {
+        let mut ai: Ai<Sampled<i32>, i32, ()> = Ai::builder()
+            .sampled(
+                100, 1,
+                |pos: &i32, _| *pos as f64,
+                |_, _| vec![1],
+                |pos, a, _, rng| {
+                    if rng.next_f64() < 0.2 {return Err(())};
+                    Ok(if rng.next_f64() < 0.5 {pos + a} else {pos + 3 * a})
+                },
+            )
+            .max_depth(0)
+            .build();
+        let mut root = Node::root(Sampled::root(0));
+        ai.full(&mut root, 0, &mut ());
+        let ch = &root.children[0].1.data;
+        assert!(ch.samples > 50 && ch.samples < 100);
+        assert!((ch.mean - 2.0).abs() < 0.5);
+        assert!((ch.variance - 1.0).abs() < 0.5);
+        assert_eq!(root.max, ch.mean.max(0.0));
+        let (lo, hi) = root.children[0].1.confidence_interval(1.96).unwrap();
+        assert!(lo < ch.mean && ch.mean < hi && hi - lo < 1.0);
+        assert_eq!(root.confidence_interval(1.96), None);
+    }
+}
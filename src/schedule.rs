@@ -0,0 +1,87 @@
+//! Depth-dependent settings.
+//!
+//! Some settings can be overridden per depth by `AiSettings::schedule`,
+//! e.g. to explore widely near root and narrow aggressively deep in the tree.
+
+use crate::Ai;
+
+/// A function returning settings for a depth.
+pub type Schedule = Box<dyn Fn(usize) -> DepthSettings + Send + Sync>;
+
+/// Settings overridden at a depth.
+///
+/// Settings that are `None` use the value of `AiSettings`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DepthSettings {
+    /// Overrides `AiSettings::max_children` for children of nodes at this depth.
+    pub max_children: Option<usize>,
+    /// Overrides `AiSettings::eps_depth` for nodes at this depth.
+    ///
+    /// The discount of a node is summed over the nodes on the path from root.
+    pub eps_depth: Option<f64>,
+    /// Overrides the width of `Ai::beam` for nodes selected at this depth.
+    pub width: Option<usize>,
+}
+
+impl<T, A, C, U, E> Ai<T, A, C, U, E> {
+    /// Returns the settings overridden at depth.
+    pub fn depth_settings(&self, depth: usize) -> DepthSettings {
+        self.settings.schedule.as_ref().map(|f| f(depth)).unwrap_or_default()
+    }
+
+    /// Returns the maximum number of children of nodes at depth.
+    pub(crate) fn max_children_at(&self, depth: usize) -> Option<usize> {
+        self.depth_settings(depth).max_children.or(self.settings.max_children)
+    }
+
+    /// Returns the utility discount from action depth at depth.
+    pub(crate) fn eps_depth_at(&self, depth: usize) -> f64 {
+        self.depth_settings(depth).eps_depth.unwrap_or(self.settings.eps_depth)
+    }
+
+    /// Returns the total utility discount of nodes deeper than `from`, up to and including `to`.
+    ///
+    /// The discount of each node on the path uses the settings at its depth.
+    pub(crate) fn eps_depth_sum(&self, from: usize, to: usize) -> f64 {
+        if self.settings.schedule.is_none() {
+            return self.settings.eps_depth * to.saturating_sub(from) as f64;
+        }
+        (from + 1..=to).map(|d| self.eps_depth_at(d)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn narrow_deep_in_tree() {
+        let mut ai = line_ai(3);
+        ai.settings.schedule = Some(Box::new(|depth| DepthSettings {
+            max_children: if depth == 0 {None} else {Some(1)},
+            ..Default::default()
+        }));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 2);
+        assert!(root.children.iter().all(|ch| ch.1.children.len() == 1));
+        assert!(root.max > -0.001);
+    }
+
+    #[test]
+    fn eps_depth_summed_along_path() {
+        let mut ai = line_ai(3);
+        ai.settings.eps_depth = 0.5;
+        ai.settings.schedule = Some(Box::new(|depth| DepthSettings {
+            eps_depth: if depth >= 2 {Some(0.25)} else {None},
+            ..Default::default()
+        }));
+        assert_eq!(ai.eps_depth_sum(0, 3), 1.0);
+        assert_eq!(ai.eps_depth_sum(1, 3), 0.5);
+        assert_eq!(ai.utility_with_settings(&0, 3, &3), -1.0);
+    }
+}
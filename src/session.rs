@@ -0,0 +1,209 @@
+//! Interactive stepping of searches.
+//!
+//! The search algorithms run until they finish, which blocks e.g. a GUI or a notebook.
+//! A `SearchSession` runs a search a few expansions at a time,
+//! such that the partial tree can be inspected in between.
+//!
+//! Nodes are expanded in breadth-first order to maximum depth,
+//! which constructs the same tree as `Ai::full` without pruning when the session finishes.
+//! Between steps, the context is in the state of root,
+//! and maximum utility is propagated to root.
+//! Each expansion replays actions from root, which costs extra executes compared to `full`.
+//!
+//! The session counts as one search, so analysis and limits span all steps.
+//! Time is only measured inside steps, so `AiSettings::max_time` excludes time between steps.
+//!
+//! Since nodes are not expanded in depth-first order,
+//! the session does not use `Ai::bound`, `AiSettings::transpositions` or `AiSettings::avoid_cycles`.
+//! `AiSettings::greed_elim` only applies to greedy searches, so it is not used either.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::outcome::SearchOutcome;
+use crate::utility::Utility;
+
+/// The status of a search session after a step.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StepStatus {
+    /// The search is paused and can be continued by another step.
+    Paused {
+        /// The number of nodes created in the step.
+        nodes_created: usize,
+    },
+    /// The search finished or stopped early, see `SearchOutcome`.
+    Finished(SearchOutcome),
+}
+
+impl StepStatus {
+    /// Returns `true` if the search session is finished.
+    pub fn is_finished(&self) -> bool {matches!(self, StepStatus::Finished(_))}
+}
+
+/// The AI setup and the tree of a search session.
+type Parts<T, A, C, U, E> = (Ai<T, A, C, U, E>, Node<T, A, U>);
+
+/// A search that runs a number of expansions at a time.
+pub struct SearchSession<T, A, C, U = f64, E = ()> {
+    ai: Ai<T, A, C, U, E>,
+    root: Node<T, A, U>,
+    /// The paths and depths of nodes to expand.
+    frontier: VecDeque<(Vec<usize>, usize)>,
+    /// Whether the timer was started by the session, or `None` before the first step.
+    started: Option<bool>,
+    /// The time spent in steps so far.
+    elapsed: Duration,
+    outcome: Option<SearchOutcome>,
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> SearchSession<T, A, C, U, E> {
+    /// Starts a new session searching from node data of root.
+    pub fn new(ai: Ai<T, A, C, U, E>, data: T) -> SearchSession<T, A, C, U, E> {
+        SearchSession {
+            ai,
+            root: Node::root(data),
+            frontier: vec![(vec![], 0)].into(),
+            started: None,
+            elapsed: Duration::from_secs(0),
+            outcome: None,
+        }
+    }
+
+    /// Expands nodes until at least `budget_nodes` nodes are created, or the search finishes.
+    ///
+    /// The context must be in the state of root.
+    pub fn step(&mut self, ctx: &mut C, budget_nodes: usize) -> StepStatus
+        where A: Clone
+    {
+        if let Some(outcome) = &self.outcome {return StepStatus::Finished(outcome.clone())};
+
+        // Continue measuring time from where the last step paused.
+        let started = match self.started {
+            None => self.ai.start_timer(),
+            Some(started) => {
+                if started {
+                    self.ai.analysis.search_start = Some(Instant::now().checked_sub(self.elapsed)
+                        .unwrap_or_else(Instant::now));
+                }
+                started
+            }
+        };
+        self.started = Some(started);
+
+        let ai = &mut self.ai;
+        let root = &mut self.root;
+        if root.max.is_unknown() {
+            root.max = ai.utility_with_settings(&root.data, 0, ctx);
+        }
+        let start = ai.analysis.nodes_created;
+        while ai.analysis.nodes_created - start < budget_nodes {
+            if ai.limit_exceeded() {break};
+            let (path, depth) = match self.frontier.pop_front() {
+                Some(x) => x,
+                None => break,
+            };
+
+            let n = ai.replay(root, &path, ctx);
+            if n == path.len() {
+                let node = root.node_mut(&path);
+                ai.sub_breadth(node, depth, ctx);
+                if depth < ai.settings.max_depth {
+                    for i in 0..node.children.len() {
+                        let mut ch_path = path.clone();
+                        ch_path.push(i);
+                        self.frontier.push_back((ch_path, depth + 1));
+                    }
+                }
+
+                // Update maximum utility of ancestors since children are changed.
+                for k in (0..path.len()).rev() {
+                    let max = root.node_mut(&path[..=k]).max;
+                    let parent = root.node_mut(&path[..k]);
+                    if max > parent.max {parent.max = max} else {break};
+                }
+            }
+
+            // Undo changes made to context to reset state.
+            ai.rollback(root, &path[..n], ctx);
+        }
+
+        if self.frontier.is_empty() || ai.limit_exceeded() {
+            let outcome = ai.finish(started);
+            self.outcome = Some(outcome.clone());
+            StepStatus::Finished(outcome)
+        } else {
+            if started {
+                if let Some(start) = ai.analysis.search_start.take() {self.elapsed = start.elapsed()};
+            }
+            StepStatus::Paused {nodes_created: ai.analysis.nodes_created - start}
+        }
+    }
+
+    /// Runs steps until the search finishes.
+    pub fn run(&mut self, ctx: &mut C) -> SearchOutcome
+        where A: Clone
+    {
+        loop {
+            if let StepStatus::Finished(outcome) = self.step(ctx, usize::MAX) {return outcome};
+        }
+    }
+
+    /// Returns the partial tree.
+    pub fn root(&self) -> &Node<T, A, U> {&self.root}
+
+    /// Returns the AI setup, e.g. to inspect analysis.
+    pub fn ai(&self) -> &Ai<T, A, C, U, E> {&self.ai}
+
+    /// Returns the AI setup and the tree.
+    ///
+    /// If the session is not finished, the search time is the time spent in steps.
+    pub fn into_inner(mut self) -> Parts<T, A, C, U, E> {
+        if self.outcome.is_none() && self.started == Some(true) {
+            self.ai.analysis.search_time = self.elapsed;
+        }
+        (self.ai, self.root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outcome::StopReason;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn steps_search() {
+        let mut session = SearchSession::new(line_ai(2), 0);
+        let mut pos = 0;
+        assert_eq!(session.step(&mut pos, 4), StepStatus::Paused {nodes_created: 4});
+        assert_eq!(pos, 0);
+        assert_eq!(session.root().children.len(), 2);
+        assert_eq!(session.root().max, session.root().children[1].1.max);
+        let outcome = session.run(&mut pos);
+        assert_eq!(outcome.stop, StopReason::DepthLimit);
+        assert_eq!(outcome.nodes_created, 14);
+        assert!(session.step(&mut pos, 4).is_finished());
+
+        let mut ai = line_ai(2);
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        let (_, tree) = session.into_inner();
+        assert_eq!(tree.max, root.max);
+        assert_eq!(tree.optimal_path(), root.optimal_path());
+    }
+
+    #[test]
+    fn excludes_time_between_steps() {
+        let mut ai = line_ai(2);
+        ai.settings.max_time = Some(Duration::from_millis(50));
+        let mut session = SearchSession::new(ai, 0);
+        let mut pos = 0;
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!session.step(&mut pos, 4).is_finished());
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(session.run(&mut pos).stop, StopReason::DepthLimit);
+    }
+}
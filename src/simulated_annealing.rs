@@ -0,0 +1,167 @@
+//! Simulated annealing over action sequences.
+//!
+//! Instead of constructing a tree by expansion,
+//! a single candidate sequence of actions is mutated and scored.
+//! Worse candidates are accepted with a probability depending on temperature,
+//! which decreases over time according to a schedule.
+//!
+//! The score of a sequence is the maximum utility along the path,
+//! which is the same semantics as for a maximum tree.
+//! The best sequence found is recorded as a path in the maximum tree,
+//! such that it can be analysed or composed with other search algorithms.
+//!
+//! The probability of accepting a worse candidate depends on the difference in score,
+//! so simulated annealing requires `f64` as utility.
+
+use crate::{Ai, Node};
+use crate::execute_error::ExecuteError;
+use crate::rng::Rng;
+
+impl<T, A, C, E: ExecuteError> Ai<T, A, C, f64, E> {
+    /// Searches for the best sequence of actions using simulated annealing.
+    ///
+    /// The `schedule` maps iteration to temperature, e.g. `|i| 0.99f64.powi(i as i32)`.
+    /// The length of sequences is limited by `AiSettings::max_depth`.
+    /// Each iteration replaces a random action in the sequence,
+    /// and when a sequence is shorter than maximum depth,
+    /// it is extended by random actions.
+    ///
+    /// The best sequence found is inserted as a path from root.
+    /// When a limit of memory or time is exceeded, the search terminates early.
+    pub fn simulated_annealing(
+        &mut self,
+        root: &mut Node<T, A>,
+        depth: usize,
+        ctx: &mut C,
+        iterations: usize,
+        schedule: fn(usize) -> f64,
+        rng: &mut Rng,
+    )
+        where A: Clone + PartialEq
+    {
+        if root.max.is_nan() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let started = self.start_timer();
+        let (mut score, mut seq) = self.anneal_evaluate(vec![], &root.data, depth, ctx, rng);
+        let mut best = (score, seq.clone());
+        for i in 0..iterations {
+            if seq.is_empty() {break};
+            if self.limit_exceeded() {break};
+
+            let candidate = self.anneal_mutate(&seq, &root.data, ctx, rng);
+            let (new_score, candidate) = self.anneal_evaluate(candidate, &root.data, depth, ctx, rng);
+            let temperature = schedule(i);
+            let accept = new_score >= score ||
+                rng.next_f64() < ((new_score - score) / temperature).exp();
+            if accept {
+                score = new_score;
+                seq = candidate;
+                if score > best.0 {
+                    best = (score, seq.clone());
+                }
+            }
+        }
+
+        self.insert_actions(root, depth, &best.1, ctx);
+        self.stop_timer(started);
+    }
+
+    /// Replaces a random action in the sequence with a random available action.
+    fn anneal_mutate(&mut self, seq: &[A], data: &T, ctx: &mut C, rng: &mut Rng) -> Vec<A>
+        where A: Clone
+    {
+        let k = rng.next_usize(seq.len());
+        let mut res: Vec<A> = vec![];
+        let mut stack: Vec<T> = vec![];
+        for a in &seq[..k] {
+            match self.try_execute(stack.last().unwrap_or(data), a, ctx) {
+                Some(new_data) => stack.push(new_data),
+                None => break,
+            }
+        }
+        if stack.len() == k {
+            res.extend(seq[..k].iter().cloned());
+            if let Some((a, new_data)) = self.execute_random(stack.last().unwrap_or(data), ctx, rng) {
+                self.revert(&new_data, ctx);
+                res.push(a);
+                res.extend(seq[k + 1..].iter().cloned());
+            }
+        }
+
+        // Undo changes made to context to reset state.
+        while let Some(data) = stack.pop() {
+            self.revert(&data, ctx);
+        }
+        res
+    }
+
+    /// Scores a sequence by maximum utility along the path.
+    ///
+    /// The sequence is truncated at the first action that fails to execute,
+    /// and then extended with random actions until maximum depth.
+    /// Returns the score and the new sequence.
+    fn anneal_evaluate(
+        &mut self,
+        mut seq: Vec<A>,
+        data: &T,
+        depth: usize,
+        ctx: &mut C,
+        rng: &mut Rng,
+    ) -> (f64, Vec<A>) {
+        let horizon = self.settings.max_depth.saturating_sub(depth);
+        let mut score = f64::NEG_INFINITY;
+        let mut stack: Vec<T> = vec![];
+        while stack.len() < horizon {
+            let k = stack.len();
+            let cur = stack.last().unwrap_or(data);
+            let next = if k < seq.len() {
+                match self.try_execute(cur, &seq[k], ctx) {
+                    Some(new_data) => Some(new_data),
+                    None => {
+                        seq.truncate(k);
+                        None
+                    }
+                }
+            } else {None};
+            let next = match next {
+                Some(new_data) => new_data,
+                None => match self.execute_random(cur, ctx, rng) {
+                    Some((a, new_data)) => {
+                        seq.push(a);
+                        new_data
+                    }
+                    None => break,
+                }
+            };
+            let utility = self.utility_with_settings(&next, depth + k + 1, ctx);
+            if utility > score {score = utility};
+            stack.push(next);
+        }
+        seq.truncate(stack.len());
+
+        // Undo changes made to context to reset state.
+        while let Some(data) = stack.pop() {
+            self.revert(&data, ctx);
+        }
+        (score, seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn records_best_sequence() {
+        let mut ai = line_ai(6);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.simulated_annealing(&mut root, 0, &mut pos, 500, |i| 0.99f64.powi(i as i32), &mut Rng::new(1));
+        assert_eq!(pos, 0);
+        assert!(root.max > -0.001);
+        assert!(root.optimal_path().len() >= 3);
+    }
+}
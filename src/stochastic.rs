@@ -0,0 +1,135 @@
+//! Stochastic transitions with weighted outcomes.
+//!
+//! In real environments, executing an action might lead to several outcomes,
+//! e.g. because of sensor noise or wind.
+//! In stochastic mode, execute returns a list of outcomes with probabilities,
+//! which are represented in the tree as chance nodes for `Ai::expectimax`.
+//! The maximum expected utility is stored in `Node::max`.
+//!
+//! Node data is wrapped in `Transition` and actions in `Step`:
+//! Executing `Step::Act` in a state creates a chance node with all outcomes,
+//! and executing `Step::Outcome` in a chance node picks one outcome.
+//! Since each action takes two levels in the tree,
+//! `AiSettings::max_depth` should be twice the number of actions to look ahead.
+//!
+//! Outcomes are computed from node data without changing the context,
+//! so the state of the environment must be stored in node data.
+//!
+//! Use `AiBuilder::stochastic` to construct an AI setup in stochastic mode:
+//!
+//! ```
+//! use max_tree::prelude::*;
+//! use max_tree::stochastic::{Step, Transition};
+//!
+//! let mut ai = Ai::builder()
+//!     .stochastic(
+//!         |pos: &i32, _: &()| -(*pos - 3).abs() as f64,
+//!         |_, _| vec![-1, 1],
+//!         // The wind blows the agent back with some probability.
+//!         |pos, a, _| vec![(0.8, pos + a), (0.2, *pos)],
+//!     )
+//!     .max_depth(1)
+//!     .build();
+//! let mut root = Node::root(Transition::State(0));
+//! ai.expectimax(&mut root, 0, &mut ());
+//! assert!((root.max - (0.8 * -2.0 + 0.2 * -3.0)).abs() < 1e-9);
+//! assert_eq!(root.children[root.optimal().unwrap()].0, Step::Act(1));
+//! ```
+
+use crate::builder::AiBuilder;
+use crate::expectimax::NodeKind;
+
+/// Node data in stochastic mode.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Transition<T> {
+    /// The AI agent chooses an action in a state.
+    State(T),
+    /// The environment chooses an outcome with probability.
+    Chance(Vec<(f64, T)>),
+}
+
+/// Actions in stochastic mode.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Step<A> {
+    /// An action chosen by the AI agent.
+    Act(A),
+    /// The index of outcome chosen by the environment.
+    Outcome(usize),
+}
+
+/// Decides the kind of node in stochastic mode, used as `Ai::node_kind`.
+pub fn transition_kind<T, A, C>(data: &Transition<T>, _: &C) -> NodeKind<Step<A>> {
+    match data {
+        Transition::State(_) => NodeKind::Decision,
+        Transition::Chance(outcomes) => NodeKind::Chance(
+            outcomes.iter().enumerate().map(|(i, (p, _))| (*p, Step::Outcome(i))).collect()
+        ),
+    }
+}
+
+impl<T: Clone + 'static, A: 'static, C: 'static> AiBuilder<Transition<T>, Step<A>, C> {
+    /// Sets utility, actions, execute, undo and node kind for stochastic transitions.
+    ///
+    /// Execute returns the outcomes of an action with probabilities.
+    /// An action without outcomes fails to execute.
+    /// The utility of a chance node is the expected utility of its outcomes.
+    pub fn stochastic(
+        self,
+        utility: impl Fn(&T, &C) -> f64 + Send + Sync + 'static,
+        actions: impl Fn(&T, &C) -> Vec<A> + Send + Sync + 'static,
+        execute: impl Fn(&T, &A, &C) -> Vec<(f64, T)> + Send + Sync + 'static,
+    ) -> Self {
+        self.utility(move |data, ctx| match data {
+                Transition::State(data) => utility(data, ctx),
+                Transition::Chance(outcomes) => {
+                    let total: f64 = outcomes.iter().map(|(p, _)| p).sum();
+                    outcomes.iter().map(|(p, data)| p * utility(data, ctx)).sum::<f64>() / total
+                }
+            })
+            .actions(move |data, ctx| match data {
+                Transition::State(data) => actions(data, ctx).into_iter().map(Step::Act).collect(),
+                // Outcomes are not chosen by the AI agent.
+                Transition::Chance(_) => vec![],
+            })
+            .execute(move |data, step, ctx| match (data, step) {
+                (Transition::State(data), Step::Act(a)) => {
+                    let outcomes = execute(data, a, ctx);
+                    if outcomes.is_empty() {Err(())} else {Ok(Transition::Chance(outcomes))}
+                }
+                (Transition::Chance(outcomes), Step::Outcome(i)) => {
+                    outcomes.get(*i).map(|(_, data)| Transition::State(data.clone())).ok_or(())
+                }
+                _ => Err(()),
+            })
+            .undo(|_, _| {})
+            .node_kind(transition_kind::<T, A, C>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ai, Node};
+
+    #[test]
+    fn expected_outcomes() {
+        let mut ai: Ai<Transition<i32>, Step<i32>, ()> = Ai::builder()
+            .stochastic(
+                |pos: &i32, _| -(*pos - 3).abs() as f64,
+                |_, _| vec![-1, 1],
+                |pos, a, _| if *a < 0 {vec![]} else {vec![(0.5, pos + 2), (0.5, *pos)]},
+            )
+            .max_depth(3)
+            .build();
+        let mut root = Node::root(Transition::State(0));
+        ai.expectimax(&mut root, 0, &mut ());
+        assert_eq!(root.children.len(), 1);
+        let chance = &root.children[0].1;
+        assert_eq!(chance.children.len(), 2);
+        assert_eq!(chance.children[0].1.data, Transition::State(2));
+        let expected = 0.5 * chance.children[0].1.max + 0.5 * chance.children[1].1.max;
+        assert!((chance.max - expected).abs() < 1e-9);
+    }
+}
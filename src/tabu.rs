@@ -0,0 +1,137 @@
+//! Tabu search.
+//!
+//! Tabu search is a local search that always moves to the best neighbor,
+//! even when this decreases utility.
+//! To avoid going back and forth between the same states,
+//! recently visited states are tabu (forbidden) for a number of steps.
+//!
+//! States are identified by `Ai::state_hash`.
+//! The trajectory is recorded in the maximum tree,
+//! where each node on the trajectory is expanded with all available actions.
+
+use std::collections::VecDeque;
+
+use crate::{Ai, Collect, Node};
+use crate::depth_first::NodeStorage;
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Constructs a trajectory using tabu search.
+    ///
+    /// The `tenure` is the number of recently visited states that are tabu.
+    /// Requires `Ai::state_hash`, otherwise no states are tabu.
+    /// The trajectory ends at maximum depth,
+    /// when a limit of memory or time is exceeded,
+    /// or when all children are tabu.
+    pub fn tabu(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C, tenure: usize)
+        where A: Clone
+    {
+        let started = self.start_timer();
+        let mut tabu = VecDeque::with_capacity(tenure + 1);
+        if let Some(state_hash) = &self.state_hash {
+            tabu.push_back(state_hash(&root.data, ctx));
+        }
+        self.tabu_step(root, depth, ctx, &mut tabu, tenure);
+        self.stop_timer(started);
+    }
+
+    fn tabu_step(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        tabu: &mut VecDeque<u64>,
+        tenure: usize,
+    )
+        where A: Clone
+    {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let mut hashes = Collect::new(|ai: &Self, data: &T, ctx: &C| ai.state_hash.as_ref().map(|f| f(data, ctx)));
+        self.sub_breadth_with(&mut NodeStorage::new(root), depth, ctx, &mut hashes);
+        let hashes = hashes.values;
+
+        if depth >= self.settings.max_depth {return};
+        if self.limit_exceeded() {return};
+
+        let mut best: Option<usize> = None;
+        for (i, ch) in root.children.iter().enumerate() {
+            if let Some(hash) = hashes[i] {
+                if tabu.contains(&hash) {continue};
+            }
+            if best.map(|j| ch.1.max > root.children[j].1.max).unwrap_or(true) {
+                best = Some(i);
+            }
+        }
+
+        if let Some(i) = best {
+            if let Some(hash) = hashes[i] {
+                tabu.push_back(hash);
+                if tabu.len() > tenure {tabu.pop_front();}
+            }
+
+            let a = &root.children[i].0;
+            if self.try_execute(&root.data, a, ctx).is_some() {
+                let ch = &mut root.children[i].1;
+                self.tabu_step(ch, depth + 1, ctx, tabu, tenure);
+
+                // Undo changes made to context to reset state.
+                self.revert(&ch.data, ctx);
+
+                // Update maximum utility since children are changed.
+                if ch.max > root.max {
+                    root.max = ch.max;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn leaves_local_maximum() {
+        let mut ai = line_ai(8);
+        ai.state_hash = Some(Box::new(|_, pos| *pos as u64));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.tabu(&mut root, 0, &mut pos, 8);
+        assert_eq!(pos, 0);
+
+        // Without tabu, the trajectory would oscillate around position 3.
+        let mut node = &root;
+        let mut p = 0;
+        while let Some(i) = node.children.iter().position(|ch| !ch.1.children.is_empty()) {
+            p += node.children[i].0;
+            node = &node.children[i].1;
+        }
+        assert_eq!(p, 5);
+        assert!(root.max > -0.001);
+    }
+
+    #[test]
+    fn limits_children() {
+        let mut ai = line_ai(8);
+        ai.state_hash = Some(Box::new(|_, pos| *pos as u64));
+        ai.settings.max_children = Some(1);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.tabu(&mut root, 0, &mut pos, 3);
+        assert_eq!(pos, 0);
+        let mut node = &root;
+        let mut p = 0;
+        while let Some((a, ch)) = node.children.first() {
+            assert_eq!(node.children.len(), 1);
+            p += a;
+            node = ch;
+        }
+        // The trajectory ends at position 3, where the only child is tabu.
+        assert_eq!(p, 2);
+    }
+}
@@ -0,0 +1,109 @@
+//! Tie-breaking among children with equal utility.
+//!
+//! `Node::optimal` returns the first child with maximum utility,
+//! which makes the choice depend on the order of actions returned by `Ai::actions`.
+//! A tie-breaking policy makes the choice among equal children explicit.
+
+use std::cmp::Ordering;
+
+use crate::Node;
+use crate::rng::Rng;
+
+/// Decides which child to pick among children with equal utility.
+#[derive(Clone, Copy, Debug)]
+pub enum TieBreak<T, A> {
+    /// Picks the first child, same as `Node::optimal`.
+    First,
+    /// Picks the last child.
+    Last,
+    /// Picks a random child using a seed.
+    Random(u64),
+    /// Picks the greatest child by comparing action and node data.
+    ///
+    /// When several children are greatest, the first one is picked.
+    Custom(fn(&A, &T, &A, &T) -> Ordering),
+}
+
+impl<T, A, U: PartialOrd> Node<T, A, U> {
+    /// Returns every optimal course of action, in the order of children.
+    ///
+    /// When there are several, the plan is not unique,
+    /// and the choice is made by tie-breaking, see `Node::optimal_with`.
+    /// Returns an empty list if the node is terminal.
+    pub fn all_optimal(&self) -> Vec<usize> {
+        self.children.iter().enumerate()
+            .filter(|(_, ch)| ch.1.max >= self.max)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Same as `Node::optimal`, but uses a tie-breaking policy.
+    pub fn optimal_with(&self, tie_break: &TieBreak<T, A>) -> Option<usize> {
+        let mut rng = tie_break_rng(tie_break);
+        self.optimal_tie_break(tie_break, &mut rng)
+    }
+
+    /// Same as `Node::optimal_path`, but uses a tie-breaking policy.
+    pub fn optimal_path_with(&self, tie_break: &TieBreak<T, A>) -> Vec<usize> {
+        let mut rng = tie_break_rng(tie_break);
+        let mut node = self;
+        let mut res = vec![];
+        while let Some(i) = node.optimal_tie_break(tie_break, &mut rng) {
+            node = &node.children[i].1;
+            res.push(i);
+        }
+        res
+    }
+
+    fn optimal_tie_break(&self, tie_break: &TieBreak<T, A>, rng: &mut Rng) -> Option<usize> {
+        let ties = self.all_optimal();
+        if ties.is_empty() {return None};
+
+        Some(match *tie_break {
+            TieBreak::First => ties[0],
+            TieBreak::Last => ties[ties.len() - 1],
+            TieBreak::Random(_) => ties[rng.next_usize(ties.len())],
+            TieBreak::Custom(cmp) => {
+                let mut best = ties[0];
+                for &i in &ties[1..] {
+                    let (a, ch) = &self.children[i];
+                    let (b, best_ch) = &self.children[best];
+                    if cmp(a, &ch.data, b, &best_ch.data) == Ordering::Greater {
+                        best = i;
+                    }
+                }
+                best
+            }
+        })
+    }
+}
+
+fn tie_break_rng<T, A>(tie_break: &TieBreak<T, A>) -> Rng {
+    match *tie_break {
+        TieBreak::Random(seed) => Rng::new(seed),
+        _ => Rng::new(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policies() {
+        let mut root: Node<i32, i32> = Node::root(0);
+        root.max = 1.0;
+        for a in 0..4 {
+            let max = if a == 1 {0.0} else {1.0};
+            root.children.push((a, Node {max, data: 10 - a, children: vec![], partial: false}));
+        }
+        assert_eq!(root.all_optimal(), vec![0, 2, 3]);
+        assert_eq!(root.optimal_with(&TieBreak::First), root.optimal());
+        assert_eq!(root.optimal_with(&TieBreak::Last), Some(3));
+        assert_eq!(root.optimal_with(&TieBreak::Custom(|_, x, _, y| x.cmp(y))), Some(0));
+        assert_eq!(root.optimal_path_with(&TieBreak::Custom(|a, _, b, _| a.cmp(b))), vec![3]);
+        let i = root.optimal_with(&TieBreak::Random(7)).unwrap();
+        assert_ne!(i, 1);
+        assert_eq!(root.optimal_with(&TieBreak::Random(7)), Some(i));
+    }
+}
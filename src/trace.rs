@@ -0,0 +1,179 @@
+//! Recording of search traces, for debugging and reproducible bug reports.
+//!
+//! When `Ai::trace` is set, every action executed by a search is recorded as an `Event`,
+//! with the path of actions leading to the node and the time since tracing started.
+//! Paths are relative to the state of the context when tracing started,
+//! tracked by pairing executed actions with undoing changes.
+//!
+//! An event that creates a new node stores the utility of the node,
+//! while other events, e.g. descending into a child, have no utility.
+//! Use `Trace::rebuild` to reconstruct the tree from events,
+//! or `Trace::rebuild_to` to step through the construction.
+//!
+//! Searches running on separate threads, e.g. `Ai::full_par`, trace every worker,
+//! and the events of workers are appended in order when the workers are finished.
+
+use std::time::{Duration, Instant};
+
+use crate::Node;
+use crate::utility::Utility;
+
+/// An action executed during search.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Event<A, U = f64> {
+    /// The path of actions to the node where the action was executed.
+    pub path: Vec<A>,
+    /// The executed action.
+    pub action: A,
+    /// The utility of the created node, `None` if no node was created.
+    pub utility: Option<U>,
+    /// The time since tracing started.
+    pub time: Duration,
+}
+
+/// Records events of search.
+pub struct Trace<A, U = f64> {
+    /// The recorded events, in order of execution.
+    pub events: Vec<Event<A, U>>,
+    /// The actions executed that are not undone yet.
+    path: Vec<A>,
+    /// Clones actions, such that recording does not require `A: Clone` in every algorithm.
+    clone: fn(&A) -> A,
+    /// The time when tracing started.
+    start: Instant,
+}
+
+impl<A: Clone, U> Trace<A, U> {
+    /// Creates a new empty trace.
+    pub fn new() -> Trace<A, U> {
+        Trace {
+            events: vec![],
+            path: vec![],
+            clone: A::clone,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl<A: Clone, U> Default for Trace<A, U> {
+    fn default() -> Trace<A, U> {Trace::new()}
+}
+
+impl<A, U: Utility> Trace<A, U> {
+    /// Records that an action was executed.
+    pub(crate) fn execute(&mut self, a: &A) {
+        let path = self.path.iter().map(self.clone).collect();
+        self.events.push(Event {
+            path,
+            action: (self.clone)(a),
+            utility: None,
+            time: self.start.elapsed(),
+        });
+        self.path.push((self.clone)(a));
+    }
+
+    /// Records the utility of a node created by the last executed action.
+    pub(crate) fn create(&mut self, utility: U) {
+        if let Some(event) = self.events.last_mut() {
+            event.utility = Some(utility);
+        }
+    }
+
+    /// Records that changes of the last executed action were undone.
+    pub(crate) fn undo(&mut self) {
+        self.path.pop();
+    }
+
+    /// Returns an empty trace continuing from the current path, e.g. for a worker thread.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn fork(&self) -> Trace<A, U> {
+        Trace {
+            events: vec![],
+            path: self.path.iter().map(self.clone).collect(),
+            clone: self.clone,
+            start: self.start,
+        }
+    }
+
+    /// Appends the events of a forked trace.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn join(&mut self, other: Trace<A, U>) {
+        self.events.extend(other.events);
+    }
+
+    /// Rebuilds the tree from all events.
+    ///
+    /// The tree contains every node that was created,
+    /// including nodes that were later pruned from the searched tree.
+    /// Node data is not recorded, so it is replaced by the index of the event creating the node.
+    pub fn rebuild(&self) -> Node<usize, A, U>
+        where A: PartialEq
+    {
+        self.rebuild_to(self.events.len())
+    }
+
+    /// Rebuilds the tree from the first `n` events.
+    ///
+    /// The root has the data `n`, since it is not created by any event.
+    pub fn rebuild_to(&self, n: usize) -> Node<usize, A, U>
+        where A: PartialEq
+    {
+        let mut root: Node<usize, A, U> = Node::root(n);
+        for (i, event) in self.events.iter().enumerate().take(n) {
+            let utility = match event.utility {
+                Some(utility) => utility,
+                None => continue,
+            };
+            // Nodes on the path are updated with new maximum utility.
+            let mut node = &mut root;
+            let mut found = true;
+            for a in &event.path {
+                if node.max.is_unknown() || utility > node.max {node.max = utility};
+                match node.children.iter().position(|(b, _)| b == a) {
+                    Some(j) => node = &mut node.children[j].1,
+                    None => {found = false; break}
+                }
+            }
+            if !found {continue};
+            if node.max.is_unknown() || utility > node.max {node.max = utility};
+            node.children.push(((self.clone)(&event.action), Node {
+                max: utility,
+                data: i,
+                children: vec![],
+                partial: false,
+            }));
+        }
+        root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn rebuilds_tree() {
+        let mut ai = line_ai(2);
+        ai.trace = Some(Trace::new());
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let trace = ai.trace.take().unwrap();
+        assert!(trace.path.is_empty());
+
+        let tree = trace.rebuild();
+        assert_eq!(tree.descendants(), root.descendants());
+        assert_eq!(tree.max, root.max);
+        let path = root.optimal_path();
+        assert_eq!(tree.optimal_path(), path);
+        let event = &trace.events[tree.get(&path).unwrap().data];
+        assert_eq!(event.utility, Some(root.max));
+        assert_eq!(event.path.len() + 1, path.len());
+
+        // Step through construction.
+        let first = trace.events.iter().position(|e| e.utility.is_some()).unwrap();
+        assert_eq!(trace.rebuild_to(first + 1).descendants(), 1);
+    }
+}
@@ -0,0 +1,153 @@
+//! Export of training data from maximum trees.
+//!
+//! A completed search labels every expanded node with the best achievable utility
+//! (maximum utility) and the best action (the optimal child).
+//! These labels can be used to train a learned heuristic or policy,
+//! e.g. for `ValueEstimator` or `Ai::rollout`, distilling a slow search into a fast model.
+//!
+//! Node data is turned into features by a user-supplied featurizer.
+//! Since the context is in the state of root after search,
+//! the features must be computed from node data alone.
+//!
+//! Leaves are skipped, since their maximum utility is only their own utility
+//! and they have no best action.
+
+use std::fmt::Write as FmtWrite;
+use std::io;
+
+use crate::Node;
+use crate::utility::Utility;
+
+/// A training sample of an expanded node.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sample<A> {
+    /// The path of child indices from root.
+    pub path: Vec<usize>,
+    /// The features of node data.
+    pub features: Vec<f64>,
+    /// The best achievable utility, which is the maximum utility of the node.
+    pub value: f64,
+    /// The best action, or `None` if the node is terminal.
+    pub action: Option<A>,
+}
+
+impl<T, A, U: Utility> Node<T, A, U> {
+    /// Returns training samples of expanded nodes in pre-order.
+    pub fn training_samples(&self, featurize: impl Fn(&T) -> Vec<f64>) -> Vec<Sample<A>>
+        where A: Clone
+    {
+        self.samples(featurize).map(|s| Sample {
+            path: s.path,
+            features: s.features,
+            value: s.value,
+            action: s.action.cloned(),
+        }).collect()
+    }
+
+    /// Writes training samples of expanded nodes as CSV lines, returning the number of samples.
+    ///
+    /// Each line has the features, followed by value and action.
+    /// No header is written, since the number of features is decided by the featurizer.
+    /// The action is empty for terminal nodes.
+    pub fn write_training_csv<W: io::Write>(
+        &self,
+        w: &mut W,
+        featurize: impl Fn(&T) -> Vec<f64>,
+        label_action: impl Fn(&A) -> String,
+    ) -> io::Result<usize> {
+        let mut n = 0;
+        for sample in self.samples(featurize) {
+            let mut line = String::new();
+            for x in &sample.features {
+                let _ = write!(line, "{},", x);
+            }
+            let action = sample.action.map(|a| csv_field(&label_action(a))).unwrap_or_default();
+            writeln!(w, "{}{},{}", line, sample.value, action)?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// Writes training samples of expanded nodes as JSON lines, returning the number of samples.
+    ///
+    /// Each line is an object with `features`, `value` and `action`,
+    /// where the action is `null` for terminal nodes.
+    /// Infinite values are written as `null`, since JSON does not support them.
+    pub fn write_training_jsonl<W: io::Write>(
+        &self,
+        w: &mut W,
+        featurize: impl Fn(&T) -> Vec<f64>,
+        label_action: impl Fn(&A) -> String,
+    ) -> io::Result<usize> {
+        let mut n = 0;
+        for sample in self.samples(featurize) {
+            let features: Vec<String> = sample.features.iter().map(|&x| json_number(x)).collect();
+            let action = sample.action.map(|a| json_string(&label_action(a)))
+                .unwrap_or_else(|| "null".into());
+            writeln!(w, "{{\"features\": [{}], \"value\": {}, \"action\": {}}}",
+                features.join(", "), json_number(sample.value), action)?;
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    fn samples<'a>(&'a self, featurize: impl Fn(&T) -> Vec<f64> + 'a) -> impl Iterator<Item = Sample<&'a A>> + 'a {
+        self.iter_preorder().filter(|(_, node)| !node.children.is_empty()).map(move |(path, node)| Sample {
+            path,
+            features: featurize(&node.data),
+            value: node.max.to_f64(),
+            action: node.optimal().map(|i| &node.children[i].0),
+        })
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {format!("\"{}\"", s.replace('"', "\"\""))} else {s.into()}
+}
+
+fn json_number(x: f64) -> String {
+    if x.is_finite() {format!("{:?}", x)} else {"null".into()}
+}
+
+fn json_string(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            c if (c as u32) < 0x20 => {let _ = write!(res, "\\u{:04x}", c as u32);}
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn exports_samples() {
+        let mut ai = line_ai(1);
+        ai.settings.eps_depth = 0.0;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let samples = root.training_samples(|&d| vec![d as f64]);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[0], Sample {path: vec![], features: vec![0.0], value: -1.0, action: Some(1)});
+
+        let mut csv = vec![];
+        assert_eq!(root.write_training_csv(&mut csv, |&d| vec![d as f64], |a| format!("{}", a)).unwrap(), 3);
+        assert_eq!(String::from_utf8(csv).unwrap().lines().next(), Some("0,-1,1"));
+        let mut jsonl = vec![];
+        root.write_training_jsonl(&mut jsonl, |_| vec![], |a| format!("\"{}\"", a)).unwrap();
+        assert_eq!(String::from_utf8(jsonl).unwrap().lines().next(),
+            Some("{\"features\": [], \"value\": -1.0, \"action\": \"\\\"1\\\"\"}"));
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+    }
+}
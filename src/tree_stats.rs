@@ -0,0 +1,91 @@
+//! Statistics of maximum trees.
+
+use crate::Node;
+
+/// Stores statistics of a tree.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TreeStats {
+    /// The number of nodes, including root.
+    pub node_count: usize,
+    /// The number of leaves.
+    pub leaf_count: usize,
+    /// The largest depth of any node, where root has depth `0`.
+    pub max_depth: usize,
+    /// The average number of children of nodes that have children.
+    ///
+    /// This is `0` when root has no children.
+    pub branching: f64,
+    /// The number of terminal nodes.
+    ///
+    /// A terminal node has no children with equal or greater utility.
+    pub terminal_count: usize,
+    /// The lowest utility of leaves.
+    pub min_utility: f64,
+    /// The highest utility of leaves.
+    pub max_utility: f64,
+    /// The mean utility of leaves.
+    pub mean_utility: f64,
+}
+
+impl<T, A> Node<T, A> {
+    /// Computes statistics of the tree.
+    ///
+    /// Utility is computed from leaves only,
+    /// since the maximum utility of a leaf is the utility of the leaf itself.
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            node_count: 0,
+            leaf_count: 0,
+            max_depth: 0,
+            branching: 0.0,
+            terminal_count: 0,
+            min_utility: f64::INFINITY,
+            max_utility: f64::NEG_INFINITY,
+            mean_utility: 0.0,
+        };
+        let mut internal = 0;
+        let mut sum = 0.0;
+        for (path, node) in self.iter_preorder() {
+            stats.node_count += 1;
+            if path.len() > stats.max_depth {stats.max_depth = path.len()};
+            if node.terminal() {stats.terminal_count += 1};
+            if node.children.is_empty() {
+                stats.leaf_count += 1;
+                stats.min_utility = stats.min_utility.min(node.max);
+                stats.max_utility = stats.max_utility.max(node.max);
+                sum += node.max;
+            } else {
+                internal += 1;
+                stats.branching += node.children.len() as f64;
+            }
+        }
+        if internal > 0 {stats.branching /= internal as f64};
+        stats.mean_utility = sum / stats.leaf_count as f64;
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn full_tree_stats() {
+        let mut ai = line_ai(1);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        let stats = root.stats();
+        assert_eq!(stats.node_count, 7);
+        assert_eq!(stats.leaf_count, 4);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.branching, 2.0);
+        // The leaves are terminal and every other node has an optimal child.
+        assert_eq!(stats.terminal_count, 4);
+        assert_eq!(stats.min_utility, -5.0 - 0.0002);
+        assert_eq!(stats.max_utility, -1.0 - 0.0002);
+        assert!((stats.mean_utility + 3.0002).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,151 @@
+//! Verification that undo restores the context.
+//!
+//! A subtly broken `Ai::undo` silently corrupts the whole search,
+//! since later utility is computed from a context that is not in the expected state.
+//! When `Ai::undo_check` is set, a fingerprint of the context is computed
+//! before executing an action and compared after undoing it.
+//! This works with every algorithm that undoes changes,
+//! e.g. `Ai::sub_breadth`, `Ai::greedy` and `Ai::full`.
+//!
+//! Mismatches are recorded as an `UndoViolation` with the offending action,
+//! or panic immediately with `UndoCheck::panicking`.
+//! Actions committed to context by `Ai::update` or `Ai::realtime` are not checked,
+//! since they are not undone.
+//! Searches running on separate threads, e.g. `Ai::full_par`, check every worker.
+//!
+//! Fingerprints are computed twice for every executed action,
+//! so this is intended for debugging.
+
+use std::fmt::Debug;
+
+/// An action whose changes to context were not restored by undo.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UndoViolation<A> {
+    /// The executed action.
+    pub action: A,
+    /// The fingerprint of context before executing the action.
+    pub before: u64,
+    /// The fingerprint of context after undoing the action.
+    pub after: u64,
+}
+
+/// Checks that undo restores the context.
+pub struct UndoCheck<A, C> {
+    /// The recorded violations, in order of undoing.
+    pub violations: Vec<UndoViolation<A>>,
+    /// Computes a fingerprint of the context, e.g. a hash.
+    fingerprint: fn(&C) -> u64,
+    /// The actions executed that are not undone yet, with fingerprints before executing.
+    stack: Vec<(u64, A)>,
+    /// Clones actions, such that checking does not require `A: Clone` in every algorithm.
+    clone: fn(&A) -> A,
+    /// Formats the offending action when panicking.
+    panic: Option<fn(&A) -> String>,
+}
+
+impl<A: Clone, C> UndoCheck<A, C> {
+    /// Creates a new check with a fingerprint of context.
+    pub fn new(fingerprint: fn(&C) -> u64) -> UndoCheck<A, C> {
+        UndoCheck {
+            violations: vec![],
+            fingerprint,
+            stack: vec![],
+            clone: A::clone,
+            panic: None,
+        }
+    }
+
+    /// Panics on the first violation instead of recording it.
+    pub fn panicking(mut self) -> Self
+        where A: Debug
+    {
+        self.panic = Some(|a| format!("{:?}", a));
+        self
+    }
+}
+
+impl<A, C> UndoCheck<A, C> {
+    /// Returns `true` if undo restored the context of every checked action.
+    pub fn is_ok(&self) -> bool {self.violations.is_empty()}
+
+    /// Computes a fingerprint of context before executing an action.
+    pub(crate) fn before(&self, ctx: &C) -> u64 {(self.fingerprint)(ctx)}
+
+    /// Records that an action was executed.
+    pub(crate) fn execute(&mut self, before: u64, a: &A) {
+        self.stack.push((before, (self.clone)(a)));
+    }
+
+    /// Records that an action was committed to context and will not be undone.
+    pub(crate) fn commit(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Checks that changes of the last executed action were undone.
+    pub(crate) fn undo(&mut self, ctx: &C) {
+        if let Some((before, action)) = self.stack.pop() {
+            let after = (self.fingerprint)(ctx);
+            if after == before {return};
+            if let Some(f) = self.panic {
+                panic!("undo did not restore context after executing action `{}`", f(&action));
+            }
+            self.violations.push(UndoViolation {action, before, after});
+        }
+    }
+
+    /// Returns an empty check without executed actions, e.g. for a worker thread.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn fork(&self) -> UndoCheck<A, C> {
+        UndoCheck {
+            violations: vec![],
+            fingerprint: self.fingerprint,
+            stack: vec![],
+            clone: self.clone,
+            panic: self.panic,
+        }
+    }
+
+    /// Appends the violations of a forked check.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn join(&mut self, other: UndoCheck<A, C>) {
+        self.violations.extend(other.violations);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn detects_broken_undo() {
+        let mut ai = line_ai(2);
+        ai.undo_check = Some(UndoCheck::new(|pos: &i32| *pos as u64));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert!(ai.undo_check.as_ref().unwrap().is_ok());
+
+        // Moving right is not undone.
+        ai.undo = Box::new(|a, pos| if *a < 0 {*pos -= a});
+        ai.undo_check = Some(UndoCheck::new(|pos: &i32| *pos as u64));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.greedy(&mut root, 0, &mut pos);
+        let check = ai.undo_check.as_ref().unwrap();
+        assert!(!check.is_ok());
+        assert!(check.violations.iter().all(|v| v.action == 1 && v.after != v.before));
+    }
+
+    #[test]
+    #[should_panic(expected = "undo did not restore context after executing action `1`")]
+    fn panics_on_broken_undo() {
+        let mut ai = line_ai(1);
+        ai.undo = Box::new(|a, pos| if *a < 0 {*pos -= a});
+        ai.undo_check = Some(UndoCheck::new(|pos: &i32| *pos as u64).panicking());
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+    }
+}
@@ -0,0 +1,116 @@
+//! Strategies for restoring the context after executing actions.
+//!
+//! By default, changes to the context are undone by `Ai::undo`,
+//! which requires storing the data to rollback delta changes in node data.
+//! Writing a correct undo is hard for complex contexts,
+//! so with `UndoStrategy::Clone`, a snapshot of the context is cloned before executing an action
+//! and restored instead of calling undo.
+//! For small contexts, cloning is simpler and often faster.
+//!
+//! Use `UndoStrategy::cloning` to clone contexts that implement `Clone`.
+
+use std::sync::Mutex;
+
+/// Decides how to restore the context after executing actions.
+#[derive(Default)]
+pub enum UndoStrategy<C> {
+    /// Undoes delta changes by calling `Ai::undo` (default).
+    #[default]
+    Delta,
+    /// Restores snapshots of the context cloned before executing actions.
+    ///
+    /// `Ai::undo` is not called.
+    Clone(Snapshots<C>),
+}
+
+impl<C: Clone> UndoStrategy<C> {
+    /// Creates a strategy restoring cloned snapshots of the context.
+    pub fn cloning() -> UndoStrategy<C> {
+        UndoStrategy::Clone(Snapshots {
+            stack: Mutex::new(vec![]),
+            clone: C::clone,
+        })
+    }
+}
+
+impl<C> UndoStrategy<C> {
+    /// Returns a snapshot of the context before executing an action, when cloning.
+    pub(crate) fn snapshot(&self, ctx: &C) -> Option<C> {
+        match self {
+            UndoStrategy::Delta => None,
+            UndoStrategy::Clone(snapshots) => Some((snapshots.clone)(ctx)),
+        }
+    }
+
+    /// Stores a snapshot of an executed action.
+    pub(crate) fn push(&mut self, snapshot: C) {
+        if let UndoStrategy::Clone(snapshots) = self {
+            snapshots.stack.get_mut().unwrap().push(snapshot);
+        }
+    }
+
+    /// Returns a strategy without snapshots, e.g. for a worker thread.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn fork(&self) -> UndoStrategy<C> {
+        match self {
+            UndoStrategy::Delta => UndoStrategy::Delta,
+            UndoStrategy::Clone(snapshots) => UndoStrategy::Clone(Snapshots {
+                stack: Mutex::new(vec![]),
+                clone: snapshots.clone,
+            }),
+        }
+    }
+
+    /// Removes the snapshot of the last executed action.
+    pub(crate) fn pop(&mut self) -> Option<C> {
+        match self {
+            UndoStrategy::Delta => None,
+            UndoStrategy::Clone(snapshots) => snapshots.stack.get_mut().unwrap().pop(),
+        }
+    }
+}
+
+/// Stores snapshots of the context, see `UndoStrategy::Clone`.
+pub struct Snapshots<C> {
+    /// The snapshots of actions executed that are not undone yet.
+    ///
+    /// This is behind a mutex, such that the AI setup can be shared between threads
+    /// without requiring `C: Sync`.
+    stack: Mutex<Vec<C>>,
+    /// Clones the context, such that the AI setup does not require `C: Clone`.
+    clone: fn(&C) -> C,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Ai, Node};
+    use crate::tests::line_ai;
+
+    #[test]
+    fn restores_cloned_context() {
+        let mut ai: Ai<(), i32, i32> = Ai::builder()
+            .utility(|_, pos: &i32| -(*pos - 3).abs() as f64)
+            .actions(|_, _| vec![-1, 1])
+            .execute(|_, a, pos| {*pos += a; Ok(())})
+            .undo_strategy(UndoStrategy::cloning())
+            .max_depth(4)
+            .eps_depth(0.0001)
+            .build();
+        let mut root = Node::root(());
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+
+        let mut delta = line_ai(4);
+        let mut delta_root = Node::root(0);
+        delta.full(&mut delta_root, 0, &mut pos);
+        assert_eq!(root.max, delta_root.max);
+        assert_eq!(root.optimal_path(), delta_root.optimal_path());
+
+        // Committed actions are not restored.
+        ai.update(&root, &mut pos);
+        assert_eq!(pos, 1);
+        assert!(ai.undo_strategy.pop().is_none());
+    }
+}
@@ -0,0 +1,222 @@
+//! Uniform-cost search (Dijkstra) for accumulated costs.
+//!
+//! Nodes are expanded in order of lowest accumulated cost,
+//! using a priority queue over the frontier of unexpanded nodes.
+//! The cost of each step is computed by `Ai::cost`.
+//! Without a cost function, every step costs `1`.
+//!
+//! The accumulated cost is subtracted from the utility of each node, see `Utility::penalize`,
+//! such that the optimal path is the path with the best trade-off
+//! between final utility and accumulated cost.
+//! For shortest path problems, use a utility that is constant
+//! for every goal and lower for every other state.
+//!
+//! When `Ai::state_hash` is set, each state is expanded only once,
+//! which is the first time it is reached with lowest accumulated cost.
+//!
+//! Weighted A* (`Ai::weighted_astar`) expands nodes in order of `g + w * h`,
+//! where `g` is the accumulated cost and `h` is the estimated remaining cost to reach a goal.
+//! Since `Ai::heuristic` estimates utility that can be gained,
+//! the remaining cost is estimated as the negative heuristic, e.g. the negative distance to goal.
+//! The weight `w` is set by `AiSettings::astar_weight`.
+//! With a weight above `1`, fewer nodes are expanded,
+//! at the price of a plan that costs up to `w` times the optimal plan,
+//! when the heuristic never overestimates the remaining cost.
+
+use std::collections::{BinaryHeap, HashSet};
+
+use crate::{Ai, Expansion, Node};
+use crate::best_first::Frontier;
+use crate::depth_first::NodeStorage;
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Expands nodes in order of lowest accumulated cost.
+    ///
+    /// Stops after the specified number of expansions,
+    /// when there are no more nodes to expand,
+    /// or when a limit of memory or time is exceeded.
+    /// Nodes deeper than `AiSettings::max_depth` are not expanded.
+    ///
+    /// The context is restored for every expanded node by replaying actions from root.
+    pub fn uniform_cost(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C, expansions: usize)
+        where A: Clone
+    {
+        self.cost_search(root, depth, ctx, expansions, None);
+    }
+
+    /// Expands nodes in order of lowest accumulated cost plus weighted heuristic,
+    /// until a goal is expanded, see `Ai::is_goal`.
+    ///
+    /// Returns the accumulated cost and path of the goal,
+    /// which costs at most `AiSettings::astar_weight` times the cost of the optimal plan
+    /// when the heuristic never overestimates the remaining cost.
+    /// Returns `None` when no goal is found within the number of expansions,
+    /// or when a limit of memory or time is exceeded.
+    /// Nodes deeper than `AiSettings::max_depth` are not expanded.
+    pub fn weighted_astar(&mut self, root: &mut Node<T, A, U>, depth: usize, ctx: &mut C, expansions: usize)
+        -> Option<(f64, Vec<usize>)>
+        where A: Clone
+    {
+        let weight = self.settings.astar_weight;
+        self.cost_search(root, depth, ctx, expansions, Some(weight))
+    }
+
+    /// Runs uniform-cost search, or weighted A* when a weight is given.
+    fn cost_search(
+        &mut self,
+        root: &mut Node<T, A, U>,
+        depth: usize,
+        ctx: &mut C,
+        expansions: usize,
+        weight: Option<f64>,
+    ) -> Option<(f64, Vec<usize>)>
+        where A: Clone
+    {
+        if root.max.is_unknown() {
+            root.max = self.utility_with_settings(&root.data, depth, ctx);
+        }
+
+        let started = self.start_timer();
+        let mut closed = HashSet::new();
+        let mut goal = None;
+        // The priority is the negative accumulated cost plus weighted heuristic.
+        let mut queue = BinaryHeap::new();
+        queue.push(Frontier {priority: 0.0, cost: 0.0, depth, path: vec![]});
+        for _ in 0..expansions {
+            let f = match queue.pop() {
+                Some(f) => f,
+                None => break,
+            };
+
+            let n = self.replay(root, &f.path, ctx);
+            if n == f.path.len() {
+                let node = root.node_mut(&f.path);
+                if weight.is_some() && self.goal_reached(&node.data, ctx) {
+                    goal = Some((f.cost, f.path.clone()));
+                }
+                let visited = if let Some(state_hash) = &self.state_hash {
+                    !closed.insert(state_hash(&node.data, ctx))
+                } else {false};
+                if goal.is_none() && !visited {
+                    let g = f.cost;
+                    let mut expansion = CostExpansion {
+                        g,
+                        heuristic: weight.is_some(),
+                        step: 0.0,
+                        steps: vec![],
+                    };
+                    self.sub_breadth_with(&mut NodeStorage::new(node), f.depth, ctx, &mut expansion);
+                    if f.depth < self.settings.max_depth {
+                        for (i, (cost, h)) in expansion.steps.into_iter().enumerate() {
+                            let mut path = f.path.clone();
+                            path.push(i);
+                            let cost = g + cost;
+                            let priority = -cost + weight.unwrap_or(0.0) * h;
+                            queue.push(Frontier {priority, cost, depth: f.depth + 1, path});
+                        }
+                    }
+                }
+            }
+            self.rollback(root, &f.path[..n], ctx);
+
+            if goal.is_some() || self.limit_exceeded() {break};
+        }
+
+        // Update maximum utility since children are changed.
+        root.update_max();
+        self.stop_timer(started);
+        goal
+    }
+}
+
+/// Expansion callbacks of `Ai::cost_search`, subtracting accumulated cost from utility.
+struct CostExpansion {
+    /// The accumulated cost of the expanded node.
+    g: f64,
+    /// Whether to compute the heuristic of children.
+    heuristic: bool,
+    /// The cost of the action being executed.
+    step: f64,
+    /// The cost of each step, with the heuristic of the child when given.
+    steps: Vec<(f64, f64)>,
+}
+
+impl<T, A, C, U: Utility, E> Expansion<T, A, C, U, E> for CostExpansion {
+    fn action(&mut self, ai: &Ai<T, A, C, U, E>, data: &T, a: &A, ctx: &C) {
+        self.step = ai.cost.as_ref().map(|f| f(data, a, ctx)).unwrap_or(1.0);
+    }
+
+    fn child(&mut self, ai: &Ai<T, A, C, U, E>, data: &T, ctx: &C, utility: &mut U) -> bool {
+        *utility = utility.penalize(self.g + self.step);
+        let h = match &ai.heuristic {
+            Some(f) if self.heuristic => f(data, ctx),
+            _ => 0.0,
+        };
+        self.steps.push((self.step, h));
+        true
+    }
+
+    fn retain(&mut self, keep: &[bool]) {
+        let mut i = 0;
+        self.steps.retain(|_| {i += 1; keep[i - 1]});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn shortest_path() {
+        let mut ai = line_ai(10);
+        ai.utility = Box::new(|_, pos| if *pos == 3 {10.0} else {0.0});
+        ai.state_hash = Some(Box::new(|_, pos| *pos as u64));
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.uniform_cost(&mut root, 0, &mut pos, 100);
+        assert_eq!(pos, 0);
+        assert_eq!(root.optimal_path().len(), 3);
+        assert!((root.max - 6.9997).abs() < 1e-9);
+    }
+
+    #[test]
+    fn weighted_astar() {
+        let mut ai = line_ai(10);
+        ai.utility = Box::new(|_, pos| if *pos == 3 {10.0} else {0.0});
+        ai.state_hash = Some(Box::new(|_, pos| *pos as u64));
+        ai.is_goal = Some(Box::new(|_, pos| *pos == 3));
+        ai.heuristic = Some(Box::new(|_, pos| -(*pos - 3).abs() as f64));
+        ai.settings.analysis = true;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        let (cost, path) = ai.weighted_astar(&mut root, 0, &mut pos, 100).unwrap();
+        assert_eq!(pos, 0);
+        assert_eq!(cost, 3.0);
+        assert_eq!(path, root.optimal_path());
+        let astar = ai.analysis.node_count;
+
+        ai.heuristic = None;
+        ai.analysis.node_count = 0;
+        let mut root = Node::root(0);
+        assert_eq!(ai.weighted_astar(&mut root, 0, &mut pos, 100).unwrap().0, 3.0);
+        assert!(astar < ai.analysis.node_count);
+    }
+
+    #[test]
+    fn uses_expansion_settings() {
+        let mut ai = line_ai(10);
+        // Expand moving right first.
+        ai.order = Some(Box::new(|_, _, a| *a as f64));
+        ai.settings.max_nodes = Some(1);
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.uniform_cost(&mut root, 0, &mut pos, 100);
+        assert_eq!(pos, 0);
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.children[0].0, 1);
+        assert!(root.partial);
+    }
+}
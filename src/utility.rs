@@ -0,0 +1,271 @@
+//! Generic utility types.
+//!
+//! By default, utility is `f64`.
+//! Other types can be used for exact comparisons, e.g. ordered integers,
+//! by implementing the `Utility` trait.
+//!
+//! Algorithms that only compare utility work with any utility type.
+//! This includes the core algorithms (`full`, `greedy`, `beam`, `iterative_deepening` etc.),
+//! and e.g. `best_first`, `uniform_cost`, `minimax`, `tabu`, `realtime` and `portfolio`.
+//! Algorithms that compute with utility require `f64`,
+//! e.g. expected values in `expectimax`, averages in `mcts` and `evaluate_policy`,
+//! or differences in `simulated_annealing` and `softmax`.
+//!
+//! For strict priorities between objectives, e.g. first never crash, then minimize fuel,
+//! use `LexUtility`, which compares objectives lexicographically.
+
+/// Implemented by types that can be used as utility.
+pub trait Utility: PartialOrd + Copy {
+    /// Returns the value of a node that has not been evaluated yet.
+    ///
+    /// This is used by `Node::root`.
+    fn unknown() -> Self;
+    /// Returns `true` if the value is unknown.
+    fn is_unknown(&self) -> bool;
+    /// Discounts utility proportional to depth, see `AiSettings::eps_depth`.
+    fn discount(self, eps_depth: f64, depth: usize) -> Self;
+    /// Converts utility to `f64`, e.g. to compare with thresholds in settings.
+    ///
+    /// This is used by `AiSettings::target_utility` and `AiSettings::min_gain`.
+    fn to_f64(self) -> f64;
+    /// Clamps utility to a range, see `AiSettings::utility_clamp`.
+    fn clamp_to(self, min: f64, max: f64) -> Self;
+    /// Subtracts a penalty from utility, see `Ai::impact`.
+    ///
+    /// This also subtracts accumulated costs in `Ai::uniform_cost`,
+    /// and adds the heuristic as a negative penalty in `Ai::best_first`.
+    fn penalize(self, penalty: f64) -> Self;
+}
+
+impl Utility for f64 {
+    fn unknown() -> f64 {f64::NAN}
+    fn is_unknown(&self) -> bool {self.is_nan()}
+    fn discount(self, eps_depth: f64, depth: usize) -> f64 {
+        self - eps_depth * depth as f64
+    }
+    fn to_f64(self) -> f64 {self}
+    fn clamp_to(self, min: f64, max: f64) -> f64 {
+        if self < min {min} else if self > max {max} else {self}
+    }
+    fn penalize(self, penalty: f64) -> f64 {self - penalty}
+}
+
+impl Utility for f32 {
+    fn unknown() -> f32 {f32::NAN}
+    fn is_unknown(&self) -> bool {self.is_nan()}
+    fn discount(self, eps_depth: f64, depth: usize) -> f32 {
+        self - (eps_depth * depth as f64) as f32
+    }
+    fn to_f64(self) -> f64 {self as f64}
+    fn clamp_to(self, min: f64, max: f64) -> f32 {
+        if self < min as f32 {min as f32} else if self > max as f32 {max as f32} else {self}
+    }
+    fn penalize(self, penalty: f64) -> f32 {self - penalty as f32}
+}
+
+macro_rules! integer_utility {
+    ($($t:ty),*) => {$(
+        /// The minimum value is used as unknown.
+        /// Discounting is not supported, since `AiSettings::eps_depth` is tiny.
+        /// Penalties are rounded to the nearest integer.
+        impl Utility for $t {
+            fn unknown() -> $t {<$t>::MIN}
+            fn is_unknown(&self) -> bool {*self == <$t>::MIN}
+            fn discount(self, _eps_depth: f64, _depth: usize) -> $t {self}
+            fn to_f64(self) -> f64 {self as f64}
+            fn clamp_to(self, min: f64, max: f64) -> $t {
+                let (min, max) = (min.ceil() as $t, max.floor() as $t);
+                if self < min {min} else if self > max {max} else {self}
+            }
+            fn penalize(self, penalty: f64) -> $t {
+                self.saturating_sub(penalty.round() as $t)
+            }
+        }
+    )*}
+}
+
+integer_utility!(i8, i16, i32, i64, i128, isize);
+
+/// Utility with objectives compared lexicographically, in order of priority.
+///
+/// A later objective is only compared when all earlier objectives are equal.
+/// This avoids encoding strict priorities as weighted sums.
+/// Discounting by depth is applied to the last objective,
+/// such that fewer steps are only preferred when everything else is equal.
+/// Penalties are also applied to the last objective.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct LexUtility<const N: usize>(pub [f64; N]);
+
+impl<const N: usize> Utility for LexUtility<N> {
+    fn unknown() -> LexUtility<N> {LexUtility([f64::NAN; N])}
+    fn is_unknown(&self) -> bool {self.0.iter().any(|x| x.is_nan())}
+    fn discount(mut self, eps_depth: f64, depth: usize) -> LexUtility<N> {
+        if let Some(x) = self.0.last_mut() {*x -= eps_depth * depth as f64};
+        self
+    }
+    /// Returns the objective with highest priority.
+    fn to_f64(self) -> f64 {self.0.first().copied().unwrap_or(0.0)}
+    fn clamp_to(mut self, min: f64, max: f64) -> LexUtility<N> {
+        for x in &mut self.0 {*x = x.clamp_to(min, max)};
+        self
+    }
+    fn penalize(mut self, penalty: f64) -> LexUtility<N> {
+        if let Some(x) = self.0.last_mut() {*x -= penalty};
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LexUtility, Utility};
+    use crate::{Ai, Node};
+
+    #[test]
+    fn integer_utility() {
+        let mut ai: Ai<i32, i32, i32, i64> = Ai::builder()
+            .utility(|_, pos: &i32| -(*pos as i64 - 3).abs())
+            .actions(|_, _| vec![-1, 1])
+            .execute(|_, a, pos| {*pos += a; Ok(*a)})
+            .undo(|a, pos| *pos -= a)
+            .max_depth(4)
+            .build();
+        let mut root = Node::root(0);
+        assert_eq!(root.max, i64::MIN);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max, 0);
+        // Without depth discount, the optimal path might take a detour.
+        let path = root.optimal_path();
+        let moves: i32 = (1..=path.len()).map(|n| root.get(&path[..n]).unwrap().data).sum();
+        assert_eq!(moves, 3);
+    }
+
+    #[test]
+    fn order_only_algorithms() {
+        use std::collections::HashMap;
+        use crate::lazy::LazyNode;
+        use crate::portfolio::{Portfolio, Strategy};
+
+        let mut ai: Ai<i32, i32, i32, i64> = Ai::builder()
+            .utility(|_, pos: &i32| -(*pos as i64 - 3).abs())
+            .actions(|_, _| vec![-1, 1])
+            .execute(|_, a, pos| {*pos += a; Ok(*a)})
+            .undo(|a, pos| *pos -= a)
+            .state_hash(|_, pos| *pos as u64)
+            .max_depth(4)
+            .build();
+        let mut pos = 0;
+        let mut root = Node::root(0);
+        ai.tabu(&mut root, 0, &mut pos, 4);
+        assert_eq!(root.max, 0);
+        let mut root = Node::root(0);
+        ai.best_first(&mut root, 0, &mut pos, 8);
+        assert_eq!(root.max, 0);
+        let mut root = Node::root(0);
+        ai.uniform_cost(&mut root, 0, &mut pos, 8);
+        // Every step costs as much as it gains.
+        assert_eq!(root.max, -3);
+        let mut root = Node::root(0);
+        ai.minimax(&mut root, 0, &mut pos);
+        assert!(!root.max.is_unknown());
+        let mut root = LazyNode::root(0);
+        ai.lazy_greedy(&mut root, 0, &mut pos);
+        assert_eq!(root.max, 0);
+        let mut portfolio = Portfolio::new();
+        portfolio.add("greedy", Strategy::Greedy);
+        let (root, reports) = ai.portfolio(&portfolio, || Node::root(0), 0, &pos);
+        assert_eq!(root.max, reports[0].max);
+        assert_eq!(pos, 0);
+
+        ai.settings.max_depth = 1;
+        let mut values = HashMap::new();
+        let (actions, data) = ai.realtime(0, &mut pos, 3, &mut values);
+        assert_eq!(actions, vec![1, 1, 1]);
+        assert_eq!(data, 1);
+        assert_eq!(pos, 3);
+    }
+
+    #[test]
+    fn custom_utility() {
+        #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+        struct Score(u32);
+
+        impl Utility for Score {
+            fn unknown() -> Score {Score(0)}
+            fn is_unknown(&self) -> bool {self.0 == 0}
+            fn discount(self, _eps_depth: f64, _depth: usize) -> Score {self}
+            fn to_f64(self) -> f64 {self.0 as f64}
+            fn clamp_to(self, min: f64, max: f64) -> Score {
+                Score(self.0.clamp(min.ceil() as u32, max.floor() as u32))
+            }
+            fn penalize(self, penalty: f64) -> Score {
+                Score(self.0.saturating_sub(penalty.round() as u32))
+            }
+        }
+
+        assert_eq!(Score(3).clamp_to(0.0, 1.0), Score(1));
+        assert_eq!(Score(3).penalize(1.0), Score(2));
+
+        let mut ai: Ai<i32, i32, i32, Score> = Ai::builder()
+            .utility(|_, pos: &i32| Score(10 - (*pos - 3).unsigned_abs()))
+            .actions(|_, _| vec![-1, 1])
+            .execute(|_, a, pos| {*pos += a; Ok(*a)})
+            .undo(|a, pos| *pos -= a)
+            .max_depth(3)
+            .build();
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.max, Score(10));
+
+        // Every step gains `1`, so greedy search continues until the target is reached.
+        ai.settings.min_gain = 1.0;
+        ai.settings.target_utility = Some(9.0);
+        let mut root = Node::root(0);
+        ai.greedy(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max, Score(9));
+        assert_eq!(root.optimal_path().len(), 2);
+        assert!(ai.analysis.target_reached);
+
+        ai.settings.target_utility = None;
+        ai.settings.utility_clamp = Some((0.0, 8.0));
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.max, Score(8));
+
+        ai.settings.utility_clamp = None;
+        ai.impact = Some(Box::new(|_, _| 1.0));
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.max, Score(9));
+    }
+
+    #[test]
+    fn lexicographic_utility() {
+        assert!(LexUtility([1.0, -5.0]) > LexUtility([0.0, 5.0]));
+        assert!(LexUtility([1.0, 2.0]) > LexUtility([1.0, 1.0]));
+
+        // Never go below zero, then get close to 3.
+        let mut ai: Ai<i32, i32, i32, LexUtility<2>> = Ai::builder()
+            .utility(|_, pos: &i32| LexUtility([
+                if *pos < 0 {-1.0} else {0.0},
+                -(*pos - 3).abs() as f64,
+            ]))
+            .actions(|_, _| vec![-1, 1])
+            .execute(|_, a, pos| {*pos += a; Ok(*a)})
+            .undo(|a, pos| *pos -= a)
+            .max_depth(4)
+            .eps_depth(0.001)
+            .build();
+        let mut root: Node<i32, i32, LexUtility<2>> = Node::root(0);
+        assert!(root.max.is_unknown());
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(pos, 0);
+        assert_eq!(root.max.0[0], 0.0);
+        assert!((root.max.0[1] + 0.003).abs() < 1e-9);
+        assert_eq!(root.optimal_path().len(), 3);
+    }
+}
@@ -0,0 +1,70 @@
+//! Value estimation of leaves.
+//!
+//! A search with limited depth evaluates leaves at the horizon by utility,
+//! which ignores what might happen after the horizon.
+//! A value estimator evaluates these leaves instead,
+//! e.g. by a learned value function or a hand-tuned potential,
+//! without changing the utility of the environment.
+//!
+//! Set `Ai::value_estimator` to use an estimator in `Ai::sub_breadth`,
+//! which evaluates children deeper than `AiSettings::max_depth`.
+//! Since these children are never expanded, this affects e.g. `full`, `greedy` and `beam`.
+//! Closures implement `ValueEstimator`.
+
+use crate::Ai;
+use crate::execute_error::ExecuteError;
+use crate::utility::Utility;
+
+/// Estimates the value of node data and context, used instead of utility at leaves.
+pub trait ValueEstimator<T, C, U = f64> {
+    /// Returns the estimated value.
+    fn estimate(&self, data: &T, ctx: &C) -> U;
+}
+
+impl<T, C, U, F: Fn(&T, &C) -> U> ValueEstimator<T, C, U> for F {
+    fn estimate(&self, data: &T, ctx: &C) -> U {self(data, ctx)}
+}
+
+impl<T, A, C, U: Utility, E: ExecuteError> Ai<T, A, C, U, E> {
+    /// Calculates utility with settings of a child created at `depth`.
+    ///
+    /// Uses `Ai::value_estimator` instead of utility for children deeper than maximum depth.
+    pub(crate) fn child_utility(&self, data: &T, depth: usize, ctx: &C) -> U {
+        match &self.value_estimator {
+            Some(estimator) if depth > self.settings.max_depth =>
+                self.with_settings(estimator.estimate(data, ctx), data, depth, ctx),
+            _ => self.utility_with_settings(data, depth, ctx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Node;
+    use crate::tests::line_ai;
+
+    #[test]
+    fn estimates_leaves() {
+        let mut ai = line_ai(1);
+        ai.settings.eps_depth = 0.0;
+        let mut root = Node::root(0);
+        let mut pos = 0;
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.max, -1.0);
+
+        // A potential that sees the maximum beyond the horizon.
+        ai.value_estimator = Some(Box::new(|_: &i32, pos: &i32| if *pos >= 2 {10.0} else {-5.0}));
+        let mut root = Node::root(0);
+        ai.full(&mut root, 0, &mut pos);
+        assert_eq!(root.max, 10.0);
+        assert_eq!(root.optimal_path().len(), 2);
+        // Children at maximum depth use utility.
+        assert_eq!(root.children[1].1.max, 10.0);
+        assert_eq!(root.children[0].1.max, -4.0);
+
+        let mut root = Node::root(0);
+        ai.greedy(&mut root, 0, &mut pos);
+        assert_eq!(root.max, 10.0);
+        assert_eq!(pos, 0);
+    }
+}